@@ -0,0 +1,536 @@
+use std::{
+    sync::mpsc::{self, Sender},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    bundle::ImportMode,
+    entity::{Command, Reply},
+    filter::{ItemFilter, SortBy},
+    log::{self, Level, LogFilter},
+    setting::ApiSetting,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(60 * 60); // 备份大文件可能很久
+
+/// 启动本地 HTTP 接口，所有请求转换成 Command 交给 command_proc 处理
+///
+/// GET    /api/save-items                                   ?q=&group=&platform=&tag=&tag=&sort=name|last-backup|size
+/// GET    /api/save-items/{id}/backups                      ?q= 按标题、备注和笔记筛选
+/// POST   /api/save-items/{id}/backups                      {"remark": ""}
+/// GET    /api/search                                       ?q= 在所有存档和备份中搜索
+/// POST   /api/save-items/{id}/backups/{backup_id}/restore
+/// POST   /api/save-items/{id}/backups/{backup_id}/restore-file {"path": ""}
+/// GET    /api/save-items/{id}/file-history                 ?path= 包含该文件的所有备份
+/// POST   /api/save-items/{id}/backups/{backup_id}/meta     {"title": "", "notes": "", "rating": 5, "pinned": true, "screenshot": ""}
+/// POST   /api/save-items/{id}/backups/bulk                 {"action": "delete|protect|unprotect|verify|export", "path": "",
+///                                                          "ids": [], "trigger": "Auto", "older_than_days": 30, "protected": false,
+///                                                          "keyword": "", "dry_run": true}
+/// POST   /api/save-items/{id}/backups/{backup_id}/protect
+/// POST   /api/save-items/{id}/backups/{backup_id}/unprotect
+/// DELETE /api/save-items/{id}/backups/{backup_id}
+/// GET    /api/save-items/{id}/history                      git 格式的提交历史
+/// GET    /api/save-items/{id}/backups/{backup_id}/diff
+/// GET    /api/save-items/{id}/backups/{backup_id}/blame    ?file= 为空时列出文件
+/// GET    /api/save-items/{id}/changes                      设置和监听路径的修改记录
+/// POST   /api/save-items/{id}/changes/{change_id}/revert
+/// GET    /api/save-items/{id}/profiles
+/// POST   /api/save-items/{id}/profiles                     {"name": ""}
+/// POST   /api/save-items/{id}/profiles/{profile}/switch    Default 表示默认配置
+/// POST   /api/save-items/{id}/export                       {"path": "", "backup_ids": []}
+/// POST   /api/import                                       {"path": "", "mode": "rename"}
+/// GET    /api/jobs
+/// GET    /api/games/scan
+/// POST   /api/games/scan                                   {"names": []} 为空时添加所有新游戏
+/// GET    /api/steam/apps
+/// GET    /api/steam/apps/{appid}/path                      ?path=%APPDATA%\\Game
+/// GET    /api/storage
+/// POST   /api/save-items/{id}/prune
+/// GET    /api/rescan                                       只报告
+/// POST   /api/rescan                                       接管没有记录的备份文件夹
+/// POST   /api/data/export                                  {"path": ""} 导出成 data.json 格式
+/// GET    /api/logs                                         ?level=debug|info|warn|error&item=&q=&limit=500
+/// GET    /api/health                                       ?item= 为空时检查所有未归档的存档
+/// POST   /api/notify/test                                  通过启用的渠道发送测试通知
+/// GET    /api/trash
+/// POST   /api/trash/{trash_id}/restore
+/// DELETE /api/trash/{trash_id}
+///
+/// {id} 可以是存档的 id 或名称
+pub fn start(setting: &ApiSetting, command: Sender<Command>) {
+    let addr = format!("127.0.0.1:{}", setting.port);
+    let server = match Server::http(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            log::log_err(format!("start api server {} error: {}", addr, e));
+            return;
+        }
+    };
+    log::log_info(format!("api server listen on {}", addr));
+    let token = setting.token.to_string();
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let command = command.clone();
+            let token = token.clone();
+            std::thread::spawn(move || handle(request, &command, &token));
+        }
+    });
+}
+
+fn handle(mut request: Request, command: &Sender<Command>, token: &str) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+    if !authorized(&request, query, token) {
+        return reply(request, 401, Err("unauthorized".to_string()));
+    }
+
+    let segments: Vec<String> = path
+        .trim_matches('/')
+        .split('/')
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+
+    let mut body = String::new();
+    let _ = request.as_reader().read_to_string(&mut body);
+    let body: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+
+    let (tx, rx) = mpsc::channel();
+    let cmd = match route(request.method(), &segments, query, &body, tx) {
+        Some(cmd) => cmd,
+        None => return reply(request, 404, Err(format!("not found: {}", path))),
+    };
+    if command.send(cmd).is_err() {
+        return reply(request, 503, Err("command channel closed".to_string()));
+    }
+    match rx.recv_timeout(TIMEOUT) {
+        Ok(result) => {
+            let status = if result.is_ok() { 200 } else { 400 };
+            reply(request, status, result)
+        }
+        Err(e) => reply(request, 504, Err(e.to_string())),
+    }
+}
+
+fn route(
+    method: &Method,
+    segments: &[&str],
+    query: &str,
+    body: &Value,
+    tx: Reply,
+) -> Option<Command> {
+    let string = |s: &str| s.to_string();
+    let cmd = match (method, segments) {
+        (Method::Get, ["api", "save-items"]) => Command::ListSaveItems {
+            filter: list_filter(query),
+            reply: tx,
+        },
+        (Method::Get, ["api", "save-items", id, "backups"]) => Command::ListBackups {
+            id: string(id),
+            keyword: query_value(query, "q").unwrap_or_default(),
+            reply: tx,
+        },
+        (Method::Get, ["api", "search"]) => Command::Search {
+            query: query_value(query, "q").unwrap_or_default(),
+            reply: tx,
+        },
+        (Method::Post, ["api", "save-items", id, "backups"]) => Command::Backup {
+            id: string(id),
+            remark: body["remark"].as_str().unwrap_or("API Backup").to_string(),
+            reply: Some(tx),
+        },
+        (Method::Post, ["api", "save-items", id, "backups", backup_id, "restore-file"]) => {
+            Command::RestoreFile {
+                id: string(id),
+                backup_id: string(backup_id),
+                path: body["path"].as_str().unwrap_or_default().to_string(),
+                reply: Some(tx),
+            }
+        }
+        (Method::Get, ["api", "save-items", id, "file-history"]) => Command::FileHistory {
+            id: string(id),
+            path: query_value(query, "path").unwrap_or_default(),
+            reply: tx,
+        },
+        (Method::Get, ["api", "save-items", id, "changes"]) => Command::ItemChanges {
+            id: string(id),
+            reply: tx,
+        },
+        (Method::Post, ["api", "save-items", id, "changes", change_id, "revert"]) => {
+            Command::RevertChange {
+                id: string(id),
+                change_id: string(change_id),
+                reply: Some(tx),
+            }
+        }
+        (Method::Post, ["api", "save-items", id, "backups", "bulk"]) => Command::BulkBackups {
+            id: string(id),
+            request: serde_json::from_value(body.clone()).ok()?,
+            reply: Some(tx),
+        },
+        (Method::Post, ["api", "save-items", id, "backups", backup_id, "restore"]) => {
+            Command::Restore {
+                id: string(id),
+                backup_id: string(backup_id),
+                reply: Some(tx),
+            }
+        }
+        (Method::Post, ["api", "save-items", id, "backups", backup_id, "meta"]) => {
+            Command::UpdateBackup {
+                id: string(id),
+                backup_id: string(backup_id),
+                update: serde_json::from_value(body.clone()).ok()?,
+                reply: Some(tx),
+            }
+        }
+        (Method::Post, ["api", "save-items", id, "backups", backup_id, action])
+            if *action == "protect" || *action == "unprotect" =>
+        {
+            Command::ProtectBackup {
+                id: string(id),
+                backup_id: string(backup_id),
+                protect: *action == "protect",
+                reply: Some(tx),
+            }
+        }
+        (Method::Delete, ["api", "save-items", id, "backups", backup_id]) => {
+            Command::DeleteBackup {
+                id: string(id),
+                backup_id: string(backup_id),
+                reply: Some(tx),
+            }
+        }
+        (Method::Get, ["api", "save-items", id, "history"]) => Command::GitHistory {
+            id: string(id),
+            reply: tx,
+        },
+        (Method::Get, ["api", "save-items", id, "backups", backup_id, "diff"]) => {
+            Command::GitDiff {
+                id: string(id),
+                backup_id: string(backup_id),
+                reply: tx,
+            }
+        }
+        (Method::Get, ["api", "save-items", id, "backups", backup_id, "blame"]) => {
+            Command::GitBlame {
+                id: string(id),
+                backup_id: string(backup_id),
+                file: query_value(query, "file"),
+                reply: tx,
+            }
+        }
+        (Method::Get, ["api", "save-items", id, "profiles"]) => Command::ListProfiles {
+            id: string(id),
+            reply: tx,
+        },
+        (Method::Post, ["api", "save-items", id, "profiles"]) => Command::AddProfile {
+            id: string(id),
+            name: body["name"].as_str().unwrap_or_default().to_string(),
+            reply: Some(tx),
+        },
+        (Method::Post, ["api", "save-items", id, "profiles", profile, "switch"]) => {
+            Command::SwitchProfile {
+                id: string(id),
+                profile: string(profile),
+                reply: Some(tx),
+            }
+        }
+        (Method::Post, ["api", "save-items", id, "export"]) => Command::Export {
+            id: string(id),
+            backup_ids: body["backup_ids"]
+                .as_array()
+                .map(|ids| {
+                    ids.iter()
+                        .filter_map(|id| id.as_str())
+                        .map(string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            path: body["path"].as_str().unwrap_or_default().to_string(),
+            reply: Some(tx),
+        },
+        (Method::Post, ["api", "import"]) => Command::Import {
+            path: body["path"].as_str().unwrap_or_default().to_string(),
+            mode: match body["mode"].as_str() {
+                Some("rename") => ImportMode::Rename,
+                Some("merge") => ImportMode::Merge,
+                _ => ImportMode::Strict,
+            },
+            reply: Some(tx),
+        },
+        (Method::Get, ["api", "jobs"]) => Command::JobStatus { reply: tx },
+        (Method::Get, ["api", "games", "scan"]) => Command::ScanGames { reply: tx },
+        (Method::Post, ["api", "games", "scan"]) => Command::AddGames {
+            names: body["names"]
+                .as_array()
+                .map(|names| {
+                    names
+                        .iter()
+                        .filter_map(|name| name.as_str())
+                        .map(string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            reply: Some(tx),
+        },
+        (Method::Get, ["api", "steam", "apps"]) => Command::SteamApps { reply: tx },
+        (Method::Get, ["api", "steam", "apps", app_id, "path"]) => Command::SteamPath {
+            app_id: app_id.parse().ok()?,
+            path: query_value(query, "path").unwrap_or_default(),
+            reply: tx,
+        },
+        (Method::Get, ["api", "storage"]) => Command::Storage { reply: tx },
+        (Method::Post, ["api", "save-items", id, "prune"]) => Command::Prune {
+            id: string(id),
+            reply: Some(tx),
+        },
+        (Method::Get | Method::Post, ["api", "rescan"]) => Command::Rescan {
+            apply: *method == Method::Post,
+            reply: Some(tx),
+        },
+        (Method::Post, ["api", "data", "export"]) => Command::ExportData {
+            path: body["path"].as_str().unwrap_or_default().to_string(),
+            reply: Some(tx),
+        },
+        (Method::Get, ["api", "logs"]) => Command::Logs {
+            filter: log_filter(query),
+            reply: tx,
+        },
+        (Method::Get, ["api", "health"]) => Command::Health {
+            id: query_value(query, "item").filter(|item| !item.is_empty()),
+            reply: tx,
+        },
+        (Method::Post, ["api", "notify", "test"]) => Command::TestNotify { reply: tx },
+        (Method::Get, ["api", "trash"]) => Command::ListTrash { reply: tx },
+        (Method::Post, ["api", "trash", id, "restore"]) => Command::RestoreTrash {
+            id: string(id),
+            reply: Some(tx),
+        },
+        (Method::Delete, ["api", "trash", id]) => Command::PurgeTrash {
+            id: string(id),
+            reply: Some(tx),
+        },
+        _ => return None,
+    };
+    Some(cmd)
+}
+
+/// 存档列表的筛选参数，tag 可以有多个，sort 不正确时按名称排序
+fn list_filter(query: &str) -> ItemFilter {
+    let mut filter = ItemFilter::default();
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        let value = query_decode(value);
+        match key {
+            "q" => filter.keyword = value,
+            "group" => filter.group = value,
+            "platform" => filter.platform = value,
+            "tag" => filter.tags.push(value),
+            "sort" => filter.sort = SortBy::parse(&value).unwrap_or_default(),
+            _ => {}
+        }
+    }
+    filter
+}
+
+fn log_filter(query: &str) -> LogFilter {
+    let mut filter = LogFilter::default();
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        let value = query_decode(value);
+        match key {
+            "level" => filter.level = Level::parse(&value).unwrap_or(filter.level),
+            "item" => filter.item = value,
+            "q" => filter.keyword = value,
+            "limit" => filter.limit = value.parse().unwrap_or(filter.limit),
+            _ => {}
+        }
+    }
+    filter
+}
+
+fn query_value(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| query_decode(value))
+}
+
+/// 支持 Authorization: Bearer <token> 或者 ?token=<token>
+fn authorized(request: &Request, query: &str, token: &str) -> bool {
+    if token.is_empty() {
+        return false;
+    }
+    let header = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .map(|h| h.value.as_str().trim().to_string());
+    if let Some(value) = header {
+        if value.strip_prefix("Bearer ").map(str::trim) == Some(token) {
+            return true;
+        }
+    }
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .any(|(key, value)| key == "token" && query_decode(value) == token)
+}
+
+fn reply(request: Request, status: u16, result: Result<Value, String>) {
+    let body = match result {
+        Ok(data) => json!({ "ok": true, "data": data }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    };
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        log::log_err(format!("api respond error: {}", e));
+    }
+}
+
+/// 路径中的 + 是普通字符，例如 /api/save-items/C++/backups
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+                match u8::from_str_radix(hex, 16) {
+                    Ok(b) => {
+                        out.push(b);
+                        i += 3;
+                        continue;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// 查询参数按表单编码，+ 表示空格，%2B 才是 +
+fn query_decode(text: &str) -> String {
+    percent_decode(&text.replace('+', " "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route_to(method: Method, path: &str, query: &str, body: Value) -> Option<Command> {
+        let segments: Vec<String> = path
+            .trim_matches('/')
+            .split('/')
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+        let (tx, _) = mpsc::channel();
+        route(&method, &segments, query, &body, tx)
+    }
+
+    #[test]
+    fn decode_percent_and_plus() {
+        assert_eq!(percent_decode("Game%20One"), "Game One");
+        assert_eq!(percent_decode("C++"), "C++");
+        assert_eq!(query_decode("a+b%2B"), "a b+");
+        assert_eq!(percent_decode("%E5%AD%98%E6%A1%A3"), "存档");
+        // 不完整或不正确的转义原样保留
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz1"), "%zz1");
+        assert_eq!(percent_decode("%4"), "%4");
+    }
+
+    #[test]
+    fn route_save_item_commands() {
+        let cmd = route_to(
+            Method::Post,
+            "/api/save-items/Game%20One/backups",
+            "",
+            json!({"remark": "before boss"}),
+        );
+        assert!(matches!(
+            cmd,
+            Some(Command::Backup { id, remark, reply: Some(_) })
+                if id == "Game One" && remark == "before boss"
+        ));
+        // 路径中的 + 不是空格
+        let cmd = route_to(Method::Post, "/api/save-items/C++/backups", "", Value::Null);
+        assert!(matches!(cmd, Some(Command::Backup { id, .. }) if id == "C++"));
+        let cmd = route_to(Method::Post, "/api/save-items/g1/backups", "", Value::Null);
+        assert!(matches!(cmd, Some(Command::Backup { remark, .. }) if remark == "API Backup"));
+        let cmd = route_to(
+            Method::Post,
+            "/api/save-items/g1/backups/b1/unprotect",
+            "",
+            Value::Null,
+        );
+        assert!(matches!(
+            cmd,
+            Some(Command::ProtectBackup { backup_id, protect: false, .. }) if backup_id == "b1"
+        ));
+        let cmd = route_to(
+            Method::Delete,
+            "/api/save-items/g1/backups/b1",
+            "",
+            Value::Null,
+        );
+        assert!(
+            matches!(cmd, Some(Command::DeleteBackup { id, backup_id, .. })
+            if id == "g1" && backup_id == "b1")
+        );
+    }
+
+    #[test]
+    fn route_queries() {
+        let cmd = route_to(
+            Method::Get,
+            "/api/save-items",
+            "q=final+boss&tag=rpg&tag=co%2Dop&sort=size",
+            Value::Null,
+        );
+        let Some(Command::ListSaveItems { filter, .. }) = cmd else {
+            panic!("expected ListSaveItems");
+        };
+        assert_eq!(filter.keyword, "final boss");
+        assert_eq!(filter.tags, ["rpg", "co-op"]);
+        assert_eq!(filter.sort, SortBy::Size);
+
+        let cmd = route_to(
+            Method::Get,
+            "/api/logs",
+            "level=warn&limit=abc",
+            Value::Null,
+        );
+        let Some(Command::Logs { filter, .. }) = cmd else {
+            panic!("expected Logs");
+        };
+        assert_eq!(filter.level, Level::Warn);
+        assert_eq!(filter.limit, LogFilter::default().limit);
+
+        let cmd = route_to(Method::Get, "/api/rescan", "", Value::Null);
+        assert!(matches!(cmd, Some(Command::Rescan { apply: false, .. })));
+        let cmd = route_to(Method::Post, "/api/rescan", "", Value::Null);
+        assert!(matches!(cmd, Some(Command::Rescan { apply: true, .. })));
+    }
+
+    #[test]
+    fn route_unknown_or_invalid() {
+        assert!(route_to(Method::Get, "/api/unknown", "", Value::Null).is_none());
+        assert!(route_to(Method::Put, "/api/save-items", "", Value::Null).is_none());
+        // appid 不是数字、批量操作的请求不正确时返回 404
+        assert!(route_to(Method::Get, "/api/steam/apps/abc/path", "", Value::Null).is_none());
+        let bulk = json!({"action": "shred"});
+        assert!(route_to(Method::Post, "/api/save-items/g1/backups/bulk", "", bulk).is_none());
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+use eframe::egui::{Context, ViewportCommand};
+
+#[derive(Default)]
+struct WindowState {
+    open: bool,
+    show: bool, // 请求打开窗口
+    quit: bool, // 请求退出程序
+    ctx: Option<Context>,
+}
+
+/// 主窗口只是前端，关闭后备份任务、command_proc 和本地接口继续在后台运行
+#[derive(Default, Clone)]
+pub struct WindowHandle {
+    state: Arc<(Mutex<WindowState>, Condvar)>,
+}
+
+impl WindowHandle {
+    /// 窗口已创建
+    pub fn opened(&self, ctx: &Context) {
+        let (state, _) = &*self.state;
+        if let Ok(mut state) = state.lock() {
+            state.open = true;
+            state.show = false;
+            state.ctx = Some(ctx.clone());
+        }
+    }
+
+    /// 窗口已关闭
+    pub fn closed(&self) {
+        let (state, _) = &*self.state;
+        if let Ok(mut state) = state.lock() {
+            state.open = false;
+            state.ctx = None;
+        }
+    }
+
+    /// 打开窗口，窗口已经打开时切换到前台
+    pub fn show(&self) {
+        let (state, cvar) = &*self.state;
+        if let Ok(mut state) = state.lock() {
+            match &state.ctx {
+                Some(ctx) if state.open => {
+                    ctx.send_viewport_cmd(ViewportCommand::Minimized(false));
+                    ctx.send_viewport_cmd(ViewportCommand::Focus);
+                    ctx.request_repaint();
+                }
+                _ => state.show = true,
+            }
+            cvar.notify_all();
+        }
+    }
+
+    /// 退出程序，窗口打开时先关闭窗口
+    pub fn quit(&self) {
+        let (state, cvar) = &*self.state;
+        if let Ok(mut state) = state.lock() {
+            state.quit = true;
+            if let Some(ctx) = &state.ctx {
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+                ctx.request_repaint();
+            }
+            cvar.notify_all();
+        }
+    }
+
+    pub fn quitting(&self) -> bool {
+        let (state, _) = &*self.state;
+        state.lock().map(|state| state.quit).unwrap_or(true)
+    }
+
+    /// 在后台等待打开窗口的请求，收到退出请求时返回 false
+    pub fn wait(&self) -> bool {
+        let (state, cvar) = &*self.state;
+        let Ok(mut state) = state.lock() else {
+            return false;
+        };
+        while !state.show && !state.quit {
+            state = match cvar.wait(state) {
+                Ok(state) => state,
+                Err(_) => return false,
+            };
+        }
+        state.show = false;
+        !state.quit
+    }
+}
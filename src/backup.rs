@@ -1,4 +1,7 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
 
 use egui_notify::ToastLevel;
 use fs_more::{directory::DirectoryCopyOptions, file::FileCopyOptions};
@@ -10,18 +13,23 @@ use uuid::Uuid;
 
 use crate::{
     data::{FileType, SaveItem},
-    log, util,
+    gitrepo,
+    hook::{self, HookKind},
+    log::{self, Entry, Level},
+    manifest, screenshot, steam, storage, util,
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct BackupData {
     pub source: String, // user data path
     pub target: String, // backup path
     pub file_type: FileType,
 }
-const BACKUP_FOLDER_FMT: &str = "%Y%m%d_%H%M%S";
+pub const BACKUP_FOLDER_FMT: &str = "%Y%m%d_%H%M%S";
+pub(crate) const NOTHING_TO_RESTORE: &str =
+    "The backup has no recorded files, add the monitored paths and rescan the backup folders";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Backup {
     pub id: String,
     pub datas: Vec<BackupData>, // 备份内容
@@ -29,6 +37,30 @@ pub struct Backup {
     pub protect: bool,          // 保护
     pub remark: String,         // 备注
     pub time: i64,
+    #[serde(default)]
+    pub trigger: Trigger, // 触发方式
+    #[serde(default)]
+    pub size: u64, // 字节
+    #[serde(default)]
+    pub files: u64,
+    #[serde(default)]
+    pub profile: String, // 所属的配置，为空时是默认配置
+    #[serde(default)]
+    pub commit: String, // git 格式的备份对应的提交
+    #[serde(default)]
+    pub format: Option<BackupFormat>, // 为空时是旧版本的记录，按提交和路径判断
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub notes: String, // 多行笔记
+    #[serde(default)]
+    pub rating: u8, // 星级 0-5
+    #[serde(default)]
+    pub pinned: bool, // 置顶
+    #[serde(default)]
+    pub screenshot: String, // 截图的路径
+    #[serde(default)]
+    pub playtime: Option<u64>, // 备份时的游玩时间（分钟），只有 Steam 游戏有
 }
 impl Backup {
     pub fn new(datas: Vec<BackupData>, backup_folder: String, remark: String) -> Self {
@@ -39,37 +71,259 @@ impl Backup {
             protect: false,
             remark,
             time: chrono::Local::now().timestamp_millis(),
+            trigger: Trigger::Manual,
+            size: 0,
+            files: 0,
+            profile: String::new(),
+            commit: String::new(),
+            format: Some(BackupFormat::Folder),
+            title: String::new(),
+            notes: String::new(),
+            rating: 0,
+            pinned: false,
+            screenshot: String::new(),
+            playtime: None,
         }
     }
 
-    pub fn run(save_item: SaveItem, remark: String) -> (Vec<(ToastLevel, String)>, Backup) {
-        let monitors = save_item.monitors.to_vec();
+    /// 标题、备注或笔记包含关键字，不区分大小写
+    pub fn matches(&self, keyword: &str) -> bool {
+        let keyword = keyword.trim().to_lowercase();
+        keyword.is_empty()
+            || [&self.title, &self.remark, &self.notes]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&keyword))
+    }
+
+    /// 备份保存在存档共用的 git 仓库中，这时 backup_folder 是整个仓库，不能移动或删除
+    pub fn is_git(&self) -> bool {
+        match self.format {
+            Some(format) => format == BackupFormat::Git,
+            None => {
+                !self.commit.is_empty()
+                    || std::path::Path::new(&self.backup_folder)
+                        .file_name()
+                        .is_some_and(|name| name == gitrepo::REPO_FOLDER)
+            }
+        }
+    }
+
+    /// 执行一次备份，返回提示信息、备份记录以及运行记录
+    /// 备份前的钩子失败或者 git 格式的备份失败时没有备份记录
+    pub fn run(
+        save_item: SaveItem,
+        remark: String,
+        trigger: Trigger,
+    ) -> (Vec<(ToastLevel, String)>, Option<Backup>, Run) {
         let mut msgs = vec![];
-        let backup = backup_file(&save_item.id, remark, monitors, &mut msgs);
-        (msgs, backup)
+        let folder = match save_item.format {
+            BackupFormat::Folder => new_backup_folder(&save_item.id),
+            BackupFormat::Git => gitrepo::repo_folder(&save_item.id),
+        };
+        let mut backup = Backup::new(vec![], folder, remark);
+        backup.format = Some(save_item.format);
+        backup.trigger = trigger.clone();
+        backup.profile = save_item.profile.to_string();
+
+        if let Err(e) = hook::run(&save_item, HookKind::PreBackup, &backup, "") {
+            msgs.push((ToastLevel::Error, format!("Hook Error\n{}", e)));
+            let run = Run::new(trigger, Outcome::Failed, e, String::new());
+            return (msgs, None, run);
+        }
+
+        let monitors = save_item.monitors.to_vec();
+        match save_item.format {
+            BackupFormat::Folder => {
+                backup.datas = backup_file(&backup.backup_folder, monitors, &mut msgs);
+                (backup.size, backup.files) = storage::measure(&backup.backup_folder);
+            }
+            BackupFormat::Git => gitrepo::backup(&mut backup, monitors, &mut msgs),
+        }
+
+        let errors: Vec<String> = msgs
+            .iter()
+            .filter(|(level, _)| *level == ToastLevel::Error)
+            .map(|(_, msg)| msg.to_string())
+            .collect();
+        let (outcome, reason) = if errors.is_empty() {
+            backup.playtime =
+                steam::parse_launch_url(&save_item.startup_path).and_then(steam::playtime);
+            backup.screenshot = screenshot::capture(&save_item, &backup.id);
+            (Outcome::Success, String::new())
+        } else {
+            (Outcome::Failed, errors.join("\n"))
+        };
+
+        let post_hook = hook::run(
+            &save_item,
+            HookKind::PostBackup,
+            &backup,
+            &outcome.to_string(),
+        );
+        if let Err(e) = post_hook {
+            msgs.push((ToastLevel::Warning, format!("Hook Error\n{}", e)));
+        }
+
+        let entry = match outcome {
+            Outcome::Success => {
+                Entry::new(Level::Info, format!("backup ok, trigger={:?}", trigger))
+            }
+            _ => Entry::new(Level::Error, format!("backup error, e={}", reason)),
+        };
+        entry.item(&save_item.name).backup(&backup.id).write();
+
+        // 失败的 git 备份没有提交，记录下来只会指向整个仓库
+        if outcome == Outcome::Failed && backup.is_git() {
+            let run = Run::new(trigger, outcome, reason, String::new());
+            return (msgs, None, run);
+        }
+        manifest::record(&backup);
+        let run = Run::new(trigger, outcome, reason, backup.id.to_string());
+        (msgs, Some(backup), run)
     }
 }
 
-pub fn backup_file(
-    id: &str,
-    remark: String,
-    monitors: Vec<crate::data::Monitor>,
-    toasts: &mut Vec<(ToastLevel, String)>,
-) -> Backup {
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Trigger {
+    #[default]
+    Manual,
+    Auto,
+    PreRestore, // 恢复前备份当前文件，例如切换配置
+}
+impl Trigger {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.to_lowercase().as_str() {
+            "manual" => Ok(Trigger::Manual),
+            "auto" => Ok(Trigger::Auto),
+            "pre-restore" => Ok(Trigger::PreRestore),
+            _ => Err(format!(
+                "unknown trigger: {}, expected manual, auto or pre-restore",
+                text
+            )),
+        }
+    }
+}
+
+/// 备份的保存方式
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum BackupFormat {
+    #[default]
+    Folder, // 每次备份复制到新的文件夹
+    Git, // 每次备份是存档 git 仓库中的一次提交，适合文本配置
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Outcome {
+    #[default]
+    Success,
+    Failed,
+    Skipped,
+}
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Outcome::Success => "Success",
+            Outcome::Failed => "Failed",
+            Outcome::Skipped => "Skipped",
+        })
+    }
+}
+
+/// 修改备份的信息，为空的字段不修改
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupUpdate {
+    pub title: Option<String>,
+    pub notes: Option<String>,
+    pub remark: Option<String>,
+    pub rating: Option<u8>,
+    pub pinned: Option<bool>,
+    pub screenshot: Option<String>, // 图片路径，会复制到截图目录，空字符串表示移除
+}
+impl BackupUpdate {
+    pub fn apply(self, backup: &mut Backup) -> Result<(), String> {
+        if let Some(rating) = self.rating {
+            if rating > MAX_RATING {
+                return Err(format!("rating should be 0-{}", MAX_RATING));
+            }
+            backup.rating = rating;
+        }
+        if let Some(title) = self.title {
+            backup.title = title;
+        }
+        if let Some(notes) = self.notes {
+            backup.notes = notes;
+        }
+        if let Some(remark) = self.remark {
+            backup.remark = remark;
+        }
+        if let Some(pinned) = self.pinned {
+            backup.pinned = pinned;
+        }
+        if let Some(screenshot) = self.screenshot {
+            // 替换或移除后删除原来的截图
+            if !backup.screenshot.is_empty() && backup.screenshot != screenshot {
+                util::delete_file(&backup.screenshot);
+            }
+            backup.screenshot = screenshot;
+        }
+        Ok(())
+    }
+}
+
+pub const MAX_RATING: u8 = 5;
+
+/// 一次备份任务的运行记录，包括被跳过和失败的任务
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Run {
+    pub id: String,
+    pub time: i64,
+    pub trigger: Trigger,
+    pub outcome: Outcome,
+    pub reason: String,    // 失败或跳过的原因
+    pub backup_id: String, // 成功时对应的备份
+}
+impl Run {
+    pub fn new(trigger: Trigger, outcome: Outcome, reason: String, backup_id: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            time: chrono::Local::now().timestamp_millis(),
+            trigger,
+            outcome,
+            reason,
+            backup_id,
+        }
+    }
+
+    pub fn skipped(trigger: Trigger, reason: String) -> Self {
+        Self::new(trigger, Outcome::Skipped, reason, String::new())
+    }
+}
+
+/// 新备份所在的路径 ./data/backup/<id>/<time>
+pub fn new_backup_folder(id: &str) -> String {
     let current_dir = util::current_dir();
     let time = chrono::Local::now();
-    let backup_folder = format!(
+    format!(
         "{}/data/backup/{}/{}",
         current_dir,
         id,
         time.format(BACKUP_FOLDER_FMT)
-    );
+    )
+}
+
+pub fn backup_file(
+    backup_folder: &str,
+    monitors: Vec<crate::data::Monitor>,
+    toasts: &mut Vec<(ToastLevel, String)>,
+) -> Vec<BackupData> {
     // println!("f = {}", backup_folder);
     // let target = "./data/a.txt";
     // let target = String::from(target);
-    util::check_dir_and_create(&backup_folder);
+    util::check_dir_and_create(backup_folder);
 
     let error = Arc::new(Mutex::new(vec![]));
+    let mut handles = vec![];
     let mut datas = vec![];
     for item in monitors {
         let backup_source = item.path.clone();
@@ -81,7 +335,7 @@ pub fn backup_file(
                 FileType::Folder => {
                     let target = format!("{}/{}", backup_folder, file_name.to_str().unwrap());
                     let backup_targer = target.clone();
-                    std::thread::spawn(move || {
+                    handles.push(std::thread::spawn(move || {
                         if let Err(e) = fs_more::directory::copy_directory(
                             &backup_source,
                             &backup_targer,
@@ -100,13 +354,13 @@ pub fn backup_file(
                             ));
                             arc_error.lock().unwrap().push(backup_source);
                         }
-                    });
+                    }));
                     target
                 }
                 FileType::File => {
                     let target = format!("{}/{}", backup_folder, file_name.to_str().unwrap());
                     let backup_targer = target.clone();
-                    std::thread::spawn(move || {
+                    handles.push(std::thread::spawn(move || {
                         if let Err(e) = fs_more::file::copy_file(
                             &backup_source,
                             &backup_targer,
@@ -121,7 +375,7 @@ pub fn backup_file(
                             ));
                             arc_error.lock().unwrap().push(backup_source);
                         }
-                    });
+                    }));
                     target
                 }
             };
@@ -133,6 +387,11 @@ pub fn backup_file(
         }
     }
 
+    // 等待所有复制线程结束，才能得到准确的结果
+    for handle in handles {
+        let _ = handle.join();
+    }
+
     let error = error.lock().unwrap();
     if error.is_empty() {
         toasts.push((ToastLevel::Success, "Backup Success".to_string()));
@@ -143,10 +402,10 @@ pub fn backup_file(
         ));
     }
 
-    Backup::new(datas, backup_folder, remark)
+    datas
 }
 
-pub fn overwrite(datas: &Vec<BackupData>, toasts: &mut Vec<(ToastLevel, String)>) {
+pub fn overwrite(datas: &Vec<BackupData>, toasts: &mut Vec<(ToastLevel, String)>) -> bool {
     let mut errors = vec![];
     for item in datas {
         let source = item.target.clone();
@@ -207,4 +466,60 @@ pub fn overwrite(datas: &Vec<BackupData>, toasts: &mut Vec<(ToastLevel, String)>
             format!("Has Error\n{}", errors.join("\n")),
         ));
     };
+    errors.is_empty()
+}
+
+/// 使用备份覆盖当前存档，前后执行恢复钩子，返回是否成功
+pub fn restore(
+    save_item: &SaveItem,
+    backup: &Backup,
+    toasts: &mut Vec<(ToastLevel, String)>,
+) -> bool {
+    // 重新扫描接管的文件夹和 Recovered 存档不知道原来的监听路径，没有可以恢复的内容
+    if backup.datas.is_empty() {
+        toasts.push((ToastLevel::Error, NOTHING_TO_RESTORE.to_string()));
+        Entry::new(
+            Level::Error,
+            "restore error, the backup has no recorded files",
+        )
+        .item(&save_item.name)
+        .backup(&backup.id)
+        .write();
+        return false;
+    }
+    if let Err(e) = hook::run(save_item, HookKind::PreRestore, backup, "") {
+        toasts.push((ToastLevel::Error, format!("Hook Error\n{}", e)));
+        return false;
+    }
+    let success = if backup.is_git() {
+        match gitrepo::checkout(backup) {
+            Ok(checkout) => overwrite(&checkout.datas, toasts),
+            Err(e) => {
+                log::log_err(format!("checkout {} error: {}", backup.commit, e));
+                toasts.push((ToastLevel::Error, e));
+                false
+            }
+        }
+    } else {
+        overwrite(&backup.datas, toasts)
+    };
+    let outcome = if success {
+        Outcome::Success
+    } else {
+        Outcome::Failed
+    };
+    let level = if success { Level::Info } else { Level::Error };
+    Entry::new(level, format!("restore {}", outcome))
+        .item(&save_item.name)
+        .backup(&backup.id)
+        .write();
+    if let Err(e) = hook::run(
+        save_item,
+        HookKind::PostRestore,
+        backup,
+        &outcome.to_string(),
+    ) {
+        toasts.push((ToastLevel::Warning, format!("Hook Error\n{}", e)));
+    }
+    success
 }
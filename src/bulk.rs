@@ -0,0 +1,321 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::{Backup, Trigger},
+    data::{Data, SaveItem},
+    gitrepo, storage, trash,
+};
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// 批量操作选择备份的条件，同时满足所有不为空的条件
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSelector {
+    pub ids: Vec<String>, // 指定的备份，为空时按其他条件从所有备份中选择
+    pub trigger: Option<Trigger>,
+    pub older_than_days: Option<u32>,
+    pub protected: Option<bool>,
+    pub keyword: String, // 标题、备注或笔记包含的文字
+}
+impl BackupSelector {
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+            && self.trigger.is_none()
+            && self.older_than_days.is_none()
+            && self.protected.is_none()
+            && self.keyword.trim().is_empty()
+    }
+
+    /// 选中的备份，没有任何条件时报错，避免误操作所有备份
+    pub fn select<'a>(&self, backups: &'a [Backup], now: i64) -> Result<Vec<&'a Backup>, String> {
+        if self.is_empty() {
+            return Err("no backups selected, give backup ids or conditions".to_string());
+        }
+        if let Some(id) = self
+            .ids
+            .iter()
+            .find(|id| !backups.iter().any(|backup| backup.id == **id))
+        {
+            return Err(format!("backup not found: {}", id));
+        }
+        Ok(backups
+            .iter()
+            .filter(|backup| self.ids.is_empty() || self.ids.contains(&backup.id))
+            .filter(|backup| self.trigger.as_ref().is_none_or(|t| backup.trigger == *t))
+            .filter(|backup| {
+                self.older_than_days
+                    .is_none_or(|days| backup.time <= now - days as i64 * DAY_MILLIS)
+            })
+            .filter(|backup| self.protected.is_none_or(|p| backup.protect == p))
+            .filter(|backup| backup.matches(&self.keyword))
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum BulkAction {
+    Delete,
+    Protect,
+    Unprotect,
+    Export { path: String },
+    Verify,
+}
+impl BulkAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BulkAction::Delete => "delete",
+            BulkAction::Protect => "protect",
+            BulkAction::Unprotect => "unprotect",
+            BulkAction::Export { .. } => "export",
+            BulkAction::Verify => "verify",
+        }
+    }
+}
+
+/// 批量操作的请求，dry_run 时只返回选中的备份
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkRequest {
+    #[serde(flatten)]
+    pub action: BulkAction,
+    #[serde(flatten)]
+    pub selector: BackupSelector,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// 每个备份的结果，error 为空表示成功
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkResult {
+    pub backup_id: String,
+    pub error: Option<String>,
+}
+
+/// 把选中的备份移到回收站，保护的备份不删除，最后只保存一次数据
+pub fn delete(
+    data: &mut Data,
+    id: &str,
+    ids: &[String],
+) -> Result<(SaveItem, Vec<BulkResult>), String> {
+    let item = data
+        .find_mut(id)
+        .ok_or(format!("save item not found: {}", id))?;
+    let snapshot = item.clone();
+    let mut results = vec![];
+    for backup_id in ids {
+        let Some(index) = item
+            .backups
+            .iter()
+            .position(|backup| backup.id == *backup_id)
+        else {
+            results.push(failed(
+                backup_id,
+                format!("backup not found: {}", backup_id),
+            ));
+            continue;
+        };
+        if item.backups[index].protect {
+            results.push(failed(
+                backup_id,
+                "Please cancel the protection first".to_string(),
+            ));
+            continue;
+        }
+        match trash::trash_backup(&snapshot, &item.backups[index]) {
+            Ok(_) => {
+                item.backups.remove(index);
+                results.push(ok(backup_id));
+            }
+            Err(e) => results.push(failed(backup_id, e)),
+        }
+    }
+    let item = item.clone();
+    data.save();
+    Ok((item, results))
+}
+
+pub fn protect(
+    data: &mut Data,
+    id: &str,
+    ids: &[String],
+    protect: bool,
+) -> Result<(SaveItem, Vec<BulkResult>), String> {
+    let item = data
+        .find_mut(id)
+        .ok_or(format!("save item not found: {}", id))?;
+    let mut results = vec![];
+    for backup_id in ids {
+        match item
+            .backups
+            .iter_mut()
+            .find(|backup| backup.id == *backup_id)
+        {
+            Some(backup) => {
+                backup.protect = protect;
+                results.push(ok(backup_id));
+            }
+            None => results.push(failed(
+                backup_id,
+                format!("backup not found: {}", backup_id),
+            )),
+        }
+    }
+    let item = item.clone();
+    data.save();
+    Ok((item, results))
+}
+
+/// 检查备份的文件是否完整：文件夹格式比较记录的大小和文件数，git 格式检查提交能否读取
+pub fn verify(backup: &Backup) -> Result<(), String> {
+    if backup.is_git() {
+        return gitrepo::verify(backup);
+    }
+    if !Path::new(&backup.backup_folder).is_dir() {
+        return Err(format!("backup folder not found: {}", backup.backup_folder));
+    }
+    if let Some(data) = backup
+        .datas
+        .iter()
+        .find(|data| !Path::new(&data.target).exists())
+    {
+        return Err(format!("file not found: {}", data.target));
+    }
+    // 旧版本的备份没有记录大小
+    if backup.size > 0 || backup.files > 0 {
+        let (size, files) = storage::measure(&backup.backup_folder);
+        if (size, files) != (backup.size, backup.files) {
+            return Err(format!(
+                "expected {} files, {} bytes, found {} files, {} bytes",
+                backup.files, backup.size, files, size
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn verify_all(backups: &[Backup]) -> Vec<BulkResult> {
+    backups
+        .iter()
+        .map(|backup| BulkResult {
+            backup_id: backup.id.to_string(),
+            error: verify(backup).err(),
+        })
+        .collect()
+}
+
+/// 整体成功的操作，例如导出
+pub fn succeeded(ids: &[String]) -> Vec<BulkResult> {
+    ids.iter().map(|id| ok(id)).collect()
+}
+
+fn ok(backup_id: &str) -> BulkResult {
+    BulkResult {
+        backup_id: backup_id.to_string(),
+        error: None,
+    }
+}
+
+fn failed(backup_id: &str, error: String) -> BulkResult {
+    BulkResult {
+        backup_id: backup_id.to_string(),
+        error: Some(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 100 * DAY_MILLIS;
+
+    fn backup(id: &str, days_ago: i64, trigger: Trigger, protect: bool, remark: &str) -> Backup {
+        Backup {
+            id: id.to_string(),
+            time: NOW - days_ago * DAY_MILLIS,
+            trigger,
+            protect,
+            ..Backup::new(vec![], String::new(), remark.to_string())
+        }
+    }
+
+    fn ids(selected: Vec<&Backup>) -> Vec<&str> {
+        selected.iter().map(|backup| backup.id.as_str()).collect()
+    }
+
+    fn backups() -> Vec<Backup> {
+        vec![
+            backup("a", 1, Trigger::Manual, false, "before boss"),
+            backup("b", 10, Trigger::Auto, false, ""),
+            backup("c", 30, Trigger::Auto, true, "Boss cleared"),
+        ]
+    }
+
+    #[test]
+    fn select_requires_a_condition() {
+        let backups = backups();
+        assert!(BackupSelector::default().select(&backups, NOW).is_err());
+        let blank = BackupSelector {
+            keyword: "  ".to_string(),
+            ..Default::default()
+        };
+        assert!(blank.select(&backups, NOW).is_err());
+    }
+
+    #[test]
+    fn select_by_ids() {
+        let backups = backups();
+        let selector = BackupSelector {
+            ids: vec!["c".to_string(), "a".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(ids(selector.select(&backups, NOW).unwrap()), ["a", "c"]);
+        let missing = BackupSelector {
+            ids: vec!["a".to_string(), "x".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            missing.select(&backups, NOW).unwrap_err(),
+            "backup not found: x"
+        );
+    }
+
+    #[test]
+    fn select_by_all_conditions() {
+        let backups = backups();
+        let selector = |selector: BackupSelector| ids(selector.select(&backups, NOW).unwrap());
+        assert_eq!(
+            selector(BackupSelector {
+                trigger: Some(Trigger::Auto),
+                ..Default::default()
+            }),
+            ["b", "c"]
+        );
+        assert_eq!(
+            selector(BackupSelector {
+                older_than_days: Some(10),
+                ..Default::default()
+            }),
+            ["b", "c"]
+        );
+        assert_eq!(
+            selector(BackupSelector {
+                keyword: "boss".to_string(),
+                protected: Some(false),
+                ..Default::default()
+            }),
+            ["a"]
+        );
+        assert_eq!(
+            selector(BackupSelector {
+                trigger: Some(Trigger::Auto),
+                older_than_days: Some(20),
+                protected: Some(false),
+                ..Default::default()
+            }),
+            Vec::<&str>::new()
+        );
+    }
+}
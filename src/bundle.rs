@@ -0,0 +1,495 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    backup::{Backup, BackupFormat, BACKUP_FOLDER_FMT},
+    data::{Data, SaveItem},
+    gitrepo,
+    hook::Hooks,
+    log, storage, util,
+};
+
+const FORMAT: &str = "auto_backup-bundle";
+const VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+const BACKUPS_DIR: &str = "backups";
+
+/// 导出包的清单，备份文件放在 backups/<backup_id>/ 下
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64,
+    pub item: SaveItem, // 只包含导出的备份
+}
+
+/// 导入时和已有存档冲突的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ImportMode {
+    #[default]
+    Strict, // 有冲突时不导入
+    Rename, // 作为新存档导入，名称加上后缀
+    Merge,  // 备份合并到同名存档
+}
+
+/// 把存档和选中的备份导出成一个 zip 文件，backup_ids 为空时导出所有备份
+pub fn export(
+    save_item: &SaveItem,
+    backup_ids: &[String],
+    path: &str,
+) -> Result<serde_json::Value, String> {
+    let mut item = save_item.clone();
+    if !backup_ids.is_empty() {
+        item.backups
+            .retain(|backup| backup_ids.contains(&backup.id));
+        if item.backups.len() != backup_ids.len() {
+            return Err("backup not found".to_string());
+        }
+    }
+    // 运行记录只和本机有关，钩子命令不随存档分发
+    item.runs.clear();
+    item.hooks = Hooks::default();
+
+    let file = File::create(path).map_err(|e| format!("create {} error: {}", path, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for backup in &item.backups {
+        let prefix = format!("{}/{}", BACKUPS_DIR, backup.id);
+        zip.add_directory(prefix.to_string(), options)
+            .map_err(|e| e.to_string())?;
+        // git 格式的备份先检出对应的提交，导出后是普通的文件夹
+        let checkout = match backup.is_git() {
+            true => Some(gitrepo::checkout(backup)?),
+            false => None,
+        };
+        let folder = checkout
+            .as_ref()
+            .map(|checkout| checkout.folder.to_string())
+            .unwrap_or(backup.backup_folder.to_string());
+        let mut files = vec![];
+        util::get_files(&folder, &mut files)
+            .map_err(|e| format!("read {} error: {}", folder, e))?;
+        for file in files {
+            let relative = Path::new(&file)
+                .strip_prefix(&folder)
+                .map_err(|e| e.to_string())?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let name = format!("{}/{}", prefix, relative);
+            if Path::new(&file).is_dir() {
+                zip.add_directory(name, options)
+                    .map_err(|e| e.to_string())?;
+            } else {
+                zip.start_file(name, options).map_err(|e| e.to_string())?;
+                let mut source =
+                    File::open(&file).map_err(|e| format!("open {} error: {}", file, e))?;
+                std::io::copy(&mut source, &mut zip)
+                    .map_err(|e| format!("write {} error: {}", file, e))?;
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        exported_at: chrono::Local::now().timestamp_millis(),
+        item,
+    };
+    zip.start_file(MANIFEST, options)
+        .map_err(|e| e.to_string())?;
+    let text = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    zip.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    zip.finish().map_err(|e| e.to_string())?;
+
+    log::log_info(format!(
+        "export {} with {} backups to {}",
+        save_item.name,
+        manifest.item.backups.len(),
+        path
+    ));
+    Ok(json!({ "path": path, "backups": manifest.item.backups.len() }))
+}
+
+pub fn read_manifest(path: &str) -> Result<Manifest, String> {
+    let file = File::open(path).map_err(|e| format!("open {} error: {}", path, e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("invalid bundle: {}", e))?;
+    let mut text = String::new();
+    zip.by_name(MANIFEST)
+        .map_err(|e| format!("invalid bundle: {}", e))?
+        .read_to_string(&mut text)
+        .map_err(|e| e.to_string())?;
+    let manifest: Manifest =
+        serde_json::from_str(&text).map_err(|e| format!("invalid manifest: {}", e))?;
+    if manifest.format != FORMAT {
+        return Err(format!("invalid bundle format: {}", manifest.format));
+    }
+    if manifest.version > VERSION {
+        return Err(format!(
+            "bundle version {} is newer than supported {}",
+            manifest.version, VERSION
+        ));
+    }
+    Ok(manifest)
+}
+
+/// 和已有存档的冲突：同名，或者监听相同的路径
+pub fn conflicts(item: &SaveItem, data: &Data) -> Vec<String> {
+    let mut conflicts = vec![];
+    for exist in &data.monitors {
+        if exist.name == item.name {
+            conflicts.push(format!("name `{}` already exists", item.name));
+            continue;
+        }
+        for monitor in &item.monitors {
+            if exist.monitors.iter().any(|m| m.path == monitor.path) {
+                conflicts.push(format!(
+                    "path `{}` is monitored by `{}`",
+                    monitor.path, exist.name
+                ));
+            }
+        }
+    }
+    conflicts
+}
+
+/// 导入的准备结果，解压备份时不需要持有数据锁
+pub struct ImportPlan {
+    item: SaveItem,
+    item_id: String,
+    mode: ImportMode,
+    merge: bool,            // 合并到已有的存档
+    pending: Vec<Backup>,   // 需要解压的备份
+    extracted: Vec<Backup>, // 已经解压并改写路径的备份
+}
+
+/// 导入存档的第一步：检查冲突并确定导入的目标
+/// 之后用 unpack 解压备份，apply 写入数据，导入后使用新的 id 并改写备份路径
+pub fn prepare(path: &str, data: &Data, mode: ImportMode) -> Result<ImportPlan, String> {
+    let manifest = read_manifest(path)?;
+    let mut item = manifest.item;
+    let conflicts = conflicts(&item, data);
+
+    let target = match mode {
+        ImportMode::Merge => data.monitors.iter().find(|m| m.name == item.name),
+        _ => None,
+    };
+    if mode == ImportMode::Strict && !conflicts.is_empty() {
+        return Err(format!(
+            "conflict with existing save items: {}",
+            conflicts.join("; ")
+        ));
+    }
+    if mode == ImportMode::Rename && !conflicts.is_empty() {
+        rename(&mut item, data);
+    }
+
+    let item_id = match target {
+        Some(target) => target.id.to_string(),
+        None => Uuid::new_v4().to_string(),
+    };
+    let mut pending = std::mem::take(&mut item.backups);
+    // 合并时跳过已有的同一时间的备份
+    if let Some(target) = target {
+        pending.retain(|backup| !target.backups.iter().any(|b| b.time == backup.time));
+    }
+    Ok(ImportPlan {
+        item,
+        item_id,
+        mode,
+        merge: target.is_some(),
+        pending,
+        extracted: vec![],
+    })
+}
+
+/// 解压备份到备份目录
+pub fn unpack(path: &str, plan: &mut ImportPlan) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("open {} error: {}", path, e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("invalid bundle: {}", e))?;
+
+    for backup in std::mem::take(&mut plan.pending) {
+        let folder_name = match backup.is_git() {
+            true => chrono::DateTime::from_timestamp_millis(backup.time)
+                .map(|time| {
+                    let time = time.with_timezone(&chrono::Local);
+                    time.format(BACKUP_FOLDER_FMT).to_string()
+                })
+                .unwrap_or(backup.id.to_string()),
+            false => Path::new(&backup.backup_folder)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or(backup.id.to_string()),
+        };
+        let folder = unique_folder(&format!(
+            "{}/data/backup/{}/{}",
+            util::current_dir(),
+            plan.item_id,
+            folder_name
+        ));
+        let result = extract(
+            &mut zip,
+            &format!("{}/{}/", BACKUPS_DIR, backup.id),
+            &folder,
+        );
+        if let Err(e) = result {
+            let _ = std::fs::remove_dir_all(&folder);
+            discard(plan);
+            return Err(e);
+        }
+        plan.extracted.push(rewrite(&backup, &folder));
+    }
+    Ok(())
+}
+
+/// 写入数据，解压期间存档有变化时重新检查冲突
+pub fn apply(plan: ImportPlan, data: &mut Data) -> Result<SaveItem, String> {
+    let mut plan = plan;
+    let mut item = plan.item.clone();
+    if plan.merge {
+        if data.find(&plan.item_id).is_none() {
+            discard(&mut plan);
+            return Err("save item not found".to_string());
+        }
+        let count = plan.extracted.len();
+        for backup in std::mem::take(&mut plan.extracted) {
+            data.insert_backup(&plan.item_id, backup)?;
+        }
+        log::log_info(format!("merge {} backups into {}", count, item.name));
+        return data
+            .find(&plan.item_id)
+            .cloned()
+            .ok_or("save item not found".to_string());
+    }
+    let conflicts = conflicts(&item, data);
+    if !conflicts.is_empty() {
+        if plan.mode == ImportMode::Strict {
+            discard(&mut plan);
+            return Err(format!(
+                "conflict with existing save items: {}",
+                conflicts.join("; ")
+            ));
+        }
+        rename(&mut item, data);
+    }
+    item.id = plan.item_id;
+    item.backups = plan.extracted;
+    data.push_monitor(item.clone());
+    log::log_info(format!(
+        "import {} with {} backups",
+        item.name,
+        item.backups.len()
+    ));
+    Ok(item)
+}
+
+/// 名称加上 (n) 后缀直到不重名
+fn rename(item: &mut SaveItem, data: &Data) {
+    let mut index = 1;
+    let name = item.name.to_string();
+    while data.monitors.iter().any(|m| m.name == item.name) {
+        item.name = format!("{} ({})", name, index);
+        index += 1;
+    }
+}
+
+/// 导入失败时删除已经解压的备份
+fn discard(plan: &mut ImportPlan) {
+    for backup in plan.extracted.drain(..) {
+        let _ = std::fs::remove_dir_all(&backup.backup_folder);
+    }
+}
+
+/// 同一秒导入多次时避免覆盖
+fn unique_folder(folder: &str) -> String {
+    let mut path = folder.to_string();
+    let mut index = 1;
+    while util::file_exist(&path) {
+        path = format!("{}_{}", folder, index);
+        index += 1;
+    }
+    path
+}
+
+/// 新的备份 id，备份内容的路径改到新的备份目录下
+fn rewrite(backup: &Backup, folder: &str) -> Backup {
+    let mut backup = backup.clone();
+    for data in &mut backup.datas {
+        if let Some(relative) = data.target.strip_prefix(&backup.backup_folder) {
+            data.target = format!("{}{}", folder, relative);
+        }
+    }
+    backup.id = Uuid::new_v4().to_string();
+    backup.backup_folder = folder.to_string();
+    backup.protect = false;
+    backup.commit = String::new();
+    backup.format = Some(BackupFormat::Folder); // git 格式的备份导出时已经检出成文件夹
+    backup.screenshot = String::new(); // 截图不随存档导出
+    (backup.size, backup.files) = storage::measure(folder);
+    backup
+}
+
+fn extract(zip: &mut ZipArchive<File>, prefix: &str, folder: &str) -> Result<(), String> {
+    util::check_dir_and_create(folder);
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index).map_err(|e| e.to_string())?;
+        // 忽略不安全的路径
+        let Some(name) = file.enclosed_name() else {
+            continue;
+        };
+        let Ok(relative) = name.strip_prefix(prefix) else {
+            continue;
+        };
+        let target = Path::new(folder).join(relative);
+        if file.is_dir() {
+            util::check_dir_and_create(&target.display().to_string());
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            util::check_dir_and_create(&parent.display().to_string());
+        }
+        let mut out = File::create(&target)
+            .map_err(|e| format!("create {} error: {}", target.display(), e))?;
+        std::io::copy(&mut file, &mut out)
+            .map_err(|e| format!("write {} error: {}", target.display(), e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backup::BackupData,
+        data::{FileType, Monitor},
+    };
+
+    /// 在临时目录准备一个有两个备份的存档并导出，返回导出包的路径
+    fn export_sample(name: &str) -> (SaveItem, String) {
+        let root = format!("{}/bundle_{}", util::current_dir(), Uuid::new_v4());
+        let mut backups = vec![];
+        for (index, time) in [2_000_i64, 1_000].iter().enumerate() {
+            let folder = format!("{}/backup_{}", root, index);
+            util::check_dir_and_create(&format!("{}/save", folder));
+            std::fs::write(
+                format!("{}/save/slot.sav", folder),
+                format!("slot {}", index),
+            )
+            .unwrap();
+            let datas = vec![BackupData {
+                source: format!("{}/game/save", root),
+                target: format!("{}/save", folder),
+                file_type: FileType::Folder,
+            }];
+            let mut backup = Backup::new(datas, folder, String::new());
+            backup.time = *time;
+            backups.push(backup);
+        }
+        let item = SaveItem {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            monitors: vec![Monitor {
+                path: format!("{}/game/save", root),
+                backup_type: FileType::Folder,
+            }],
+            backups,
+            ..Default::default()
+        };
+        let path = format!("{}/{}.zip", root, name);
+        export(&item, &[], &path).unwrap();
+        (item, path)
+    }
+
+    fn empty_data() -> Data {
+        serde_json::from_value(json!({ "monitors": [] })).unwrap()
+    }
+
+    fn import(path: &str, data: &mut Data, mode: ImportMode) -> Result<SaveItem, String> {
+        let mut plan = prepare(path, data, mode)?;
+        unpack(path, &mut plan)?;
+        apply(plan, data)
+    }
+
+    #[test]
+    fn import_rewrites_paths() {
+        let (origin, path) = export_sample("rewrite");
+        let mut data = empty_data();
+        let item = import(&path, &mut data, ImportMode::Strict).unwrap();
+        assert_ne!(item.id, origin.id);
+        assert_eq!(item.backups.len(), 2);
+        let prefix = format!("{}/data/backup/{}/", util::current_dir(), item.id);
+        for (backup, origin) in item.backups.iter().zip(&origin.backups) {
+            assert_ne!(backup.id, origin.id);
+            assert_eq!(backup.time, origin.time);
+            assert!(backup.backup_folder.starts_with(&prefix));
+            // 备份内容的路径改到新的备份目录，来源路径不变
+            assert_eq!(
+                backup.datas[0].target,
+                format!("{}/save", backup.backup_folder)
+            );
+            assert_eq!(backup.datas[0].source, origin.datas[0].source);
+            assert!(util::file_exist(&format!(
+                "{}/slot.sav",
+                backup.datas[0].target
+            )));
+        }
+        assert_eq!(data.monitors.len(), 1);
+    }
+
+    #[test]
+    fn import_conflict_modes() {
+        let (_, path) = export_sample("modes");
+        let mut data = empty_data();
+        let first = import(&path, &mut data, ImportMode::Strict).unwrap();
+
+        let err = import(&path, &mut data, ImportMode::Strict).unwrap_err();
+        assert!(err.contains("name `modes` already exists"));
+        assert_eq!(data.monitors.len(), 1);
+
+        let renamed = import(&path, &mut data, ImportMode::Rename).unwrap();
+        assert_eq!(renamed.name, "modes (1)");
+        assert_ne!(renamed.id, first.id);
+        assert_eq!(data.monitors.len(), 2);
+
+        // 合并时只导入没有的同一时间的备份
+        data.find_mut(&first.id)
+            .unwrap()
+            .backups
+            .retain(|backup| backup.time == 1_000);
+        let merged = import(&path, &mut data, ImportMode::Merge).unwrap();
+        assert_eq!(merged.id, first.id);
+        let times: Vec<i64> = merged.backups.iter().map(|backup| backup.time).collect();
+        assert_eq!(times, vec![2_000, 1_000]);
+        assert_eq!(data.monitors.len(), 2);
+    }
+
+    #[test]
+    fn reject_newer_version() {
+        let path = format!("{}/bundle_{}.zip", util::current_dir(), Uuid::new_v4());
+        util::check_dir_and_create(&util::current_dir());
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file(MANIFEST, SimpleFileOptions::default())
+            .unwrap();
+        let manifest = Manifest {
+            format: FORMAT.to_string(),
+            version: VERSION + 1,
+            exported_at: 0,
+            item: SaveItem::default(),
+        };
+        zip.write_all(serde_json::to_string(&manifest).unwrap().as_bytes())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let err = read_manifest(&path).unwrap_err();
+        assert!(err.contains("newer than supported"));
+        assert!(prepare(&path, &empty_data(), ImportMode::Strict).is_err());
+    }
+}
@@ -0,0 +1,816 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use rusqlite::{params, Connection, Row, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::{Backup, BackupData, Run},
+    changelog::{self, Change, CHANGE_LIMIT},
+    data::{FileType, Monitor, SaveItem},
+    manifest::FileEntry,
+    util,
+};
+
+const PATH: &str = "./data/catalog.db";
+/// 备份列表每页数量的选项，默认使用第二个
+pub const PAGE_SIZES: [usize; 4] = [50, 100, 200, 500];
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS items (
+    id TEXT PRIMARY KEY,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS monitors (
+    item_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    backup_type TEXT NOT NULL,
+    PRIMARY KEY (item_id, position)
+);
+CREATE TABLE IF NOT EXISTS backups (
+    id TEXT PRIMARY KEY,
+    item_id TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    pinned INTEGER NOT NULL,
+    protect INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    profile TEXT NOT NULL,
+    title TEXT NOT NULL,
+    remark TEXT NOT NULL,
+    notes TEXT NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS backups_item_time ON backups (item_id, time);
+CREATE INDEX IF NOT EXISTS backups_item_size ON backups (item_id, size);
+CREATE TABLE IF NOT EXISTS backup_files (
+    backup_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    target TEXT NOT NULL,
+    file_type TEXT NOT NULL,
+    PRIMARY KEY (backup_id, position)
+);
+CREATE TABLE IF NOT EXISTS backup_manifest (
+    backup_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    mtime INTEGER,
+    hash TEXT NOT NULL,
+    PRIMARY KEY (backup_id, path)
+);
+CREATE TABLE IF NOT EXISTS runs (
+    id TEXT PRIMARY KEY,
+    item_id TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    outcome TEXT NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS runs_item_time ON runs (item_id, time);
+CREATE TABLE IF NOT EXISTS item_changes (
+    id TEXT PRIMARY KEY,
+    item_id TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    old_body TEXT NOT NULL,
+    new_body TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS item_changes_item_time ON item_changes (item_id, time);
+";
+
+static CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+/// 每次写入后加一，界面据此判断缓存的分页是否过期
+static REVISION: AtomicU64 = AtomicU64::new(0);
+
+/// 上次写入索引的内容，保存时只写入和它不同的行，不会覆盖其他进程修改的存档和备份
+#[derive(Debug, Default, Clone)]
+pub struct Snapshot {
+    items: HashMap<String, (usize, String, Vec<Monitor>)>, // 存档 id -> (位置, 设置, 监听路径)
+    backups: HashMap<String, (String, Backup)>,            // 备份 id -> (存档 id, 备份)
+    runs: HashMap<String, (String, Run)>,                  // 运行记录 id -> (存档 id, 运行记录)
+}
+
+/// 备份列表的排序列
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BackupSort {
+    #[default]
+    Time,
+    Profile,
+    Remark,
+    Size,
+    Protect,
+}
+impl BackupSort {
+    fn column(&self) -> &'static str {
+        match self {
+            BackupSort::Time => "time",
+            BackupSort::Profile => "profile",
+            BackupSort::Remark => "title || remark",
+            BackupSort::Size => "size",
+            BackupSort::Protect => "protect",
+        }
+    }
+}
+
+/// 备份列表的一页，置顶的备份总是在前
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageQuery {
+    pub item_id: String,
+    pub keyword: String, // 标题、备注或笔记包含的文字
+    pub sort: BackupSort,
+    pub desc: bool,
+    pub page: usize, // 从 0 开始
+    pub page_size: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Page {
+    pub item_ids: HashSet<String>,  // 存档所有备份的 id
+    pub ids: Vec<String>,           // 筛选后所有备份的 id，按排序的顺序
+    pub rows: Vec<(usize, Backup)>, // 当前页的备份以及在存档中的序号（按时间倒序）
+    pub revision: u64,
+}
+impl Page {
+    pub fn pages(&self, page_size: usize) -> usize {
+        self.ids.len().div_ceil(page_size.max(1)).max(1)
+    }
+}
+
+fn open() -> rusqlite::Result<Connection> {
+    // 测试使用内存数据库，不写入 ./data
+    let connection = if cfg!(test) {
+        Connection::open_in_memory()?
+    } else {
+        util::check_dir_and_create("./data");
+        Connection::open(PATH)?
+    };
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+fn with<T>(f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+    let mut connection = CONNECTION.lock().map_err(|e| e.to_string())?;
+    if connection.is_none() {
+        *connection = Some(open().map_err(|e| e.to_string())?);
+    }
+    f(connection.as_mut().unwrap()).map_err(|e| e.to_string())
+}
+
+fn rows<T>(
+    connection: &Connection,
+    sql: &str,
+    f: impl FnMut(&Row) -> rusqlite::Result<T>,
+) -> rusqlite::Result<Vec<T>> {
+    let mut stmt = connection.prepare_cached(sql)?;
+    let rows = stmt.query_map([], f)?.collect();
+    rows
+}
+
+fn file_type(text: &str) -> FileType {
+    match text {
+        "File" => FileType::File,
+        _ => FileType::Folder,
+    }
+}
+
+/// 存档的设置，监听路径、备份和运行记录保存在单独的表中
+fn settings(item: &mut SaveItem) -> serde_json::Result<String> {
+    let monitors = std::mem::take(&mut item.monitors);
+    let backups = std::mem::take(&mut item.backups);
+    let runs = std::mem::take(&mut item.runs);
+    let body = serde_json::to_string(item);
+    item.monitors = monitors;
+    item.backups = backups;
+    item.runs = runs;
+    body
+}
+
+/// 快照中的存档设置加上监听路径，再按 changelog::retain 只保留记录修改的字段
+fn definition(body: &str, monitors: &[Monitor]) -> Result<serde_json::Value, String> {
+    let mut value: serde_json::Value = serde_json::from_str(body).map_err(|e| e.to_string())?;
+    value["monitors"] = serde_json::to_value(monitors).map_err(|e| e.to_string())?;
+    Ok(changelog::retain(value))
+}
+
+/// 备份记录，备份的文件保存在 backup_files 中
+fn backup_body(backup: &Backup) -> serde_json::Result<String> {
+    serde_json::to_string(&Backup {
+        datas: vec![],
+        ..backup.clone()
+    })
+}
+
+pub fn revision() -> u64 {
+    REVISION.load(Ordering::Relaxed)
+}
+
+/// 读取索引中的所有存档及其监听路径、备份和运行记录，备份和运行记录按时间倒序
+pub fn load() -> Result<(Vec<SaveItem>, Snapshot), String> {
+    let text = |row: &Row, index: usize| row.get::<_, String>(index);
+    let (items, monitors, backups, files, runs) = with(|connection| {
+        Ok((
+            rows(
+                connection,
+                "SELECT id, body FROM items ORDER BY position",
+                |row| Ok((text(row, 0)?, text(row, 1)?)),
+            )?,
+            rows(
+                connection,
+                "SELECT item_id, path, backup_type FROM monitors ORDER BY item_id, position",
+                |row| Ok((text(row, 0)?, text(row, 1)?, text(row, 2)?)),
+            )?,
+            rows(
+                connection,
+                "SELECT item_id, body FROM backups ORDER BY time DESC",
+                |row| Ok((text(row, 0)?, text(row, 1)?)),
+            )?,
+            rows(
+                connection,
+                "SELECT backup_id, source, target, file_type FROM backup_files
+                 ORDER BY backup_id, position",
+                |row| Ok((text(row, 0)?, text(row, 1)?, text(row, 2)?, text(row, 3)?)),
+            )?,
+            rows(
+                connection,
+                "SELECT item_id, body FROM runs ORDER BY time DESC",
+                |row| Ok((text(row, 0)?, text(row, 1)?)),
+            )?,
+        ))
+    })?;
+
+    let mut snapshot = Snapshot::default();
+    let mut monitors_of: HashMap<String, Vec<Monitor>> = HashMap::new();
+    for (item_id, path, backup_type) in monitors {
+        monitors_of.entry(item_id).or_default().push(Monitor {
+            path,
+            backup_type: file_type(&backup_type),
+        });
+    }
+    let mut files_of: HashMap<String, Vec<BackupData>> = HashMap::new();
+    for (backup_id, source, target, kind) in files {
+        files_of.entry(backup_id).or_default().push(BackupData {
+            source,
+            target,
+            file_type: file_type(&kind),
+        });
+    }
+    let mut backups_of: HashMap<String, Vec<Backup>> = HashMap::new();
+    for (item_id, body) in backups {
+        let mut backup: Backup = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+        backup.datas = files_of.remove(&backup.id).unwrap_or_default();
+        snapshot
+            .backups
+            .insert(backup.id.to_string(), (item_id.to_string(), backup.clone()));
+        backups_of.entry(item_id).or_default().push(backup);
+    }
+    let mut runs_of: HashMap<String, Vec<Run>> = HashMap::new();
+    for (item_id, body) in runs {
+        let run: Run = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+        snapshot
+            .runs
+            .insert(run.id.to_string(), (item_id.to_string(), run.clone()));
+        runs_of.entry(item_id).or_default().push(run);
+    }
+
+    let mut list = vec![];
+    for (position, (id, body)) in items.into_iter().enumerate() {
+        let mut item: SaveItem = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+        item.monitors = monitors_of.remove(&id).unwrap_or_default();
+        item.backups = backups_of.remove(&id).unwrap_or_default();
+        item.runs = runs_of.remove(&id).unwrap_or_default();
+        snapshot
+            .items
+            .insert(id, (position, body, item.monitors.to_vec()));
+        list.push(item);
+    }
+    Ok((list, snapshot))
+}
+
+/// 把和快照相比新增、修改和删除的存档、备份和运行记录写入索引，在一个事务中完成
+pub fn sync(items: &mut [SaveItem], snapshot: &mut Snapshot) -> Result<(), String> {
+    let mut changed_items = vec![];
+    let mut changes = vec![];
+    let mut changed_backups = vec![];
+    let mut changed_runs = vec![];
+    let mut item_ids = HashSet::new();
+    let mut backup_ids = HashSet::new();
+    let mut run_ids = HashSet::new();
+    for (position, item) in items.iter_mut().enumerate() {
+        let body = settings(item).map_err(|e| e.to_string())?;
+        item_ids.insert(item.id.to_string());
+        match snapshot.items.get(&item.id) {
+            Some((saved_position, saved, monitors))
+                if *saved_position == position && *saved == body && *monitors == item.monitors => {}
+            saved => {
+                // 用户编辑的设置变化时记录修改前后的定义，新建的存档不记录
+                if let Some((_, saved, monitors)) = saved {
+                    if *saved != body || *monitors != item.monitors {
+                        let before = definition(saved, monitors)?;
+                        let after = changelog::definition(item).map_err(|e| e.to_string())?;
+                        if before != after {
+                            changes.push((
+                                item.id.to_string(),
+                                before.to_string(),
+                                after.to_string(),
+                            ));
+                        }
+                    }
+                }
+                changed_items.push((
+                    item.id.to_string(),
+                    position,
+                    item.name.to_string(),
+                    body,
+                    item.monitors.to_vec(),
+                ));
+            }
+        }
+        for backup in &item.backups {
+            backup_ids.insert(backup.id.to_string());
+            match snapshot.backups.get(&backup.id) {
+                Some((item_id, saved)) if *item_id == item.id && saved == backup => {}
+                _ => {
+                    let body = backup_body(backup).map_err(|e| e.to_string())?;
+                    changed_backups.push((item.id.to_string(), backup.clone(), body));
+                }
+            }
+        }
+        for run in &item.runs {
+            run_ids.insert(run.id.to_string());
+            match snapshot.runs.get(&run.id) {
+                Some((item_id, saved)) if *item_id == item.id && saved == run => {}
+                _ => {
+                    let body = serde_json::to_string(run).map_err(|e| e.to_string())?;
+                    changed_runs.push((item.id.to_string(), run.clone(), body));
+                }
+            }
+        }
+    }
+    let removed = |ids: &HashSet<String>, saved: Vec<&String>| -> Vec<String> {
+        saved
+            .into_iter()
+            .filter(|id| !ids.contains(*id))
+            .cloned()
+            .collect()
+    };
+    let removed_items = removed(&item_ids, snapshot.items.keys().collect());
+    let removed_backups = removed(&backup_ids, snapshot.backups.keys().collect());
+    let removed_runs = removed(&run_ids, snapshot.runs.keys().collect());
+    if changed_items.is_empty()
+        && changed_backups.is_empty()
+        && changed_runs.is_empty()
+        && removed_items.is_empty()
+        && removed_backups.is_empty()
+        && removed_runs.is_empty()
+    {
+        return Ok(());
+    }
+
+    with(|connection| {
+        let tx = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        {
+            let mut insert_item = tx.prepare_cached(
+                "INSERT OR REPLACE INTO items (id, position, name, body) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let mut delete_item = tx.prepare_cached("DELETE FROM items WHERE id = ?1")?;
+            let mut insert_monitor = tx.prepare_cached(
+                "INSERT INTO monitors (item_id, position, path, backup_type) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let mut delete_monitors =
+                tx.prepare_cached("DELETE FROM monitors WHERE item_id = ?1")?;
+            for (id, position, name, body, monitors) in &changed_items {
+                insert_item.execute(params![id, *position as i64, name, body])?;
+                delete_monitors.execute([id])?;
+                for (index, monitor) in monitors.iter().enumerate() {
+                    insert_monitor.execute(params![
+                        id,
+                        index as i64,
+                        monitor.path,
+                        monitor.backup_type.to_string()
+                    ])?;
+                }
+            }
+            for id in &removed_items {
+                delete_item.execute([id])?;
+                delete_monitors.execute([id])?;
+            }
+
+            let mut insert_change = tx.prepare_cached(
+                "INSERT INTO item_changes (id, item_id, time, old_body, new_body)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let mut trim_changes = tx.prepare_cached(
+                "DELETE FROM item_changes WHERE item_id = ?1 AND id NOT IN
+                 (SELECT id FROM item_changes WHERE item_id = ?1 ORDER BY time DESC LIMIT ?2)",
+            )?;
+            let time = chrono::Local::now().timestamp_millis();
+            for (item_id, before, after) in &changes {
+                let id = uuid::Uuid::new_v4().to_string();
+                insert_change.execute(params![id, item_id, time, before, after])?;
+                trim_changes.execute(params![item_id, CHANGE_LIMIT as i64])?;
+            }
+
+            let mut insert_backup = tx.prepare_cached(
+                "INSERT OR REPLACE INTO backups
+                 (id, item_id, time, pinned, protect, size, profile, title, remark, notes, body)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            let mut delete_backup = tx.prepare_cached("DELETE FROM backups WHERE id = ?1")?;
+            let mut insert_file = tx.prepare_cached(
+                "INSERT INTO backup_files (backup_id, position, source, target, file_type)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let mut delete_files =
+                tx.prepare_cached("DELETE FROM backup_files WHERE backup_id = ?1")?;
+            let mut delete_manifest =
+                tx.prepare_cached("DELETE FROM backup_manifest WHERE backup_id = ?1")?;
+            for (item_id, backup, body) in &changed_backups {
+                insert_backup.execute(params![
+                    backup.id,
+                    item_id,
+                    backup.time,
+                    backup.pinned,
+                    backup.protect,
+                    backup.size as i64,
+                    backup.profile,
+                    backup.title,
+                    backup.remark,
+                    backup.notes,
+                    body,
+                ])?;
+                delete_files.execute([&backup.id])?;
+                for (index, data) in backup.datas.iter().enumerate() {
+                    insert_file.execute(params![
+                        backup.id,
+                        index as i64,
+                        data.source,
+                        data.target,
+                        data.file_type.to_string()
+                    ])?;
+                }
+            }
+            for id in &removed_backups {
+                delete_backup.execute([id])?;
+                delete_files.execute([id])?;
+                delete_manifest.execute([id])?;
+            }
+
+            let mut insert_run = tx.prepare_cached(
+                "INSERT OR REPLACE INTO runs (id, item_id, time, outcome, body)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let mut delete_run = tx.prepare_cached("DELETE FROM runs WHERE id = ?1")?;
+            for (item_id, run, body) in &changed_runs {
+                insert_run.execute(params![
+                    run.id,
+                    item_id,
+                    run.time,
+                    run.outcome.to_string(),
+                    body
+                ])?;
+            }
+            for id in &removed_runs {
+                delete_run.execute([id])?;
+            }
+        }
+        tx.commit()
+    })?;
+
+    for (id, position, _, body, monitors) in changed_items {
+        snapshot.items.insert(id, (position, body, monitors));
+    }
+    for (item_id, backup, _) in changed_backups {
+        snapshot
+            .backups
+            .insert(backup.id.to_string(), (item_id, backup));
+    }
+    for (item_id, run, _) in changed_runs {
+        snapshot.runs.insert(run.id.to_string(), (item_id, run));
+    }
+    for id in removed_items {
+        snapshot.items.remove(&id);
+    }
+    for id in removed_backups {
+        snapshot.backups.remove(&id);
+    }
+    for id in removed_runs {
+        snapshot.runs.remove(&id);
+    }
+    REVISION.fetch_add(1, Ordering::Relaxed);
+    Ok(())
+}
+
+/// 记录备份中的文件，替换已有的记录
+pub fn record_manifest(backup_id: &str, files: &[FileEntry]) -> Result<(), String> {
+    with(|connection| {
+        let tx = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute(
+            "DELETE FROM backup_manifest WHERE backup_id = ?1",
+            [backup_id],
+        )?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO backup_manifest (backup_id, path, size, mtime, hash)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for file in files {
+                insert.execute(params![
+                    backup_id,
+                    file.path,
+                    file.size as i64,
+                    file.mtime,
+                    file.hash
+                ])?;
+            }
+        }
+        tx.commit()
+    })
+}
+
+/// 已经记录了文件清单的备份
+pub fn manifest_ids() -> Result<HashSet<String>, String> {
+    with(|connection| {
+        rows(
+            connection,
+            "SELECT DISTINCT backup_id FROM backup_manifest",
+            |row| row.get(0),
+        )
+    })
+    .map(|ids| ids.into_iter().collect())
+}
+
+/// 路径包含 keyword 的文件，返回 (备份 id, 路径)，keyword 是小写
+pub fn find_files(keyword: &str) -> Result<Vec<(String, String)>, String> {
+    with(|connection| {
+        let mut stmt = connection.prepare_cached(
+            "SELECT backup_id, path FROM backup_manifest
+             WHERE instr(lower(path), ?1) > 0 ORDER BY backup_id, path",
+        )?;
+        let rows = stmt
+            .query_map([keyword], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+        rows
+    })
+}
+
+/// 存档设置的修改记录，新的在前，旧版本记录的只有状态变化的修改不列出
+pub fn changes(item_id: &str) -> Result<Vec<Change>, String> {
+    let rows = with(|connection| {
+        let mut stmt = connection.prepare_cached(
+            "SELECT id, item_id, time, old_body, new_body FROM item_changes
+             WHERE item_id = ?1 ORDER BY time DESC",
+        )?;
+        let rows = stmt
+            .query_map([item_id], change_row)?
+            .collect::<rusqlite::Result<Vec<_>>>();
+        rows
+    })?;
+    let changes: Vec<Change> = rows
+        .into_iter()
+        .map(parse_change)
+        .collect::<Result<_, _>>()?;
+    Ok(changes
+        .into_iter()
+        .filter(|change| change.before != change.after)
+        .collect())
+}
+
+pub fn find_change(id: &str) -> Result<Option<Change>, String> {
+    let rows = with(|connection| {
+        let mut stmt = connection.prepare_cached(
+            "SELECT id, item_id, time, old_body, new_body FROM item_changes WHERE id = ?1",
+        )?;
+        let rows = stmt
+            .query_map([id], change_row)?
+            .collect::<rusqlite::Result<Vec<_>>>();
+        rows
+    })?;
+    rows.into_iter().next().map(parse_change).transpose()
+}
+
+type ChangeRow = (String, String, i64, String, String);
+
+fn change_row(row: &Row) -> rusqlite::Result<ChangeRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+    ))
+}
+
+fn parse_change((id, item_id, time, before, after): ChangeRow) -> Result<Change, String> {
+    Ok(Change {
+        id,
+        item_id,
+        time,
+        before: changelog::retain(serde_json::from_str(&before).map_err(|e| e.to_string())?),
+        after: changelog::retain(serde_json::from_str(&after).map_err(|e| e.to_string())?),
+    })
+}
+
+/// 按条件排序和分页，只读取当前页的备份内容
+pub fn page(query: &PageQuery) -> Result<Page, String> {
+    let revision = revision();
+    let keyword = query.keyword.trim().to_lowercase();
+    let page_size = query.page_size.max(1);
+    let sql = format!(
+        "SELECT id, number,
+                ?2 = '' OR instr(lower(title), ?2) > 0
+                OR instr(lower(remark), ?2) > 0 OR instr(lower(notes), ?2) > 0
+         FROM (
+             SELECT id, pinned, time, protect, size, profile, title, remark, notes,
+                    ROW_NUMBER() OVER (ORDER BY time DESC) - 1 AS number
+             FROM backups WHERE item_id = ?1)
+         ORDER BY pinned DESC, {} {}, time DESC",
+        query.sort.column(),
+        if query.desc { "DESC" } else { "ASC" }
+    );
+    let (rows, bodies) = with(|connection| {
+        let mut stmt = connection.prepare_cached(&sql)?;
+        let rows = stmt
+            .query_map(params![query.item_id, keyword], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, bool>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut body = connection.prepare_cached("SELECT body FROM backups WHERE id = ?1")?;
+        let mut files = connection.prepare_cached(
+            "SELECT source, target, file_type FROM backup_files
+             WHERE backup_id = ?1 ORDER BY position",
+        )?;
+        let mut bodies = vec![];
+        for (id, number, _) in rows
+            .iter()
+            .filter(|(_, _, matched)| *matched)
+            .skip(query.page * page_size)
+            .take(page_size)
+        {
+            let datas = files
+                .query_map([id], |row| {
+                    Ok(BackupData {
+                        source: row.get(0)?,
+                        target: row.get(1)?,
+                        file_type: file_type(&row.get::<_, String>(2)?),
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            bodies.push((
+                *number as usize,
+                body.query_row([id], |row| row.get::<_, String>(0))?,
+                datas,
+            ));
+        }
+        Ok((rows, bodies))
+    })?;
+    let ids = rows
+        .iter()
+        .filter(|(_, _, matched)| *matched)
+        .map(|(id, _, _)| id.to_string())
+        .collect();
+    let item_ids = rows.into_iter().map(|(id, _, _)| id).collect();
+    let mut rows = vec![];
+    for (number, body, datas) in bodies {
+        let mut backup: Backup = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+        backup.datas = datas;
+        rows.push((number, backup));
+    }
+    Ok(Page {
+        item_ids,
+        ids,
+        rows,
+        revision,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{Outcome, Trigger};
+
+    fn item(name: &str) -> SaveItem {
+        SaveItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            monitors: vec![Monitor {
+                path: format!("/saves/{}", name),
+                backup_type: FileType::Folder,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn backup() -> Backup {
+        let data = BackupData {
+            source: "/saves/game".to_string(),
+            target: "/backup/game".to_string(),
+            file_type: FileType::Folder,
+        };
+        Backup::new(vec![data], "/backup".to_string(), String::new())
+    }
+
+    fn stored(id: &str) -> Option<SaveItem> {
+        load().unwrap().0.into_iter().find(|item| item.id == id)
+    }
+
+    fn manifest_rows(backup_id: &str) -> i64 {
+        with(|connection| {
+            connection.query_row(
+                "SELECT COUNT(*) FROM backup_manifest WHERE backup_id = ?1",
+                [backup_id],
+                |row| row.get(0),
+            )
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn sync_inserts_and_deletes_rows() {
+        let mut items = vec![item("insert")];
+        let mut snapshot = Snapshot::default();
+        let id = items[0].id.to_string();
+        let backup = backup();
+        items[0].backups.push(backup.clone());
+        items[0].runs.push(Run::new(
+            Trigger::Manual,
+            Outcome::Success,
+            String::new(),
+            backup.id.to_string(),
+        ));
+        sync(&mut items, &mut snapshot).unwrap();
+        let saved = stored(&id).unwrap();
+        assert_eq!(saved.monitors, items[0].monitors);
+        assert_eq!(saved.backups, vec![backup]);
+        assert_eq!(saved.backups[0].datas.len(), 1);
+        assert_eq!(saved.runs.len(), 1);
+        // 新建的存档不记录修改
+        assert!(changes(&id).unwrap().is_empty());
+
+        items[0].backups.clear();
+        items[0].runs.clear();
+        items[0].description = "edited".to_string();
+        sync(&mut items, &mut snapshot).unwrap();
+        let saved = stored(&id).unwrap();
+        assert!(saved.backups.is_empty());
+        assert!(saved.runs.is_empty());
+        assert_eq!(saved.description, "edited");
+        assert_eq!(changes(&id).unwrap().len(), 1);
+
+        sync(&mut [], &mut snapshot).unwrap();
+        assert!(stored(&id).is_none());
+    }
+
+    #[test]
+    fn sync_keeps_rows_written_by_others() {
+        let mut items = vec![item("shared")];
+        let mut snapshot = Snapshot::default();
+        let id = items[0].id.to_string();
+        sync(&mut items, &mut snapshot).unwrap();
+        // 另一个进程在此之后添加了备份
+        let mut other_items = items.clone();
+        let mut other_snapshot = snapshot.clone();
+        other_items[0].backups.push(backup());
+        sync(&mut other_items, &mut other_snapshot).unwrap();
+
+        items[0].description = "edited".to_string();
+        sync(&mut items, &mut snapshot).unwrap();
+        let saved = stored(&id).unwrap();
+        assert_eq!(saved.description, "edited");
+        assert_eq!(saved.backups, other_items[0].backups);
+    }
+
+    #[test]
+    fn manifest_removed_with_backup() {
+        let mut items = vec![item("manifest")];
+        let mut snapshot = Snapshot::default();
+        let backup = backup();
+        items[0].backups.push(backup.clone());
+        sync(&mut items, &mut snapshot).unwrap();
+        let file = FileEntry {
+            path: "game/slot1.sav".to_string(),
+            size: 6,
+            mtime: Some(1_000),
+            hash: "ce013625030ba8dba906f756967f9e9ca394464a".to_string(),
+        };
+        record_manifest(&backup.id, std::slice::from_ref(&file)).unwrap();
+        // 再次记录时替换已有的记录
+        record_manifest(&backup.id, &[file]).unwrap();
+        assert_eq!(manifest_rows(&backup.id), 1);
+
+        items[0].backups.clear();
+        sync(&mut items, &mut snapshot).unwrap();
+        assert_eq!(manifest_rows(&backup.id), 0);
+    }
+}
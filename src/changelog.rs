@@ -0,0 +1,383 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    catalog,
+    data::{Data, SaveItem},
+    log,
+};
+
+/// 每个存档保留的修改记录数量
+pub const CHANGE_LIMIT: usize = 100;
+/// 记录修改的字段，除了状态都是用户编辑的设置
+/// 配置（profile）由切换配置改变，撤销它不会恢复对应的文件，所以不记录
+const FIELDS: [&str; 14] = [
+    "name",
+    "status",
+    "description",
+    "startup_path",
+    "auto",
+    "monitors",
+    "hooks",
+    "retention",
+    "group",
+    "platform",
+    "tags",
+    "format",
+    "screenshot_folder",
+    "notify",
+];
+/// 归档、删除和自动备份的启停只记录，撤销时保持当前的状态
+const STATES: [&str; 2] = ["status", "auto.status"];
+
+/// 存档设置的一次修改，before 和 after 是 definition 的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Change {
+    pub id: String,
+    pub item_id: String,
+    pub time: i64,
+    pub before: Value,
+    pub after: Value,
+}
+impl Change {
+    pub fn diffs(&self) -> Vec<Diff> {
+        diff(&self.before, &self.after)
+    }
+
+    pub fn summary(&self) -> ChangeSummary {
+        ChangeSummary {
+            id: self.id.to_string(),
+            time: self.time,
+            diffs: self.diffs(),
+        }
+    }
+}
+
+/// 列表中显示的修改记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeSummary {
+    pub id: String,
+    pub time: i64,
+    pub diffs: Vec<Diff>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+impl Display for DiffKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DiffKind::Added => "Added",
+            DiffKind::Removed => "Removed",
+            DiffKind::Changed => "Changed",
+        })
+    }
+}
+
+/// 一个字段的修改，field 是 json 中的路径，例如 auto.cron、monitors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diff {
+    pub field: String,
+    pub kind: DiffKind,
+    pub before: String,
+    pub after: String,
+}
+impl Diff {
+    /// 状态的变化不能撤销
+    pub fn revertible(&self) -> bool {
+        !STATES.contains(&self.field.as_str())
+    }
+}
+
+/// 存档中记录修改的设置，见 FIELDS
+pub fn definition(item: &SaveItem) -> serde_json::Result<Value> {
+    serde_json::to_value(item).map(retain)
+}
+
+/// 只保留 FIELDS 中的字段，也用于读取旧的修改记录
+pub fn retain(value: Value) -> Value {
+    let Value::Object(mut object) = value else {
+        return value;
+    };
+    object.retain(|key, _| FIELDS.contains(&key.as_str()));
+    Value::Object(object)
+}
+
+/// 去掉 STATES 中的字段，撤销时不改变它们
+fn without_states(value: &Value) -> Value {
+    let mut value = value.clone();
+    if let Value::Object(object) = &mut value {
+        object.remove("status");
+        if let Some(Value::Object(auto)) = object.get_mut("auto") {
+            auto.remove("status");
+        }
+    }
+    value
+}
+
+/// 比较两个版本的设置，列表只列出添加和删除的元素
+pub fn diff(before: &Value, after: &Value) -> Vec<Diff> {
+    let mut diffs = vec![];
+    diff_value("", before, after, &mut diffs);
+    diffs
+}
+
+/// 撤销一次修改：只把这次修改涉及的字段改回修改前的值，之后的其他修改保持不变
+/// 撤销本身也会记录为一次修改，可以再次撤销
+pub fn revert(data: &mut Data, id: &str, change_id: &str) -> Result<SaveItem, String> {
+    let item_id = data
+        .find(id)
+        .ok_or(format!("save item not found: {}", id))?
+        .id
+        .to_string();
+    let change = catalog::find_change(change_id)?
+        .filter(|change| change.item_id == item_id)
+        .ok_or(format!("change not found: {}", change_id))?;
+    if !change.diffs().iter().any(Diff::revertible) {
+        return Err("status changes can't be undone".to_string());
+    }
+
+    let item = data
+        .find_mut(&item_id)
+        .ok_or(format!("save item not found: {}", id))?;
+    let backups = std::mem::take(&mut item.backups);
+    let runs = std::mem::take(&mut item.runs);
+    let reverted = serde_json::to_value(&*item).and_then(|mut value| {
+        let mut current = retain(value.clone());
+        undo(
+            &mut current,
+            &without_states(&change.before),
+            &without_states(&change.after),
+        );
+        merge(&mut value, current);
+        serde_json::from_value::<SaveItem>(value)
+    });
+    item.backups = backups;
+    item.runs = runs;
+    let reverted = reverted.map_err(|e| e.to_string())?;
+    // 只写回设置，配置、状态、备份和运行记录保持不变
+    data.set_monitor(item_id.to_string(), reverted);
+    let item = data
+        .find(&item_id)
+        .cloned()
+        .ok_or(format!("save item not found: {}", id))?;
+    log::log_info(format!(
+        "revert change, name={}, change={}",
+        item.name, change_id
+    ));
+    Ok(item)
+}
+
+/// 把设置写回完整的存档，auto 中没有的字段保持不变
+fn merge(item: &mut Value, definition: Value) {
+    let (Value::Object(item), Value::Object(definition)) = (item, definition) else {
+        return;
+    };
+    for (key, value) in definition {
+        match (item.get_mut(&key), value) {
+            (Some(Value::Object(current)), Value::Object(value)) if key == "auto" => {
+                current.extend(value);
+            }
+            (_, value) => {
+                item.insert(key, value);
+            }
+        }
+    }
+}
+
+fn diff_value(field: &str, before: &Value, after: &Value, diffs: &mut Vec<Diff>) {
+    if before == after {
+        return;
+    }
+    match (before, after) {
+        (Value::Object(b), Value::Object(a)) => {
+            let mut keys: Vec<&String> = b.keys().chain(a.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let field = if field.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", field, key)
+                };
+                diff_value(
+                    &field,
+                    b.get(key).unwrap_or(&Value::Null),
+                    a.get(key).unwrap_or(&Value::Null),
+                    diffs,
+                );
+            }
+        }
+        (Value::Array(b), Value::Array(a)) => {
+            for value in b.iter().filter(|value| !a.contains(value)) {
+                diffs.push(Diff {
+                    field: field.to_string(),
+                    kind: DiffKind::Removed,
+                    before: text(value),
+                    after: String::new(),
+                });
+            }
+            for value in a.iter().filter(|value| !b.contains(value)) {
+                diffs.push(Diff {
+                    field: field.to_string(),
+                    kind: DiffKind::Added,
+                    before: String::new(),
+                    after: text(value),
+                });
+            }
+        }
+        _ => diffs.push(Diff {
+            field: field.to_string(),
+            kind: DiffKind::Changed,
+            before: text(before),
+            after: text(after),
+        }),
+    }
+}
+
+/// 把 current 中 before -> after 的修改改回去，列表加回删除的元素、去掉添加的元素
+fn undo(current: &mut Value, before: &Value, after: &Value) {
+    if before == after {
+        return;
+    }
+    match (current, before, after) {
+        (Value::Object(current), Value::Object(b), Value::Object(a)) => {
+            for (key, value) in b {
+                let changed = a.get(key).unwrap_or(&Value::Null);
+                if value == changed {
+                    continue;
+                }
+                match current.get_mut(key) {
+                    Some(current) => undo(current, value, changed),
+                    None => {
+                        current.insert(key.to_string(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(current), Value::Array(b), Value::Array(a)) => {
+            current.retain(|value| !a.contains(value) || b.contains(value));
+            for value in b {
+                if !a.contains(value) && !current.contains(value) {
+                    current.push(value.clone());
+                }
+            }
+        }
+        (current, before, _) => *current = before.clone(),
+    }
+}
+
+/// 监听路径显示路径和类型，其他对象显示 json
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.to_string(),
+        Value::Object(object) => match (object.get("path"), object.get("backup_type")) {
+            (Some(Value::String(path)), Some(Value::String(kind))) => {
+                format!("{} ({})", path, kind)
+            }
+            _ => value.to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn diff_fields_and_lists() {
+        let before = json!({"name": "a", "auto": {"cron": "0 * * * * *"}, "tags": ["x", "y"]});
+        let after = json!({"name": "b", "auto": {"cron": "0 0 * * * *"}, "tags": ["y", "z"]});
+        let diffs: Vec<(String, DiffKind, String, String)> = diff(&before, &after)
+            .into_iter()
+            .map(|diff| (diff.field, diff.kind, diff.before, diff.after))
+            .collect();
+        let expected = [
+            ("auto.cron", DiffKind::Changed, "0 * * * * *", "0 0 * * * *"),
+            ("name", DiffKind::Changed, "a", "b"),
+            ("tags", DiffKind::Removed, "x", ""),
+            ("tags", DiffKind::Added, "", "z"),
+        ];
+        assert_eq!(diffs.len(), expected.len());
+        for (diff, (field, kind, before, after)) in diffs.iter().zip(expected) {
+            assert_eq!(diff, &(field.into(), kind, before.into(), after.into()));
+        }
+        assert!(diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn undo_keeps_later_changes() {
+        let before = json!({"name": "a", "auto": {"cron": "1"}, "tags": ["x"]});
+        let after = json!({"name": "a", "auto": {"cron": "2"}, "tags": ["x", "y"]});
+        // 这次修改之后又改了名称、添加了标签
+        let mut current = json!({"name": "b", "auto": {"cron": "2"}, "tags": ["x", "y", "z"]});
+        undo(&mut current, &before, &after);
+        assert_eq!(
+            current,
+            json!({"name": "b", "auto": {"cron": "1"}, "tags": ["x", "z"]})
+        );
+    }
+
+    #[test]
+    fn retain_drops_runtime_state() {
+        let value = json!({
+            "name": "a",
+            "status": "Archive",
+            "profile": "Speedrun",
+            "backups": [],
+            "auto": {"cron": "1", "status": "Running"},
+        });
+        assert_eq!(
+            retain(value),
+            json!({"name": "a", "status": "Archive", "auto": {"cron": "1", "status": "Running"}})
+        );
+    }
+
+    #[test]
+    fn undo_skips_states() {
+        let before = json!({"status": "Valid", "auto": {"cron": "1", "status": "Stop"}});
+        let after = json!({"status": "Archive", "auto": {"cron": "2", "status": "Running"}});
+        let diffs = diff(&before, &after);
+        let fields: Vec<(&str, bool)> = diffs
+            .iter()
+            .map(|diff| (diff.field.as_str(), diff.revertible()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("auto.cron", true),
+                ("auto.status", false),
+                ("status", false)
+            ]
+        );
+        let mut current = after.clone();
+        undo(
+            &mut current,
+            &without_states(&before),
+            &without_states(&after),
+        );
+        assert_eq!(
+            current,
+            json!({"status": "Archive", "auto": {"cron": "1", "status": "Running"}})
+        );
+    }
+
+    #[test]
+    fn merge_keeps_auto_status() {
+        let mut item =
+            json!({"name": "a", "profile": "p", "auto": {"cron": "1", "status": "Running"}});
+        merge(&mut item, json!({"name": "b", "auto": {"cron": "2"}}));
+        assert_eq!(
+            item,
+            json!({"name": "b", "profile": "p", "auto": {"cron": "2", "status": "Running"}})
+        );
+    }
+}
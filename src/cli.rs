@@ -0,0 +1,457 @@
+use std::sync::mpsc::{self, Sender};
+
+use serde_json::Value;
+
+use crate::{
+    backup::{BackupUpdate, Trigger},
+    bulk::{BackupSelector, BulkAction, BulkRequest},
+    bundle::ImportMode,
+    entity::{Command, Reply},
+    filter::{ItemFilter, SortBy},
+    health::{self, Health, HealthStatus},
+    log::{Level, LogFilter},
+};
+
+pub const USAGE: &str = "Usage: auto_backup [command] [args]
+
+Without command, open the main window.
+
+Commands:
+  list [keyword] [--tag <tag>]... [--group <group>] [--platform <platform>]
+       [--sort name|last-backup|size]
+                                 list save items, multiple tags must all match
+  backups <item> [keyword]       list backups of a save item, filtered by title, remark and notes
+  search <query>                 search save items, backup titles, remarks, notes, dates
+                                 and the file names in backups of all save items
+  backup <item> [remark]         backup now
+  restore <item> <backup_id>     overwrite the save with a backup
+  meta <item> <backup_id> [--title <title>] [--notes <notes>] [--remark <remark>]
+       [--rating <0-5>] [--pin|--unpin] [--screenshot <file>]
+                                 edit the metadata of a backup, an empty --screenshot removes it
+  file-history <item> <path>    list the backups containing a monitored file
+                                 with its size, modified time and hash
+  restore-file <item> <backup_id> <path>
+                                 overwrite only this file with its version in a backup
+  bulk delete|protect|unprotect|verify <item> [backup_id...]
+       [--trigger manual|auto|pre-restore] [--older-than <days>] [--protected|--unprotected]
+       [--keyword <text>] [--dry-run]
+                                 apply an action to the given backups, or to all backups
+                                 matching the conditions, --dry-run only lists them
+  bulk export <item> <file> [backup_id...] [conditions]
+                                 export the selected backups
+  protect <item> <backup_id>     protect a backup
+  unprotect <item> <backup_id>   cancel the protection of a backup
+  delete <item> <backup_id>      move a backup to the trash
+  history <item>                 list the commits of a git-format save item
+  diff <item> <backup_id>        show the changes of a git-format backup
+  blame <item> <backup_id> [file]
+                                 show who changed each line of a file in a git-format backup,
+                                 list the files without [file]
+  changes <item>                 list the changes of the settings and monitored paths of a save item
+  revert <item> <change_id>      undo a change, later changes are kept
+  profiles <item>                list the profiles of a save item
+  profile add <item> <name>      add a profile
+  switch <item> <profile>        backup the current files into the current profile,
+                                 then restore the latest backup of <profile>
+  scan [--add [name...]]         find installed games in the game database,
+                                 --add creates save items for them
+  steam                          list installed Steam games with their Proton prefixes
+  steam path <appid> <path>      resolve a Windows-style path inside the Proton prefix
+  storage                        show storage usage
+  prune <item>                   move the backups beyond the retention policy to the trash
+  rescan [--apply]               compare the backup folders with the records,
+                                 --apply adopts the folders without records
+  export-data <file>             export all save items, backups and runs in the data.json format
+  logs [keyword] [--level debug|info|warn|error] [--item <item>] [--limit <n>]
+                                 show the newest logs, at least --level, 500 at most by default
+  check [item]                   check the health of save items: missing monitor paths,
+                                 failing auto backups and stale backups,
+                                 exits with 1 on warnings and 2 on errors
+  notify test                    send a test notification through the enabled channels
+  trash                          list the trash
+  untrash <trash_id>             restore from the trash
+  purge <trash_id>               delete from the trash permanently
+  export <item> <file> [backup_id...]
+                                 export a save item with its backups
+  import <file> [--rename|--merge]
+                                 import a save item exported by AutoBackup
+  jobs                           show auto backup jobs
+  daemon                         run in background without the main window
+  show                           open the main window
+  quit                           quit the running instance
+  help                           show this message
+
+<item> can be the id or the name of a save item.
+If AutoBackup is already running, the command is forwarded to it.";
+
+/// 把命令行参数转换成 Command
+pub fn command(args: &[String], reply: Reply) -> Result<Command, String> {
+    let arg = |index: usize, name: &str| {
+        args.get(index)
+            .map(|s| s.to_string())
+            .ok_or(format!("missing argument <{}>\n\n{}", name, USAGE))
+    };
+    let name = args.first().map(|s| s.as_str()).unwrap_or_default();
+    let cmd = match name {
+        "list" => Command::ListSaveItems {
+            filter: list_filter(&args[1..])?,
+            reply,
+        },
+        "backups" => Command::ListBackups {
+            id: arg(1, "item")?,
+            keyword: args.get(2).cloned().unwrap_or_default(),
+            reply,
+        },
+        "search" => Command::Search {
+            query: arg(1, "query")?,
+            reply,
+        },
+        "meta" => Command::UpdateBackup {
+            id: arg(1, "item")?,
+            backup_id: arg(2, "backup_id")?,
+            update: backup_update(&args[3..])?,
+            reply: Some(reply),
+        },
+        "backup" => Command::Backup {
+            id: arg(1, "item")?,
+            remark: args.get(2).cloned().unwrap_or("CLI Backup".to_string()),
+            reply: Some(reply),
+        },
+        "restore" => Command::Restore {
+            id: arg(1, "item")?,
+            backup_id: arg(2, "backup_id")?,
+            reply: Some(reply),
+        },
+        "file-history" => Command::FileHistory {
+            id: arg(1, "item")?,
+            path: arg(2, "path")?,
+            reply,
+        },
+        "restore-file" => Command::RestoreFile {
+            id: arg(1, "item")?,
+            backup_id: arg(2, "backup_id")?,
+            path: arg(3, "path")?,
+            reply: Some(reply),
+        },
+        "protect" | "unprotect" => Command::ProtectBackup {
+            id: arg(1, "item")?,
+            backup_id: arg(2, "backup_id")?,
+            protect: name == "protect",
+            reply: Some(reply),
+        },
+        "delete" => Command::DeleteBackup {
+            id: arg(1, "item")?,
+            backup_id: arg(2, "backup_id")?,
+            reply: Some(reply),
+        },
+        "bulk" => {
+            let action = match arg(1, "action")?.as_str() {
+                "delete" => BulkAction::Delete,
+                "protect" => BulkAction::Protect,
+                "unprotect" => BulkAction::Unprotect,
+                "verify" => BulkAction::Verify,
+                "export" => BulkAction::Export {
+                    path: arg(3, "file")?,
+                },
+                action => return Err(format!("unknown command: bulk {}\n\n{}", action, USAGE)),
+            };
+            let skip = if matches!(action, BulkAction::Export { .. }) {
+                4
+            } else {
+                3
+            };
+            Command::BulkBackups {
+                id: arg(2, "item")?,
+                request: bulk_request(action, &args[skip.min(args.len())..])?,
+                reply: Some(reply),
+            }
+        }
+        "export" => Command::Export {
+            id: arg(1, "item")?,
+            path: arg(2, "file")?,
+            backup_ids: args.iter().skip(3).cloned().collect(),
+            reply: Some(reply),
+        },
+        "import" => Command::Import {
+            path: arg(1, "file")?,
+            mode: match args.get(2).map(|s| s.as_str()) {
+                None => ImportMode::Strict,
+                Some("--rename") => ImportMode::Rename,
+                Some("--merge") => ImportMode::Merge,
+                Some(flag) => return Err(format!("unknown flag: {}\n\n{}", flag, USAGE)),
+            },
+            reply: Some(reply),
+        },
+        "history" => Command::GitHistory {
+            id: arg(1, "item")?,
+            reply,
+        },
+        "diff" => Command::GitDiff {
+            id: arg(1, "item")?,
+            backup_id: arg(2, "backup_id")?,
+            reply,
+        },
+        "blame" => Command::GitBlame {
+            id: arg(1, "item")?,
+            backup_id: arg(2, "backup_id")?,
+            file: args.get(3).cloned(),
+            reply,
+        },
+        "profiles" => Command::ListProfiles {
+            id: arg(1, "item")?,
+            reply,
+        },
+        "profile" => match args.get(1).map(|s| s.as_str()) {
+            Some("add") => Command::AddProfile {
+                id: arg(2, "item")?,
+                name: arg(3, "name")?,
+                reply: Some(reply),
+            },
+            Some(sub) => return Err(format!("unknown command: profile {}\n\n{}", sub, USAGE)),
+            None => return Err(format!("missing command: profile add\n\n{}", USAGE)),
+        },
+        "switch" => Command::SwitchProfile {
+            id: arg(1, "item")?,
+            profile: arg(2, "profile")?,
+            reply: Some(reply),
+        },
+        "jobs" => Command::JobStatus { reply },
+        "scan" => match args.get(1).map(|s| s.as_str()) {
+            None => Command::ScanGames { reply },
+            Some("--add") => Command::AddGames {
+                names: args.iter().skip(2).cloned().collect(),
+                reply: Some(reply),
+            },
+            Some(flag) => return Err(format!("unknown flag: {}\n\n{}", flag, USAGE)),
+        },
+        "steam" => match args.get(1).map(|s| s.as_str()) {
+            None => Command::SteamApps { reply },
+            Some("path") => Command::SteamPath {
+                app_id: arg(2, "appid")?
+                    .parse()
+                    .map_err(|_| format!("invalid appid\n\n{}", USAGE))?,
+                path: arg(3, "path")?,
+                reply,
+            },
+            Some(sub) => return Err(format!("unknown command: steam {}\n\n{}", sub, USAGE)),
+        },
+        "storage" => Command::Storage { reply },
+        "prune" => Command::Prune {
+            id: arg(1, "item")?,
+            reply: Some(reply),
+        },
+        "rescan" => Command::Rescan {
+            apply: match args.get(1).map(|s| s.as_str()) {
+                None => false,
+                Some("--apply") => true,
+                Some(flag) => return Err(format!("unknown flag: {}\n\n{}", flag, USAGE)),
+            },
+            reply: Some(reply),
+        },
+        "changes" => Command::ItemChanges {
+            id: arg(1, "item")?,
+            reply,
+        },
+        "revert" => Command::RevertChange {
+            id: arg(1, "item")?,
+            change_id: arg(2, "change_id")?,
+            reply: Some(reply),
+        },
+        "export-data" => Command::ExportData {
+            path: arg(1, "file")?,
+            reply: Some(reply),
+        },
+        "logs" => Command::Logs {
+            filter: log_filter(&args[1..])?,
+            reply,
+        },
+        "check" => Command::Health {
+            id: args.get(1).cloned(),
+            reply,
+        },
+        "notify" => match args.get(1).map(|s| s.as_str()) {
+            Some("test") => Command::TestNotify { reply },
+            Some(sub) => return Err(format!("unknown command: notify {}\n\n{}", sub, USAGE)),
+            None => return Err(format!("missing command: notify test\n\n{}", USAGE)),
+        },
+        "trash" => Command::ListTrash { reply },
+        "untrash" => Command::RestoreTrash {
+            id: arg(1, "trash_id")?,
+            reply: Some(reply),
+        },
+        "purge" => Command::PurgeTrash {
+            id: arg(1, "trash_id")?,
+            reply: Some(reply),
+        },
+        "show" => Command::Show { reply: Some(reply) },
+        "quit" => Command::Quit { reply: Some(reply) },
+        _ => return Err(format!("unknown command: {}\n\n{}", name, USAGE)),
+    };
+    Ok(cmd)
+}
+
+/// list 命令的筛选参数
+fn list_filter(args: &[String]) -> Result<ItemFilter, String> {
+    let mut filter = ItemFilter::default();
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or(format!("missing value of {}\n\n{}", flag, USAGE))
+        };
+        match flag.as_str() {
+            "--tag" => filter.tags.push(value()?),
+            "--group" => filter.group = value()?,
+            "--platform" => filter.platform = value()?,
+            "--sort" => filter.sort = SortBy::parse(&value()?)?,
+            _ if !flag.starts_with("--") && filter.keyword.is_empty() => {
+                filter.keyword = flag.to_string()
+            }
+            _ => return Err(format!("unknown flag: {}\n\n{}", flag, USAGE)),
+        }
+    }
+    Ok(filter)
+}
+
+/// logs 命令的筛选参数
+fn log_filter(args: &[String]) -> Result<LogFilter, String> {
+    let mut filter = LogFilter::default();
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or(format!("missing value of {}\n\n{}", flag, USAGE))
+        };
+        match flag.as_str() {
+            "--level" => filter.level = Level::parse(&value()?)?,
+            "--item" => filter.item = value()?,
+            "--limit" => {
+                filter.limit = value()?
+                    .parse()
+                    .map_err(|_| format!("invalid limit\n\n{}", USAGE))?
+            }
+            _ if !flag.starts_with("--") && filter.keyword.is_empty() => {
+                filter.keyword = flag.to_string()
+            }
+            _ => return Err(format!("unknown flag: {}\n\n{}", flag, USAGE)),
+        }
+    }
+    Ok(filter)
+}
+
+/// bulk 命令的备份和条件
+fn bulk_request(action: BulkAction, args: &[String]) -> Result<BulkRequest, String> {
+    let mut selector = BackupSelector::default();
+    let mut dry_run = false;
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or(format!("missing value of {}\n\n{}", flag, USAGE))
+        };
+        match flag.as_str() {
+            "--trigger" => selector.trigger = Some(Trigger::parse(&value()?)?),
+            "--older-than" => {
+                selector.older_than_days = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| format!("invalid days\n\n{}", USAGE))?,
+                )
+            }
+            "--protected" => selector.protected = Some(true),
+            "--unprotected" => selector.protected = Some(false),
+            "--keyword" => selector.keyword = value()?,
+            "--dry-run" => dry_run = true,
+            _ if !flag.starts_with("--") => selector.ids.push(flag.to_string()),
+            _ => return Err(format!("unknown flag: {}\n\n{}", flag, USAGE)),
+        }
+    }
+    Ok(BulkRequest {
+        action,
+        selector,
+        dry_run,
+    })
+}
+
+/// meta 命令的参数
+fn backup_update(args: &[String]) -> Result<BackupUpdate, String> {
+    let mut update = BackupUpdate::default();
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or(format!("missing value of {}\n\n{}", flag, USAGE))
+        };
+        match flag.as_str() {
+            "--title" => update.title = Some(value()?),
+            "--notes" => update.notes = Some(value()?),
+            "--remark" => update.remark = Some(value()?),
+            "--rating" => {
+                update.rating = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| format!("invalid rating\n\n{}", USAGE))?,
+                )
+            }
+            "--pin" => update.pinned = Some(true),
+            "--unpin" => update.pinned = Some(false),
+            "--screenshot" => update.screenshot = Some(value()?),
+            _ => return Err(format!("unknown flag: {}\n\n{}", flag, USAGE)),
+        }
+    }
+    Ok(update)
+}
+
+/// 执行命令行参数对应的命令，等待 command_proc 的回复
+pub fn run(args: &[String], sender: &Sender<Command>) -> Result<Value, String> {
+    if matches!(
+        args.first().map(|s| s.as_str()),
+        Some("help" | "-h" | "--help")
+    ) {
+        return Ok(Value::String(USAGE.to_string()));
+    }
+    let (tx, rx) = mpsc::channel();
+    let cmd = command(args, tx)?;
+    sender.send(cmd).map_err(|e| e.to_string())?;
+    rx.recv().map_err(|e| e.to_string())?
+}
+
+/// 输出结果并返回退出码，check 命令按最严重的健康状态返回
+pub fn finish(args: &[String], result: Result<Value, String>) -> i32 {
+    let status = match (&result, args.first().map(|s| s.as_str())) {
+        (Ok(value), Some("check")) => serde_json::from_value::<Vec<Health>>(value.clone())
+            .map(|healths| health::worst(&healths))
+            .unwrap_or_default(),
+        _ => HealthStatus::Ok,
+    };
+    let code = print(result);
+    match status {
+        HealthStatus::Ok => code,
+        HealthStatus::Warn => 1,
+        HealthStatus::Error => 2,
+    }
+}
+
+pub fn print(result: Result<Value, String>) -> i32 {
+    match result {
+        Ok(Value::String(text)) => {
+            println!("{}", text);
+            0
+        }
+        Ok(value) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&value).unwrap_or_default()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt::Display;
+
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::util;
+
+const TIME_FMT: &str = "%H:%M";
+
+/// 自动备份的执行条件，任何一条不满足都会跳过本次备份
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Condition {
+    pub quiet_hours: bool,
+    pub quiet_start: String, // HH:MM
+    pub quiet_end: String,   // HH:MM
+    pub skip_on_battery: bool,
+    pub min_free_mb: u64, // 0 表示不检查
+    pub process: String,
+    pub process_rule: ProcessRule,
+}
+impl Default for Condition {
+    fn default() -> Self {
+        Self {
+            quiet_hours: false,
+            quiet_start: "22:00".to_string(),
+            quiet_end: "08:00".to_string(),
+            skip_on_battery: false,
+            min_free_mb: 0,
+            process: String::new(),
+            process_rule: ProcessRule::Ignore,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ProcessRule {
+    #[default]
+    Ignore,
+    Running,    // 进程运行时才备份
+    NotRunning, // 进程未运行时才备份
+}
+impl Display for ProcessRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProcessRule::Ignore => "Ignore",
+            ProcessRule::Running => "Only while running",
+            ProcessRule::NotRunning => "Only while not running",
+        })
+    }
+}
+
+impl Condition {
+    /// 检查是否可以执行，不可以时返回跳过的原因
+    pub fn check(&self) -> Result<(), String> {
+        if self.quiet_hours && self.in_quiet_hours(Local::now().time()) {
+            return Err(format!(
+                "in quiet hours {} - {}",
+                self.quiet_start, self.quiet_end
+            ));
+        }
+        if self.skip_on_battery && on_battery() {
+            return Err("running on battery".to_string());
+        }
+        if self.min_free_mb > 0 {
+            if let Some(free) = free_space_mb(&format!("{}/data", util::current_dir())) {
+                if free < self.min_free_mb {
+                    return Err(format!(
+                        "free space {}MB is below {}MB",
+                        free, self.min_free_mb
+                    ));
+                }
+            }
+        }
+        let process = self.process.trim();
+        if !process.is_empty() {
+            match self.process_rule {
+                ProcessRule::Ignore => {}
+                ProcessRule::Running => {
+                    if !process_running(process) {
+                        return Err(format!("process {} is not running", process));
+                    }
+                }
+                ProcessRule::NotRunning => {
+                    if process_running(process) {
+                        return Err(format!("process {} is running", process));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn in_quiet_hours(&self, now: NaiveTime) -> bool {
+        let (Ok(start), Ok(end)) = (
+            NaiveTime::parse_from_str(&self.quiet_start, TIME_FMT),
+            NaiveTime::parse_from_str(&self.quiet_end, TIME_FMT),
+        ) else {
+            return false;
+        };
+        if start <= end {
+            start <= now && now < end
+        } else {
+            // 跨越午夜，例如 22:00 - 08:00
+            now >= start || now < end
+        }
+    }
+
+    pub fn valid_time(time: &str) -> bool {
+        NaiveTime::parse_from_str(time, TIME_FMT).is_ok()
+    }
+}
+
+/// 是否使用电池供电，读取 /sys/class/power_supply
+#[cfg(target_os = "linux")]
+pub fn on_battery() -> bool {
+    let read = |path: &std::path::Path, name: &str| {
+        std::fs::read_to_string(path.join(name))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut discharging = false;
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match read(&path, "type").as_str() {
+            "Mains" | "USB" if read(&path, "online") == "1" => return false,
+            "Battery" if read(&path, "status") == "Discharging" => discharging = true,
+            _ => {}
+        }
+    }
+    discharging
+}
+#[cfg(not(target_os = "linux"))]
+pub fn on_battery() -> bool {
+    false
+}
+
+/// 路径所在磁盘的剩余空间，单位 MB，路径不存在时查询最近的已存在的上级目录
+pub fn free_space_mb(path: &str) -> Option<u64> {
+    let existing = std::path::Path::new(path)
+        .ancestors()
+        .find(|dir| dir.exists())?;
+    fs2::available_space(existing).ok().map(|v| v / 1024 / 1024)
+}
+
+/// 进程是否在运行，按进程名匹配，不区分大小写
+#[cfg(target_os = "linux")]
+pub fn process_running(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_pid = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .map(|f| f.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or(false);
+        if !is_pid {
+            continue;
+        }
+        if let Ok(comm) = std::fs::read_to_string(path.join("comm")) {
+            if comm.trim().to_lowercase() == name {
+                return true;
+            }
+        }
+        // comm 最长 15 个字符，再用命令行的程序名匹配一次
+        if let Ok(cmdline) = std::fs::read(path.join("cmdline")) {
+            let program = cmdline.split(|b| *b == 0).next().unwrap_or_default();
+            let program = String::from_utf8_lossy(program).replace('\\', "/");
+            if let Some(program) = program.rsplit('/').next() {
+                if program.to_lowercase() == name {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+#[cfg(windows)]
+pub fn process_running(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let output = std::process::Command::new("tasklist")
+        .args(["/FO", "CSV", "/NH"])
+        .output();
+    if let Ok(output) = output {
+        let text = String::from_utf8_lossy(&output.stdout).to_lowercase();
+        for line in text.lines() {
+            let image = line.split(',').next().unwrap_or_default().trim_matches('"');
+            if image == name || image.trim_end_matches(".exe") == name {
+                return true;
+            }
+        }
+    }
+    false
+}
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn process_running(_name: &str) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet(start: &str, end: &str) -> Condition {
+        Condition {
+            quiet_hours: true,
+            quiet_start: start.to_string(),
+            quiet_end: end.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn at(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, TIME_FMT).unwrap()
+    }
+
+    #[test]
+    fn quiet_hours_same_day() {
+        let condition = quiet("09:00", "18:00");
+        assert!(condition.in_quiet_hours(at("09:00")));
+        assert!(condition.in_quiet_hours(at("12:30")));
+        assert!(!condition.in_quiet_hours(at("18:00")));
+        assert!(!condition.in_quiet_hours(at("08:59")));
+    }
+
+    #[test]
+    fn quiet_hours_across_midnight() {
+        let condition = quiet("22:00", "08:00");
+        assert!(condition.in_quiet_hours(at("23:15")));
+        assert!(condition.in_quiet_hours(at("00:00")));
+        assert!(condition.in_quiet_hours(at("07:59")));
+        assert!(!condition.in_quiet_hours(at("08:00")));
+        assert!(!condition.in_quiet_hours(at("21:59")));
+    }
+
+    #[test]
+    fn quiet_hours_invalid_time() {
+        assert!(!quiet("25:00", "08:00").in_quiet_hours(at("23:00")));
+        assert!(!quiet("22:00", "").in_quiet_hours(at("23:00")));
+        assert!(!Condition::valid_time("8"));
+        assert!(Condition::valid_time("08:05"));
+    }
+
+    #[test]
+    fn free_space_of_missing_dir() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let missing = dir.join("nested");
+        assert!(free_space_mb(&missing.to_string_lossy()).is_some());
+        assert!(!dir.exists());
+    }
+}
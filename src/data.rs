@@ -1,42 +1,195 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{backup::Backup, entity::AutoBackup, util};
+use crate::{
+    backup::{Backup, BackupFormat, BackupUpdate, Run},
+    catalog::{self, Snapshot},
+    condition::Condition,
+    entity::AutoBackup,
+    hook::Hooks,
+    log,
+    notify::NotifyRule,
+    storage::Retention,
+    util,
+};
 
 const PATH: &str = "./data/data.json";
+const BACKUP_PATH: &str = "./data/data.json.bak"; // 迁移到索引后的 data.json
+const RUN_HISTORY_LIMIT: usize = 200; // 每个存档保留的运行记录数量
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Data {
     pub monitors: Vec<SaveItem>,
+    #[serde(skip)]
+    saved: Snapshot, // 上次写入索引的内容，保存时只写入变化的部分
 }
 impl Default for Data {
     fn default() -> Self {
-        if let Ok(data) = util::read_data(PATH) {
-            if let Ok(data) = serde_json::from_str::<Data>(&data) {
-                data
-            } else {
-                Self::new()
+        let json = util::read_data(PATH)
+            .ok()
+            .and_then(|data| serde_json::from_str::<Data>(&data).ok());
+        let (monitors, saved) = match catalog::load() {
+            Ok(catalog) => catalog,
+            Err(e) => {
+                log::log_err(format!("load catalog error: {}", e));
+                // 不能用 Data::default，读取失败时会重复读取
+                return match json {
+                    Some(json) => json,
+                    None => Self::new(),
+                };
             }
-        } else {
-            Self::new()
+        };
+        let mut data = Self { monitors, saved };
+        if let Some(json) = json {
+            data.migrate(json);
         }
+        data
     }
 }
 impl Data {
     fn new() -> Self {
         Self {
             monitors: Default::default(),
+            saved: Default::default(),
+        }
+    }
+
+    /// 第一次启动或者上次写入索引失败时，以 data.json 为准写入索引，成功后改名为 data.json.bak
+    fn migrate(&mut self, json: Data) {
+        self.monitors = json.monitors;
+        match catalog::sync(&mut self.monitors, &mut self.saved) {
+            Ok(_) => {
+                let _ = std::fs::rename(PATH, BACKUP_PATH);
+                log::log_info(format!(
+                    "migrate {} save items from data.json to the catalog",
+                    self.monitors.len()
+                ));
+            }
+            Err(e) => log::log_err(format!("migrate data.json error: {}", e)),
         }
     }
 
     pub fn save(&mut self) {
-        util::check_dir_and_create("./data");
-        let _ = util::write_data(PATH, serde_json::to_string(&self).unwrap());
+        // 写入索引失败时保存到 data.json，下次启动时再写入索引
+        // 快照只在写入成功后更新，这期间的修改记录会在下次写入成功时合并成一条，不会丢失
+        if let Err(e) = catalog::sync(&mut self.monitors, &mut self.saved) {
+            log::log_err(format!(
+                "save catalog error, the change history is kept until the next save: {}",
+                e
+            ));
+            util::check_dir_and_create("./data");
+            let _ = util::write_data(PATH, serde_json::to_string(&self).unwrap());
+        }
+    }
+
+    /// 导出成和 data.json 相同格式的 json，兼容旧版本和外部工具
+    pub fn export(&self, path: &str) -> Result<serde_json::Value, String> {
+        let json = serde_json::to_string_pretty(&self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())?;
+        Ok(serde_json::json!({
+            "path": path,
+            "items": self.monitors.len(),
+            "backups": self.monitors.iter().map(|item| item.backups.len()).sum::<usize>(),
+        }))
     }
 
     pub fn list(&self) -> Vec<SaveItem> {
         self.monitors.to_vec()
     }
 
+    /// 根据 id 或名称查找存档
+    pub fn find(&self, key: &str) -> Option<&SaveItem> {
+        self.monitors
+            .iter()
+            .find(|item| item.id == key)
+            .or_else(|| self.monitors.iter().find(|item| item.name == key))
+    }
+
+    pub fn find_mut(&mut self, key: &str) -> Option<&mut SaveItem> {
+        let id = self.find(key)?.id.to_string();
+        self.monitors.iter_mut().find(|item| item.id == id)
+    }
+
+    pub fn protect_backup(
+        &mut self,
+        id: &str,
+        backup_id: &str,
+        protect: bool,
+    ) -> Result<SaveItem, String> {
+        let item = self
+            .find_mut(id)
+            .ok_or(format!("save item not found: {}", id))?;
+        let backup = item
+            .backups
+            .iter_mut()
+            .find(|backup| backup.id == backup_id)
+            .ok_or(format!("backup not found: {}", backup_id))?;
+        backup.protect = protect;
+        let item = item.clone();
+        self.save();
+        Ok(item)
+    }
+
+    /// 修改备份的标题、笔记、星级等信息
+    pub fn update_backup(
+        &mut self,
+        id: &str,
+        backup_id: &str,
+        update: BackupUpdate,
+    ) -> Result<SaveItem, String> {
+        let item = self
+            .find_mut(id)
+            .ok_or(format!("save item not found: {}", id))?;
+        let backup = item
+            .backups
+            .iter_mut()
+            .find(|backup| backup.id == backup_id)
+            .ok_or(format!("backup not found: {}", backup_id))?;
+        update.apply(backup)?;
+        let item = item.clone();
+        self.save();
+        Ok(item)
+    }
+
+    /// 移除备份记录，受保护的备份不能移除，返回移除后的存档和被移除的备份
+    pub fn remove_backup(
+        &mut self,
+        id: &str,
+        backup_id: &str,
+    ) -> Result<(SaveItem, Backup), String> {
+        let item = self
+            .find_mut(id)
+            .ok_or(format!("save item not found: {}", id))?;
+        let index = item
+            .backups
+            .iter()
+            .position(|backup| backup.id == backup_id)
+            .ok_or(format!("backup not found: {}", backup_id))?;
+        if item.backups[index].protect {
+            return Err("Please cancel the protection first".to_string());
+        }
+        let backup = item.backups.remove(index);
+        let item = item.clone();
+        self.save();
+        Ok((item, backup))
+    }
+
+    /// 按时间顺序插回备份记录，返回更新后的存档
+    pub fn insert_backup(&mut self, id: &str, backup: Backup) -> Result<SaveItem, String> {
+        let item = self
+            .find_mut(id)
+            .ok_or(format!("save item not found: {}", id))?;
+        // 备份按时间倒序排列
+        let index = item
+            .backups
+            .iter()
+            .position(|b| b.time < backup.time)
+            .unwrap_or(item.backups.len());
+        item.backups.insert(index, backup);
+        let item = item.clone();
+        self.save();
+        Ok(item)
+    }
+
     pub fn push_monitor(&mut self, monitor: SaveItem) {
         self.monitors.push(monitor);
         self.save();
@@ -62,20 +215,41 @@ impl Data {
            }
        }
     */
+    /// 只更新存档的设置，备份、运行记录、配置以当前数据为准
+    /// 编辑页面的存档可能是打开时的副本，期间新增的备份和运行记录不能被覆盖
     pub fn set_monitor(&mut self, id: String, monitor: SaveItem) {
         for item in &mut self.monitors {
             if item.id == id {
-                // item.path = monitor.path;
-                // item.name = monitor.name;
-                // item.description = monitor.description;
-                // item.cron = monitor.cron;
-                // item.status = monitor.status;
-                *item = SaveItem { ..monitor };
+                item.name = monitor.name;
+                item.description = monitor.description;
+                item.startup_path = monitor.startup_path;
+                item.status = monitor.status;
+                item.auto = monitor.auto;
+                item.monitors = monitor.monitors;
+                item.hooks = monitor.hooks;
+                item.retention = monitor.retention;
+                item.group = monitor.group;
+                item.platform = monitor.platform;
+                item.tags = monitor.tags;
+                item.format = monitor.format;
+                item.screenshot_folder = monitor.screenshot_folder;
+                item.notify = monitor.notify;
                 self.save();
                 break;
             }
         }
     }
+
+    /// 替换一条备份记录，存档的其他内容不变
+    pub fn set_backup(&mut self, id: &str, backup: Backup) {
+        let Some(item) = self.find_mut(id) else {
+            return;
+        };
+        if let Some(saved) = item.backups.iter_mut().find(|b| b.id == backup.id) {
+            *saved = backup;
+            self.save();
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -87,7 +261,30 @@ pub struct SaveItem {
     pub status: Status,
     pub auto: Auto,
     pub monitors: Vec<Monitor>,
+    #[serde(default)]
     pub backups: Vec<Backup>,
+    #[serde(default)]
+    pub runs: Vec<Run>, // 运行记录，新的在前
+    #[serde(default)]
+    pub hooks: Hooks, // 备份、恢复前后执行的命令
+    #[serde(default)]
+    pub retention: Retention, // 备份保留策略
+    #[serde(default)]
+    pub group: String, // 侧边栏分组，为空时不分组
+    #[serde(default)]
+    pub platform: String, // 平台，例如 Steam、Switch 模拟器
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub profiles: Vec<String>, // 除默认配置外的配置名称
+    #[serde(default)]
+    pub profile: String, // 当前使用的配置，为空时是默认配置
+    #[serde(default)]
+    pub format: BackupFormat, // 新备份的保存方式
+    #[serde(default)]
+    pub screenshot_folder: String, // 备份时从这里附加最新的截图，为空时使用 Steam 的截图目录
+    #[serde(default)]
+    pub notify: NotifyRule, // 备份成功、失败、跳过时是否通知
 }
 impl SaveItem {
     pub fn new() -> Self {
@@ -96,6 +293,40 @@ impl SaveItem {
             ..Self::default()
         }
     }
+
+    pub fn push_run(&mut self, run: Run) {
+        self.runs.insert(0, run);
+        self.runs.truncate(RUN_HISTORY_LIMIT);
+    }
+
+    /// 所有备份的大小和文件数量
+    pub fn storage(&self) -> (u64, u64) {
+        self.backups.iter().fold((0, 0), |(size, files), backup| {
+            (size + backup.size, files + backup.files)
+        })
+    }
+
+    /// 列表展示用的概要信息，不包含备份明细
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "description": self.description,
+            "group": self.group,
+            "platform": self.platform,
+            "tags": self.tags,
+            "profile": self.profile,
+            "profiles": self.profiles,
+            "status": self.status,
+            "auto": self.auto,
+            "format": self.format,
+            "monitors": self.monitors,
+            "backups": self.backups.len(),
+            "size": self.storage().0,
+            "last_backup": self.backups.first().map(|backup| backup.time),
+            "last_run": self.runs.first(),
+        })
+    }
 }
 impl SaveItem {
     pub fn save(self, app: &mut AutoBackup) {
@@ -108,7 +339,7 @@ impl SaveItem {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Monitor {
     pub path: String,
     pub backup_type: FileType,
@@ -119,6 +350,8 @@ pub struct Auto {
     pub cron: String,
     pub status: AutoStatus,
     pub open: bool,
+    #[serde(default)]
+    pub condition: Condition, // 执行条件
 }
 impl ToString for AutoStatus {
     fn to_string(&self) -> String {
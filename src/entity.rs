@@ -1,21 +1,224 @@
-use std::sync::mpsc::Sender;
+use std::collections::HashSet;
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex};
 
 use egui_notify::Toasts;
 
+use crate::background::WindowHandle;
+use crate::backup::BackupUpdate;
+use crate::bulk::BulkRequest;
+use crate::bundle::ImportMode;
+use crate::catalog::{BackupSort, Page, PageQuery};
+use crate::condition::Condition;
 use crate::data::{Data, SaveItem};
+use crate::filter::ItemFilter;
 use crate::il8n::Translator;
 use crate::job::JobHandle;
+use crate::log::{Entry, LogFilter};
+use crate::search::Hit;
 use crate::setting::Setting;
 use crate::statis::Statis;
+use crate::trash::TrashEntry;
 use crate::view::Form;
-use crate::windows::{ShowConfirmDelBackup, ShowConfirmOverwriteBackup, ShowConfirmTotoallyDel};
+use crate::windows::{
+    ShowConfirmDelBackup, ShowConfirmImport, ShowConfirmOverwriteBackup, ShowConfirmPurgeTrash,
+    ShowConfirmSwitchProfile, ShowConfirmTotoallyDel, ShowExport, ShowFileHistory, ShowGit,
+    ShowItemHistory, ShowRescan, ShowScanGames,
+};
 
+/// 命令执行结果的回复通道，界面发出的命令不需要回复
+pub type Reply = Sender<Result<serde_json::Value, String>>;
+pub type Pending = Receiver<Result<serde_json::Value, String>>;
+pub type OnReply = Box<dyn FnOnce(&mut AutoBackup, Result<serde_json::Value, String>)>;
+
+/// 界面发出的请求，command_proc 回复后在界面线程调用 on_reply
+pub struct PendingRequest {
+    pub name: &'static str, // 同名的请求没有回复前不会重复发送
+    pub rx: Pending,
+    pub on_reply: OnReply,
+}
+
+#[allow(clippy::large_enum_variant)]
 pub enum Command {
     StartJob, // 启动备份任务
-    ProtectBackup { id: String, protect: bool },
-    DeleteBackup { id: String, backup_id: String },
-    TotallyDelete { save_item: SaveItem },
+    ProtectBackup {
+        id: String,
+        backup_id: String,
+        protect: bool,
+        reply: Option<Reply>,
+    },
+    DeleteBackup {
+        id: String,
+        backup_id: String,
+        reply: Option<Reply>,
+    },
+    BulkBackups {
+        id: String,
+        request: BulkRequest,
+        reply: Option<Reply>,
+    }, // 批量删除、保护、导出、校验备份
+    TotallyDelete {
+        save_item: SaveItem,
+    },
+    ListSaveItems {
+        filter: ItemFilter,
+        reply: Reply,
+    },
+    ListBackups {
+        id: String,
+        keyword: String, // 按标题、备注和笔记筛选，为空时不筛选
+        reply: Reply,
+    },
+    UpdateBackup {
+        id: String,
+        backup_id: String,
+        update: BackupUpdate,
+        reply: Option<Reply>,
+    }, // 修改备份的标题、笔记、星级等信息
+    Backup {
+        id: String,
+        remark: String,
+        reply: Option<Reply>,
+    },
+    Restore {
+        id: String,
+        backup_id: String,
+        reply: Option<Reply>,
+    },
+    ListProfiles {
+        id: String,
+        reply: Reply,
+    },
+    AddProfile {
+        id: String,
+        name: String,
+        reply: Option<Reply>,
+    },
+    SwitchProfile {
+        id: String,
+        profile: String, // 为空或 Default 时切换到默认配置
+        reply: Option<Reply>,
+    }, // 备份当前文件后恢复目标配置最新的备份
+    GitHistory {
+        id: String,
+        reply: Reply,
+    }, // git 格式的提交历史
+    GitDiff {
+        id: String,
+        backup_id: String,
+        reply: Reply,
+    },
+    GitBlame {
+        id: String,
+        backup_id: String,
+        file: Option<String>, // 为空时列出备份中的文件
+        reply: Reply,
+    },
+    FileHistory {
+        id: String,
+        path: String, // 监听路径中的文件
+        reply: Reply,
+    }, // 包含该文件的所有备份
+    RestoreFile {
+        id: String,
+        backup_id: String,
+        path: String,
+        reply: Option<Reply>,
+    }, // 只恢复备份中的一个文件
+    JobStatus {
+        reply: Reply,
+    },
+    Export {
+        id: String,
+        backup_ids: Vec<String>, // 为空时导出所有备份
+        path: String,
+        reply: Option<Reply>,
+    },
+    Import {
+        path: String,
+        mode: ImportMode,
+        reply: Option<Reply>,
+    },
+    ScanGames {
+        reply: Reply,
+    }, // 按游戏数据库查找已安装的游戏
+    AddGames {
+        names: Vec<String>, // 为空时添加所有还没有存档的游戏
+        reply: Option<Reply>,
+    },
+    SteamApps {
+        reply: Reply,
+    }, // 已安装的 Steam 游戏
+    SteamPath {
+        app_id: u32,
+        path: String, // Windows 风格的路径
+        reply: Reply,
+    }, // 解析 Proton 前缀中的路径
+    Rescan {
+        apply: bool, // 为 false 时只报告不修改
+        reply: Option<Reply>,
+    },
+    ItemChanges {
+        id: String,
+        reply: Reply,
+    }, // 存档设置的修改记录
+    RevertChange {
+        id: String,
+        change_id: String,
+        reply: Option<Reply>,
+    }, // 撤销一次修改
+    ExportData {
+        path: String,
+        reply: Option<Reply>,
+    }, // 导出成 data.json 格式
+    Storage {
+        reply: Reply,
+    },
+    Prune {
+        id: String,
+        reply: Option<Reply>,
+    }, // 按保留策略把备份移到回收站
+    MeasureStorage, // 统计旧版本没有记录大小的备份
+    Search {
+        query: String,
+        reply: Reply,
+    }, // 在所有存档和备份中搜索
+    Logs {
+        filter: LogFilter,
+        reply: Reply,
+    }, // 按级别、存档和关键字读取日志
+    Health {
+        id: Option<String>, // 为空时检查所有未归档的存档
+        reply: Reply,
+    },
+    TestNotify {
+        reply: Reply,
+    }, // 通过所有启用的渠道发送一条测试通知
+    ListTrash {
+        reply: Reply,
+    },
+    RestoreTrash {
+        id: String,
+        reply: Option<Reply>,
+    },
+    PurgeTrash {
+        id: String,
+        reply: Option<Reply>,
+    }, // 从回收站永久删除
+    PurgeExpiredTrash, // 永久删除超过保留天数的内容
+    Show {
+        reply: Option<Reply>,
+    }, // 打开主窗口
+    Quit {
+        reply: Option<Reply>,
+    }, // 退出后台运行的程序
+}
+
+/// 回复命令的执行结果，没有回复通道时忽略
+pub fn respond(reply: &Option<Reply>, result: Result<serde_json::Value, String>) {
+    if let Some(reply) = reply {
+        let _ = reply.send(result);
+    }
 }
 
 #[derive(Default)]
@@ -30,6 +233,7 @@ pub struct AutoBackup {
     pub data: Arc<Mutex<Data>>,
     pub toasts: Toasts,
     pub command: Option<Sender<Command>>,
+    pub window: WindowHandle,
 }
 
 #[derive(Default, PartialEq, Clone)]
@@ -40,21 +244,54 @@ pub enum Navigation {
     New,
     Edit,
     Archive,
+    Search,
+    Timeline,
+    Storage,
+    Trash,
+    Logs,
     Setting,
 }
 
 #[derive(Default)]
 pub struct Control {
-    pub debounce: bool, // 防抖
+    pub pending: Vec<PendingRequest>, // 等待 command_proc 回复的请求，每帧检查一次
+    pub condition_draft: Option<(String, Condition, Condition)>, // 存档 id、已保存的执行条件和正在编辑的草稿，提交后才保存
     pub manage: String,
     pub backup_filter: String,
-    pub save_item_filter: String,
-    pub totally_delete_name: String, // 彻底删除前的输入确认
+    pub save_item_filter: ItemFilter, // 归档页面的筛选
+    pub item_filter: ItemFilter,      // 备份管理侧边栏的筛选
+    pub totally_delete_name: String,  // 彻底删除前的输入确认
     pub nav: Navigation,
     pub show_backup_remark: isize,
     pub show_check_monitor: String, // 检查监听内容
     pub show_confirm_overwrite_backup: ShowConfirmOverwriteBackup, // 二次确认操作
     pub show_confirm_del_backup: ShowConfirmDelBackup, // 二次确认操作
     pub show_confirm_totally_del: ShowConfirmTotoallyDel, // 二次确认操作
-    pub new_remark: String,         // 新备份的备注
+    pub show_confirm_purge_trash: ShowConfirmPurgeTrash, // 二次确认操作
+    pub show_confirm_switch_profile: ShowConfirmSwitchProfile, // 二次确认操作
+    pub show_export: ShowExport,
+    pub show_confirm_import: ShowConfirmImport, // 导入时有冲突
+    pub show_rescan: ShowRescan,                // 扫描备份目录的结果
+    pub show_scan_games: ShowScanGames,         // 扫描到的已安装游戏
+    pub show_git: ShowGit,                      // git 格式备份的修改和逐行记录
+    pub show_file_history: ShowFileHistory,     // 单个文件在所有备份中的版本
+    pub show_item_history: ShowItemHistory,     // 存档设置的修改记录
+    pub trash: Option<Vec<TrashEntry>>,         // 回收站列表，为空时重新读取
+    pub log_filter: LogFilter,                  // 日志页面的筛选
+    pub logs: Option<Result<Vec<Entry>, String>>, // 日志列表，为空时重新读取
+    pub new_remark: String,                     // 新备份的备注
+    pub new_profile: String,                    // 新配置的名称
+    pub search: String,                         // 全局搜索的内容
+    pub search_hits: Option<Result<Vec<Hit>, String>>, // 搜索结果，为空时还没有搜索
+    pub jump_backup: String,                    // 从搜索结果跳转的备份，在备份列表中高亮
+    pub jump_scroll: bool,                      // 跳转后滚动到该备份
+    pub timeline_item: String,                  // 时间轴显示的存档 id，为空时显示所有存档
+    pub timeline_days: u32,                     // 时间轴显示最近的天数，0 表示全部
+    pub selected_backups: HashSet<String>,      // 备份列表中选中的备份，用于批量操作
+    pub confirm_bulk_delete: bool,              // 批量删除前的确认
+    pub backup_sort: BackupSort,                // 备份列表的排序列
+    pub backup_asc: bool,                       // 升序，默认新的在前
+    pub backup_page: usize,                     // 备份列表的当前页，从 0 开始
+    pub backup_page_size: usize,                // 每页的备份数量，0 时使用默认值
+    pub backup_view: Option<(PageQuery, Page)>, // 缓存的当前页，条件或备份索引变化时重新查询
 }
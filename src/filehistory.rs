@@ -0,0 +1,316 @@
+use std::{io::Read, path::Path, time::UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::{
+    backup::{Backup, Outcome},
+    data::{FileType, SaveItem},
+    gitrepo,
+    hook::{self, HookKind},
+    log::{self, Entry, Level},
+};
+
+/// 文件在某个备份中的版本
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileVersion {
+    pub backup_id: String,
+    pub time: i64, // 备份时间
+    pub remark: String,
+    pub profile: String,
+    pub size: u64,
+    pub modified: Option<i64>, // 文件的修改时间，git 格式的备份没有
+    pub hash: String,          // 和 git 相同的对象 id，不同格式的备份可以比较
+}
+
+/// 文件的历史：当前文件以及包含它的所有备份，新的在前
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileHistory {
+    pub path: String,
+    pub current: Option<FileVersion>, // 当前文件，不存在时为空
+    pub versions: Vec<FileVersion>,
+}
+
+/// 统一分隔符并去掉结尾的分隔符，用于比较路径
+fn normalize(path: &str) -> String {
+    path.trim()
+        .replace('\\', "/")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// path 在 root 里面时返回相对路径，相同时返回空
+fn relative(root: &str, path: &str) -> Option<String> {
+    let (root, path) = (normalize(root), normalize(path));
+    if path == root {
+        return Some(String::new());
+    }
+    path.strip_prefix(&format!("{}/", root))
+        .map(|rest| rest.to_string())
+}
+
+/// 文件必须在某个监听路径中
+pub fn check_path(save_item: &SaveItem, path: &str) -> Result<String, String> {
+    // 只按字符串比较前缀，带 .. 或 . 的路径可能指向监听路径以外的文件
+    if normalize(path)
+        .split('/')
+        .any(|segment| segment == ".." || segment == ".")
+    {
+        return Err(format!("relative segments are not allowed: {}", path));
+    }
+    let monitored = save_item
+        .monitors
+        .iter()
+        .any(|monitor| match monitor.backup_type {
+            FileType::File => normalize(&monitor.path) == normalize(path),
+            FileType::Folder => relative(&monitor.path, path).is_some_and(|rel| !rel.is_empty()),
+        });
+    if monitored {
+        Ok(normalize(path))
+    } else {
+        Err(format!("not a file under the monitored paths: {}", path))
+    }
+}
+
+/// 文件在备份中的路径：文件夹格式是备份目录中的绝对路径，git 格式是仓库中的相对路径
+fn locate(backup: &Backup, path: &str) -> Option<String> {
+    let target = backup.datas.iter().find_map(|data| {
+        let rel = relative(&data.source, path)?;
+        match data.file_type {
+            FileType::File if rel.is_empty() => Some(normalize(&data.target)),
+            FileType::Folder if !rel.is_empty() => {
+                Some(format!("{}/{}", normalize(&data.target), rel))
+            }
+            _ => None,
+        }
+    })?;
+    if backup.is_git() {
+        relative(&backup.backup_folder, &target)
+    } else {
+        Some(target)
+    }
+}
+
+/// 和 git hash-object 相同的 SHA-1
+pub(crate) fn hash_file(path: &str) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("open {} error: {}", path, e))?;
+    let size = file.metadata().map_err(|e| e.to_string())?.len();
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", size));
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .map_err(|e| format!("read {} error: {}", path, e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub(crate) fn modified(path: &Path) -> Option<i64> {
+    let time = path.metadata().ok()?.modified().ok()?;
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64)
+}
+
+/// 磁盘上文件的版本信息，不是文件时返回 None
+fn file_version(path: &str) -> Option<FileVersion> {
+    let file = Path::new(path);
+    if !file.is_file() {
+        return None;
+    }
+    let hash = match hash_file(path) {
+        Ok(hash) => hash,
+        Err(e) => {
+            log::log_err(e);
+            return None;
+        }
+    };
+    Some(FileVersion {
+        size: file.metadata().map(|meta| meta.len()).unwrap_or_default(),
+        modified: modified(file),
+        hash,
+        ..FileVersion::default()
+    })
+}
+
+/// 文件在备份中的版本，备份不包含该文件时返回 None
+fn backup_version(backup: &Backup, path: &str) -> Option<FileVersion> {
+    let location = locate(backup, path)?;
+    let version = if backup.is_git() {
+        match gitrepo::blob_info(backup, &location) {
+            Ok(info) => {
+                let (hash, size) = info?;
+                FileVersion {
+                    size,
+                    hash,
+                    modified: None,
+                    ..FileVersion::default()
+                }
+            }
+            Err(e) => {
+                log::log_err(e);
+                return None;
+            }
+        }
+    } else {
+        file_version(&location)?
+    };
+    Some(FileVersion {
+        backup_id: backup.id.to_string(),
+        time: backup.time,
+        remark: backup.remark.to_string(),
+        profile: backup.profile.to_string(),
+        ..version
+    })
+}
+
+/// 列出包含该文件的所有备份
+pub fn history(save_item: &SaveItem, path: &str) -> Result<FileHistory, String> {
+    let path = check_path(save_item, path)?;
+    Ok(FileHistory {
+        current: file_version(&path),
+        versions: save_item
+            .backups
+            .iter()
+            .filter_map(|backup| backup_version(backup, &path))
+            .collect(),
+        path,
+    })
+}
+
+/// 只用备份中的这个文件覆盖当前文件，恢复前后执行钩子
+pub fn restore(save_item: &SaveItem, backup: &Backup, path: &str) -> Result<(), String> {
+    let path = check_path(save_item, path)?;
+    let location =
+        locate(backup, &path).ok_or(format!("file not found in the backup: {}", path))?;
+    hook::run(save_item, HookKind::PreRestore, backup, "")
+        .map_err(|e| format!("Hook Error\n{}", e))?;
+
+    if let Some(parent) = Path::new(&path).parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let result = if backup.is_git() {
+        gitrepo::blob(backup, &location).and_then(|content| {
+            std::fs::write(&path, content).map_err(|e| format!("write {} error: {}", path, e))
+        })
+    } else if Path::new(&location).is_file() {
+        std::fs::copy(&location, &path)
+            .map(|_| ())
+            .map_err(|e| format!("copy {} error: {}", location, e))
+    } else {
+        Err(format!("file not found in the backup: {}", path))
+    };
+
+    let outcome = if result.is_ok() {
+        Outcome::Success
+    } else {
+        Outcome::Failed
+    };
+    if let Err(e) = hook::run(
+        save_item,
+        HookKind::PostRestore,
+        backup,
+        &outcome.to_string(),
+    ) {
+        log::log_err(format!("post restore hook error: {}", e));
+    }
+    let entry = match &result {
+        Ok(_) => Entry::new(Level::Info, format!("restore file, path={}", path)),
+        Err(e) => Entry::new(Level::Error, format!("restore file error: {}", e)),
+    };
+    entry.item(&save_item.name).backup(&backup.id).write();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backup::{BackupData, BackupFormat},
+        data::Monitor,
+    };
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative("/saves/game", "/saves/game/slot1.sav").as_deref(),
+            Some("slot1.sav")
+        );
+        assert_eq!(
+            relative("C:\\Saves\\Game\\", "C:/Saves/Game/a/b.sav").as_deref(),
+            Some("a/b.sav")
+        );
+        assert_eq!(relative("/saves/game/", "/saves/game").as_deref(), Some(""));
+        // 只是名称前缀相同的路径不在里面
+        assert_eq!(relative("/saves/game", "/saves/game2/slot1.sav"), None);
+        assert_eq!(relative("/saves/game", "/saves"), None);
+    }
+
+    #[test]
+    fn check_monitored_paths() {
+        let save_item = SaveItem {
+            monitors: vec![
+                Monitor {
+                    path: "/saves/game".to_string(),
+                    backup_type: FileType::Folder,
+                },
+                Monitor {
+                    path: "/config/game.ini".to_string(),
+                    backup_type: FileType::File,
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            check_path(&save_item, "\\saves\\game\\slot1.sav").as_deref(),
+            Ok("/saves/game/slot1.sav")
+        );
+        assert!(check_path(&save_item, "/config/game.ini").is_ok());
+        // 监听的文件夹本身不是文件
+        assert!(check_path(&save_item, "/saves/game/").is_err());
+        assert!(check_path(&save_item, "/config/other.ini").is_err());
+        assert!(check_path(&save_item, "/saves/game2/slot1.sav").is_err());
+        // 不能用 .. 或 . 跳出监听的文件夹
+        assert!(check_path(&save_item, "/saves/game/../../etc/passwd").is_err());
+        assert!(check_path(&save_item, "\\saves\\game\\..\\other.sav").is_err());
+        assert!(check_path(&save_item, "/saves/game/./slot1.sav").is_err());
+    }
+
+    #[test]
+    fn locate_in_backups() {
+        let datas = vec![
+            BackupData {
+                source: "/saves/game".to_string(),
+                target: "/backup/20260101/game".to_string(),
+                file_type: FileType::Folder,
+            },
+            BackupData {
+                source: "/config/game.ini".to_string(),
+                target: "/backup/20260101/game.ini".to_string(),
+                file_type: FileType::File,
+            },
+        ];
+        let mut backup = Backup::new(datas, "/backup/20260101".to_string(), String::new());
+        assert_eq!(
+            locate(&backup, "/saves/game/a/slot1.sav").as_deref(),
+            Some("/backup/20260101/game/a/slot1.sav")
+        );
+        assert_eq!(
+            locate(&backup, "/config/game.ini").as_deref(),
+            Some("/backup/20260101/game.ini")
+        );
+        assert_eq!(locate(&backup, "/saves/game"), None);
+        assert_eq!(locate(&backup, "/saves/other.sav"), None);
+
+        // git 格式返回仓库中的相对路径
+        backup.format = Some(BackupFormat::Git);
+        backup.backup_folder = "/backup".to_string();
+        assert_eq!(
+            locate(&backup, "/saves/game/a/slot1.sav").as_deref(),
+            Some("20260101/game/a/slot1.sav")
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::SaveItem;
+
+/// 存档列表的排序方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SortBy {
+    #[default]
+    Name,
+    LastBackup, // 最近备份的在前
+    Size,       // 占用空间大的在前
+}
+impl SortBy {
+    pub const ALL: [SortBy; 3] = [SortBy::Name, SortBy::LastBackup, SortBy::Size];
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "name" => Ok(SortBy::Name),
+            "last-backup" => Ok(SortBy::LastBackup),
+            "size" => Ok(SortBy::Size),
+            _ => Err(format!(
+                "unknown sort: {}, expected name, last-backup or size",
+                text
+            )),
+        }
+    }
+}
+impl Display for SortBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            SortBy::Name => "Name",
+            SortBy::LastBackup => "Last Backup",
+            SortBy::Size => "Size",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// 存档的筛选条件，空的条件不限制，多个标签需要同时包含
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ItemFilter {
+    pub keyword: String, // 名称、描述或标签包含的文字，不区分大小写
+    pub group: String,
+    pub platform: String,
+    pub tags: Vec<String>,
+    pub sort: SortBy,
+}
+impl ItemFilter {
+    pub fn is_empty(&self) -> bool {
+        self.keyword.is_empty()
+            && self.group.is_empty()
+            && self.platform.is_empty()
+            && self.tags.is_empty()
+    }
+
+    pub fn matches(&self, item: &SaveItem) -> bool {
+        if !self.group.is_empty() && !item.group.eq_ignore_ascii_case(&self.group) {
+            return false;
+        }
+        if !self.platform.is_empty() && !item.platform.eq_ignore_ascii_case(&self.platform) {
+            return false;
+        }
+        if !self.tags.iter().all(|tag| has_tag(item, tag)) {
+            return false;
+        }
+        let keyword = self.keyword.trim().to_lowercase();
+        keyword.is_empty()
+            || item.name.to_lowercase().contains(&keyword)
+            || item.description.to_lowercase().contains(&keyword)
+            || item
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(&keyword))
+    }
+
+    /// 筛选并排序
+    pub fn apply<'a>(&self, items: impl IntoIterator<Item = &'a SaveItem>) -> Vec<&'a SaveItem> {
+        let mut items: Vec<&SaveItem> = items
+            .into_iter()
+            .filter(|item| self.matches(item))
+            .collect();
+        match self.sort {
+            SortBy::Name => items.sort_by_key(|item| item.name.to_lowercase()),
+            SortBy::LastBackup => items.sort_by_key(|item| {
+                std::cmp::Reverse(item.backups.first().map(|backup| backup.time))
+            }),
+            SortBy::Size => items.sort_by_key(|item| std::cmp::Reverse(item.storage().0)),
+        }
+        items
+    }
+
+    /// 切换标签的选中状态
+    pub fn toggle_tag(&mut self, tag: &str) {
+        match self.tags.iter().position(|t| t == tag) {
+            Some(index) => {
+                self.tags.remove(index);
+            }
+            None => self.tags.push(tag.to_string()),
+        }
+    }
+}
+
+pub fn has_tag(item: &SaveItem, tag: &str) -> bool {
+    item.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+}
+
+/// 去掉首尾空白和重复的标签
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !result.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            result.push(tag.to_string());
+        }
+    }
+    result
+}
+
+/// 所有存档使用过的标签，按名称排序
+pub fn all_tags<'a>(items: impl IntoIterator<Item = &'a SaveItem>) -> Vec<String> {
+    let tags: Vec<String> = items
+        .into_iter()
+        .flat_map(|item| item.tags.iter().cloned())
+        .collect();
+    sorted(normalize_tags(&tags))
+}
+
+/// 所有存档使用过的分组，按名称排序，不包含未分组
+pub fn all_groups<'a>(items: impl IntoIterator<Item = &'a SaveItem>) -> Vec<String> {
+    let groups: Vec<String> = items.into_iter().map(|item| item.group.clone()).collect();
+    sorted(normalize_tags(&groups))
+}
+
+/// 所有存档使用过的平台，按名称排序
+pub fn all_platforms<'a>(items: impl IntoIterator<Item = &'a SaveItem>) -> Vec<String> {
+    let platforms: Vec<String> = items
+        .into_iter()
+        .map(|item| item.platform.clone())
+        .collect();
+    sorted(normalize_tags(&platforms))
+}
+
+fn sorted(mut list: Vec<String>) -> Vec<String> {
+    list.sort_by_key(|s| s.to_lowercase());
+    list
+}
@@ -0,0 +1,456 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{Data, FileType, Monitor, SaveItem},
+    log, steam, util,
+};
+
+const BUNDLED: &str = include_str!("../assets/games.json");
+const USER_PATH: &str = "./data/games.json"; // 用户补充的游戏，同名时覆盖内置的
+const DEFAULT_CRON: &str = "0 30 * * * *";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Os {
+    Windows,
+    Linux,
+    Mac,
+}
+impl Os {
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Os::Windows
+        } else if cfg!(target_os = "macos") {
+            Os::Mac
+        } else {
+            Os::Linux
+        }
+    }
+}
+
+/// 游戏数据库中的一个游戏，格式参考 Ludusavi 的 manifest
+///
+/// 路径中可以使用变量：`<home>`、`<xdgData>`、`<xdgConfig>`、`<winAppData>`、
+/// `<winLocalAppData>`、`<winDocuments>`、`<base>`（安装目录）、`<storeUserId>`（任意用户 id）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub name: String,
+    #[serde(default)]
+    pub steam_id: Option<u32>,
+    #[serde(default)]
+    pub install_dir: Vec<String>, // 安装目录的名称，用于解析 <base>
+    #[serde(default)]
+    pub files: Vec<GameFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameFile {
+    pub path: String,
+    #[serde(default)]
+    pub os: Option<Os>, // 为空时所有系统都可以使用
+}
+
+/// 内置的游戏数据库加上 data/games.json 中用户补充的游戏
+pub fn load() -> Vec<Game> {
+    let mut games: Vec<Game> = match serde_json::from_str(BUNDLED) {
+        Ok(games) => games,
+        Err(e) => {
+            log::log_err(format!("read bundled game database error: {}", e));
+            vec![]
+        }
+    };
+    if let Ok(data) = util::read_data(USER_PATH) {
+        match serde_json::from_str::<Vec<Game>>(&data) {
+            Ok(user_games) => {
+                for game in user_games {
+                    games.retain(|g| !g.name.eq_ignore_ascii_case(&game.name));
+                    games.push(game);
+                }
+            }
+            Err(e) => log::log_err(format!("read {} error: {}", USER_PATH, e)),
+        }
+    }
+    games.sort_by_key(|game| game.name.to_lowercase());
+    games
+}
+
+/// 解析路径变量的环境：本机系统，或者一个 Wine/Proton 前缀
+#[derive(Debug, Clone)]
+pub struct Env {
+    pub platform: String,
+    pub os: Os,
+    pub vars: HashMap<&'static str, String>,
+}
+impl Env {
+    fn native() -> Option<Self> {
+        let home = home_dir()?;
+        let mut vars = HashMap::new();
+        if cfg!(windows) {
+            let env = |key: &str, default: String| std::env::var(key).unwrap_or(default);
+            vars.insert(
+                "winAppData",
+                env("APPDATA", format!("{}/AppData/Roaming", home)),
+            );
+            vars.insert(
+                "winLocalAppData",
+                env("LOCALAPPDATA", format!("{}/AppData/Local", home)),
+            );
+            vars.insert("winDocuments", format!("{}/Documents", home));
+        } else {
+            let xdg = |key: &str, default: &str| {
+                std::env::var(key)
+                    .ok()
+                    .filter(|dir| !dir.is_empty())
+                    .unwrap_or(format!("{}/{}", home, default))
+            };
+            vars.insert("xdgData", xdg("XDG_DATA_HOME", ".local/share"));
+            vars.insert("xdgConfig", xdg("XDG_CONFIG_HOME", ".config"));
+        }
+        vars.insert("home", home);
+        Some(Self {
+            platform: "Native".to_string(),
+            os: Os::current(),
+            vars,
+        })
+    }
+
+    /// Wine 前缀中的一个用户，Windows 路径解析到 drive_c/users/<user> 下
+    pub fn wine(platform: &str, prefix: &str, user: &str) -> Self {
+        let home = format!("{}/drive_c/users/{}", prefix, user);
+        let mut vars = HashMap::new();
+        vars.insert("winAppData", format!("{}/AppData/Roaming", home));
+        vars.insert("winLocalAppData", format!("{}/AppData/Local", home));
+        vars.insert("winDocuments", format!("{}/Documents", home));
+        vars.insert("home", home);
+        Self {
+            platform: platform.to_string(),
+            os: Os::Windows,
+            vars,
+        }
+    }
+
+    /// 替换路径变量，有无法解析的变量时返回 None
+    fn resolve(&self, path: &str, base: Option<&str>) -> Option<String> {
+        let mut result = path.replace("<storeUserId>", "*");
+        for (key, value) in &self.vars {
+            result = result.replace(&format!("<{}>", key), value);
+        }
+        if let Some(base) = base {
+            result = result.replace("<base>", base);
+        }
+        if result.contains('<') {
+            return None;
+        }
+        Some(result.replace('\\', "/"))
+    }
+}
+
+pub fn home_dir() -> Option<String> {
+    let key = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var(key)
+        .ok()
+        .filter(|home| !home.is_empty())
+        .map(|home| home.replace('\\', "/"))
+}
+
+fn sub_dirs(path: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = util::read_dir(path)
+        .iter()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Steam 的安装目录，包括 Flatpak 版本
+pub fn steam_roots() -> Vec<String> {
+    let Some(home) = home_dir() else {
+        return vec![];
+    };
+    let candidates = if cfg!(windows) {
+        vec!["C:/Program Files (x86)/Steam".to_string()]
+    } else {
+        vec![
+            format!("{}/.steam/steam", home),
+            format!("{}/.local/share/Steam", home),
+            format!(
+                "{}/.var/app/com.valvesoftware.Steam/.local/share/Steam",
+                home
+            ),
+        ]
+    };
+    let mut roots: Vec<String> = vec![];
+    for root in candidates {
+        // ~/.steam/steam 通常是指向 ~/.local/share/Steam 的链接
+        let Ok(path) = std::fs::canonicalize(&root) else {
+            continue;
+        };
+        let path = path.display().to_string();
+        if util::file_exist(&format!("{}/steamapps", path)) && !roots.contains(&path) {
+            roots.push(path);
+        }
+    }
+    roots
+}
+
+/// Lutris 和默认的 Wine 前缀，返回平台名称和前缀路径
+fn wine_prefixes() -> Vec<(String, String)> {
+    let mut prefixes: Vec<(String, String)> = vec![];
+    if cfg!(windows) {
+        return prefixes;
+    }
+    let Some(home) = home_dir() else {
+        return prefixes;
+    };
+    let mut push = |platform: &str, prefix: String| {
+        if util::file_exist(&format!("{}/drive_c", prefix))
+            && !prefixes.iter().any(|(_, p)| *p == prefix)
+        {
+            prefixes.push((platform.to_string(), prefix));
+        }
+    };
+
+    // Lutris 的游戏配置中记录了前缀
+    let lutris = format!("{}/.config/lutris/games", home);
+    for entry in util::read_dir(&lutris) {
+        let Ok(text) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        for line in text.lines() {
+            if let Some(prefix) = line.trim().strip_prefix("prefix:") {
+                let prefix = prefix.trim().trim_matches(|c| c == '"' || c == '\'');
+                push("Lutris", prefix.replace('~', &home));
+            }
+        }
+    }
+    // Lutris 默认的安装目录
+    let games = format!("{}/Games", home);
+    for dir in sub_dirs(&games) {
+        push("Lutris", format!("{}/{}", games, dir));
+    }
+    if let Ok(prefix) = std::env::var("WINEPREFIX") {
+        push("Wine", prefix);
+    }
+    push("Wine", format!("{}/.wine", home));
+    prefixes
+}
+
+/// 前缀中的用户，不包括 Public
+fn wine_users(prefix: &str) -> Vec<String> {
+    sub_dirs(&format!("{}/drive_c/users", prefix))
+        .into_iter()
+        .filter(|user| user != "Public")
+        .collect()
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// 展开路径中的 *，返回存在的路径
+fn expand(path: &str) -> Vec<String> {
+    if !path.contains('*') {
+        return if util::file_exist(path) {
+            vec![path.to_string()]
+        } else {
+            vec![]
+        };
+    }
+    let mut current = vec![String::new()];
+    for (index, segment) in path.split('/').enumerate() {
+        if index == 0 {
+            current = vec![segment.to_string()];
+            continue;
+        }
+        let mut next = vec![];
+        for dir in &current {
+            if segment.contains('*') {
+                for name in sub_dirs(dir) {
+                    if wildcard_match(segment, &name) {
+                        next.push(format!("{}/{}", dir, name));
+                    }
+                }
+            } else {
+                next.push(format!("{}/{}", dir, segment));
+            }
+        }
+        current = next;
+    }
+    current
+        .into_iter()
+        .filter(|path| util::file_exist(path))
+        .collect()
+}
+
+/// 扫描到的已安装游戏
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Detected {
+    pub name: String,
+    pub platform: String,
+    pub steam_id: Option<u32>, // 通过 Steam 安装时的 appid
+    pub monitors: Vec<Monitor>,
+    pub exists: bool, // 已经有同名或者监听相同路径的存档
+}
+impl Detected {
+    pub fn to_save_item(&self) -> SaveItem {
+        let mut item = SaveItem::new();
+        item.name = self.name.to_string();
+        item.platform = self.platform.to_string();
+        item.monitors = self.monitors.clone();
+        item.auto.cron = DEFAULT_CRON.to_string();
+        if let Some(app_id) = self.steam_id {
+            item.startup_path = steam::launch_url(app_id);
+        }
+        item
+    }
+}
+
+fn detect(game: &Game, env: &Env, bases: &[String]) -> Option<Detected> {
+    let mut monitors: Vec<Monitor> = vec![];
+    let mut in_library = false;
+    for file in &game.files {
+        if file.os.is_some_and(|os| os != env.os) {
+            continue;
+        }
+        let paths = if file.path.contains("<base>") {
+            bases
+                .iter()
+                .filter_map(|base| env.resolve(&file.path, Some(base)))
+                .collect()
+        } else {
+            env.resolve(&file.path, None)
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        for path in paths.iter().flat_map(|path| expand(path)) {
+            if monitors.iter().any(|m| m.path == path) {
+                continue;
+            }
+            let backup_type = if Path::new(&path).is_dir() {
+                FileType::Folder
+            } else {
+                FileType::File
+            };
+            in_library |= bases.iter().any(|base| path.starts_with(base.as_str()));
+            monitors.push(Monitor { path, backup_type });
+        }
+    }
+    if monitors.is_empty() {
+        return None;
+    }
+    // 本机的存档在 Steam 游戏库中时，平台是 Steam
+    let platform = if env.platform == "Native" && in_library {
+        "Steam"
+    } else {
+        &env.platform
+    };
+    Some(Detected {
+        name: game.name.to_string(),
+        platform: platform.to_string(),
+        steam_id: game.steam_id,
+        monitors,
+        exists: false,
+    })
+}
+
+/// 在本机、Steam 游戏库、Proton 和 Wine 前缀中查找数据库中的游戏
+pub fn scan(data: &Data) -> Vec<Detected> {
+    let games = load();
+    let libraries = steam::libraries();
+    let apps = steam::installed_apps();
+    let mut envs: Vec<Env> = Env::native().into_iter().collect();
+    for (platform, prefix) in wine_prefixes() {
+        for user in wine_users(&prefix) {
+            envs.push(Env::wine(&platform, &prefix, &user));
+        }
+    }
+
+    let mut detected = vec![];
+    for game in &games {
+        let app = game
+            .steam_id
+            .and_then(|id| apps.iter().find(|app| app.app_id == id));
+        // appmanifest 中的安装目录优先，没有时按名称在游戏库中查找
+        let mut bases: Vec<String> = app.map(|app| app.install_dir.clone()).into_iter().collect();
+        for library in &libraries {
+            for dir in &game.install_dir {
+                let base = format!("{}/steamapps/common/{}", library, dir);
+                if util::file_exist(&base) && !bases.contains(&base) {
+                    bases.push(base);
+                }
+            }
+        }
+        let mut game_envs = envs.clone();
+        // Proton 前缀按 appid 区分，只对这个游戏有效
+        if let Some(prefix) = game.steam_id.and_then(steam::prefix) {
+            game_envs.push(Env::wine("Proton", &prefix, "steamuser"));
+        }
+        for env in &game_envs {
+            if let Some(mut found) = detect(game, env, &bases) {
+                // 只和安装目录有关的路径在每个环境中都一样
+                if detected.iter().any(|d: &Detected| {
+                    d.name == found.name
+                        && d.monitors
+                            .iter()
+                            .map(|m| &m.path)
+                            .eq(found.monitors.iter().map(|m| &m.path))
+                }) {
+                    continue;
+                }
+                if app.is_none() && found.platform != "Proton" {
+                    found.steam_id = None;
+                }
+                found.exists = data.monitors.iter().any(|item| {
+                    item.name == found.name
+                        || item
+                            .monitors
+                            .iter()
+                            .any(|m| found.monitors.iter().any(|f| f.path == m.path))
+                });
+                detected.push(found);
+            }
+        }
+    }
+    log::log_info(format!(
+        "scan games, database={}, detected={}",
+        games.len(),
+        detected.len()
+    ));
+    detected
+}
+
+/// 为扫描到的游戏创建存档，names 为空时添加所有还没有存档的游戏
+pub fn add(data: &mut Data, names: &[String]) -> Vec<SaveItem> {
+    let mut added: Vec<SaveItem> = vec![];
+    for found in scan(data) {
+        if found.exists
+            || (!names.is_empty() && !names.contains(&found.name))
+            || added.iter().any(|item| item.name == found.name)
+        {
+            continue;
+        }
+        let item = found.to_save_item();
+        log::log_info(format!(
+            "add save item from game database, name={}, platform={}",
+            item.name, item.platform
+        ));
+        added.push(item.clone());
+        data.push_monitor(item);
+    }
+    added
+}
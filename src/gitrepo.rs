@@ -0,0 +1,289 @@
+use std::process::Command;
+
+use egui_notify::ToastLevel;
+use serde_json::json;
+
+use crate::{
+    backup::{self, Backup, BackupData},
+    data::Monitor,
+    log, storage, util,
+};
+
+/// 每个存档一个 git 仓库 ./data/backup/<id>/git
+pub const REPO_FOLDER: &str = "git";
+
+pub fn repo_folder(id: &str) -> String {
+    format!("{}/data/backup/{}/{}", util::current_dir(), id, REPO_FOLDER)
+}
+
+/// 执行 git 命令，返回标准输出
+fn git(repo: &str, args: &[&str], envs: &[(&str, &str)]) -> Result<String, String> {
+    git_bytes(repo, args, envs).map(|output| String::from_utf8_lossy(&output).to_string())
+}
+
+/// 执行 git 命令，返回原始的标准输出，用于读取二进制文件
+fn git_bytes(repo: &str, args: &[&str], envs: &[(&str, &str)]) -> Result<Vec<u8>, String> {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C")
+        .arg(repo)
+        .args(["-c", "user.name=AutoBackup"])
+        .args(["-c", "user.email=auto_backup@localhost"])
+        .args(["-c", "core.autocrlf=false"])
+        .args(["-c", "core.quotepath=false"])
+        .args(args);
+    for (key, value) in envs {
+        cmd.env(key, value);
+    }
+    let output = cmd
+        .output()
+        .map_err(|e| format!("run git error: {}, please install git", e))?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(format!(
+            "git {} error: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn init(repo: &str) -> Result<(), String> {
+    if util::file_exist(&format!("{}/.git", repo)) {
+        return Ok(());
+    }
+    util::check_dir_and_create(repo);
+    git(repo, &["init", "-q"], &[])?;
+    log::log_info(format!("init git repository {}", repo));
+    Ok(())
+}
+
+/// 把监听的文件提交到仓库，备注作为提交信息
+///
+/// 仓库的工作区先清空，这样删除的文件也会被记录，备份内容的路径和文件夹格式相同
+pub fn backup(backup: &mut Backup, monitors: Vec<Monitor>, msgs: &mut Vec<(ToastLevel, String)>) {
+    let repo = backup.backup_folder.to_string();
+    if let Err(e) = init(&repo) {
+        msgs.push((ToastLevel::Error, format!("Backup Error\n{}", e)));
+        return;
+    }
+    for entry in util::read_dir(&repo) {
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let path = entry.path();
+        let _ = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+    }
+
+    backup.datas = backup::backup_file(&repo, monitors, msgs);
+    if msgs.iter().any(|(level, _)| *level == ToastLevel::Error) {
+        return;
+    }
+
+    let before = storage::measure(&format!("{}/.git", repo)).0;
+    let result = git(&repo, &["add", "-A"], &[])
+        .and_then(|_| {
+            git(
+                &repo,
+                &[
+                    "commit",
+                    "-q",
+                    "--allow-empty",
+                    "--allow-empty-message",
+                    "-m",
+                    &backup.remark,
+                ],
+                &[],
+            )
+        })
+        .and_then(|_| git(&repo, &["rev-parse", "HEAD"], &[]));
+    match result {
+        Ok(commit) => {
+            backup.commit = commit.trim().to_string();
+            // 只统计这次提交增加的空间和修改的文件
+            backup.size = storage::measure(&format!("{}/.git", repo))
+                .0
+                .saturating_sub(before);
+            backup.files = changed_files(&repo, &backup.commit).len() as u64;
+        }
+        Err(e) => msgs.push((ToastLevel::Error, format!("Backup Error\n{}", e))),
+    }
+}
+
+fn changed_files(repo: &str, commit: &str) -> Vec<String> {
+    git(
+        repo,
+        &[
+            "diff-tree",
+            "--no-commit-id",
+            "--name-only",
+            "-r",
+            "--root",
+            commit,
+        ],
+        &[],
+    )
+    .map(|text| text.lines().map(|line| line.to_string()).collect())
+    .unwrap_or_default()
+}
+
+/// 检出到临时目录的备份内容，离开作用域时删除
+pub struct Checkout {
+    pub folder: String,
+    pub datas: Vec<BackupData>,
+}
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        util::delete_dirs(&self.folder);
+        util::delete_file(&format!("{}.index", self.folder));
+    }
+}
+
+/// 把备份对应的提交检出到临时目录，不修改仓库的工作区和暂存区
+pub fn checkout(backup: &Backup) -> Result<Checkout, String> {
+    let folder = format!(
+        "{}/auto_backup_checkout_{}",
+        std::env::temp_dir().display(),
+        backup.id
+    );
+    let checkout = Checkout {
+        datas: backup
+            .datas
+            .iter()
+            .map(|data| BackupData {
+                target: data.target.replacen(&backup.backup_folder, &folder, 1),
+                ..data.clone()
+            })
+            .collect(),
+        folder,
+    };
+    util::delete_dirs(&checkout.folder);
+    util::check_dir_and_create(&checkout.folder);
+    let index = format!("{}.index", checkout.folder);
+    let work_tree = format!("--work-tree={}", checkout.folder);
+    git(
+        &backup.backup_folder,
+        &[&work_tree, "checkout", &backup.commit, "--", "."],
+        &[("GIT_INDEX_FILE", &index)],
+    )?;
+    Ok(checkout)
+}
+
+/// 备份相对上一次提交的修改
+pub fn diff(backup: &Backup) -> Result<String, String> {
+    git(
+        &backup.backup_folder,
+        &["show", "--stat", "--patch", "--date=iso", &backup.commit],
+        &[],
+    )
+}
+
+/// 检查备份对应的提交和文件能否从仓库中读取
+pub fn verify(backup: &Backup) -> Result<(), String> {
+    if !util::file_exist(&format!("{}/.git", backup.backup_folder)) {
+        return Err(format!(
+            "git repository not found: {}",
+            backup.backup_folder
+        ));
+    }
+    let commit = format!("{}^{{commit}}", backup.commit);
+    git(&backup.backup_folder, &["cat-file", "-e", &commit], &[])?;
+    git(
+        &backup.backup_folder,
+        &["ls-tree", "-r", "-l", &backup.commit],
+        &[],
+    )
+    .map(|_| ())
+}
+
+/// 备份中的所有文件
+pub fn files(backup: &Backup) -> Result<Vec<String>, String> {
+    let text = git(
+        &backup.backup_folder,
+        &["ls-tree", "-r", "--name-only", &backup.commit],
+        &[],
+    )?;
+    Ok(text.lines().map(|line| line.to_string()).collect())
+}
+
+/// 备份中所有文件的路径、对象 id 和大小
+pub fn tree(backup: &Backup) -> Result<Vec<(String, String, u64)>, String> {
+    let text = git(
+        &backup.backup_folder,
+        &["ls-tree", "-r", "-l", &backup.commit],
+        &[],
+    )?;
+    Ok(text.lines().filter_map(parse_tree_line).collect())
+}
+
+/// <mode> blob <object> <size>\t<path>，不是文件时返回 None
+fn parse_tree_line(line: &str) -> Option<(String, String, u64)> {
+    let (info, path) = line.split_once('\t')?;
+    let mut parts = info.split_whitespace();
+    if parts.nth(1)? != "blob" {
+        return None;
+    }
+    let object = parts.next()?.to_string();
+    let size = parts.next()?.parse().ok()?;
+    Some((path.to_string(), object, size))
+}
+
+/// 备份中文件的对象 id 和大小，file 是仓库中的相对路径，文件不存在时返回 None
+pub fn blob_info(backup: &Backup, file: &str) -> Result<Option<(String, u64)>, String> {
+    let text = git(
+        &backup.backup_folder,
+        &["ls-tree", "-l", &backup.commit, "--", file],
+        &[],
+    )?;
+    Ok(text
+        .lines()
+        .find_map(parse_tree_line)
+        .map(|(_, object, size)| (object, size)))
+}
+
+/// 读取备份中文件的内容
+pub fn blob(backup: &Backup, file: &str) -> Result<Vec<u8>, String> {
+    git_bytes(
+        &backup.backup_folder,
+        &["cat-file", "blob", &format!("{}:{}", backup.commit, file)],
+        &[],
+    )
+}
+
+/// 文件每一行最后修改的提交，file 是仓库中的相对路径
+pub fn blame(backup: &Backup, file: &str) -> Result<String, String> {
+    git(
+        &backup.backup_folder,
+        &["blame", "--date=short", &backup.commit, "--", file],
+        &[],
+    )
+}
+
+/// 仓库的提交历史，新的在前
+pub fn history(id: &str) -> Result<serde_json::Value, String> {
+    let repo = repo_folder(id);
+    if !util::file_exist(&format!("{}/.git", repo)) {
+        return Ok(json!([]));
+    }
+    let text = git(
+        &repo,
+        &["log", "--date=iso", "--format=%H%x09%ad%x09%s"],
+        &[],
+    )?;
+    let commits: Vec<serde_json::Value> = text
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            Some(json!({
+                "commit": parts.next()?,
+                "time": parts.next()?,
+                "message": parts.next().unwrap_or_default(),
+            }))
+        })
+        .collect();
+    Ok(json!(commits))
+}
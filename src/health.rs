@@ -0,0 +1,164 @@
+use std::{fmt::Display, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::{Outcome, Trigger},
+    data::{Data, SaveItem, Status},
+    setting::HealthSetting,
+};
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum HealthStatus {
+    #[default]
+    Ok,
+    Warn,
+    Error,
+}
+impl Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HealthStatus::Ok => "Ok",
+            HealthStatus::Warn => "Warn",
+            HealthStatus::Error => "Error",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum IssueKind {
+    MissingPath,   // 监听的路径不存在
+    FailedRuns,    // 最近的自动备份失败
+    Stale,         // 很久没有备份
+    NeverBackedUp, // 还没有备份
+}
+impl Display for IssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            IssueKind::MissingPath => "Monitor path not found",
+            IssueKind::FailedRuns => "Auto backup failed",
+            IssueKind::Stale => "No backup for a long time",
+            IssueKind::NeverBackedUp => "Never backed up",
+        })
+    }
+}
+
+/// 一个问题，detail 是路径、失败的次数或者天数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    pub status: HealthStatus,
+    pub kind: IssueKind,
+    pub detail: String,
+}
+
+/// 存档的健康状态，取所有问题中最严重的
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Health {
+    pub item_id: String,
+    pub item_name: String,
+    pub status: HealthStatus,
+    pub issues: Vec<Issue>,
+}
+impl Health {
+    /// 悬浮提示用的文字，每个问题一行
+    pub fn text(&self, translate: impl Fn(&str) -> String) -> String {
+        self.issues
+            .iter()
+            .map(|issue| format!("{}: {}", translate(&issue.kind.to_string()), issue.detail))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub fn check(item: &SaveItem, setting: &HealthSetting) -> Health {
+    let mut issues = vec![];
+    for monitor in &item.monitors {
+        if !Path::new(&monitor.path).exists() {
+            issues.push(Issue {
+                status: HealthStatus::Error,
+                kind: IssueKind::MissingPath,
+                detail: monitor.path.to_string(),
+            });
+        }
+    }
+
+    // 跳过不算失败，只看最近连续失败的自动备份
+    let failed = item
+        .runs
+        .iter()
+        .filter(|run| run.trigger == Trigger::Auto && run.outcome != Outcome::Skipped)
+        .take_while(|run| run.outcome == Outcome::Failed)
+        .count();
+    if failed > 0 {
+        let status = if failed >= setting.failed_runs.max(1) {
+            HealthStatus::Error
+        } else {
+            HealthStatus::Warn
+        };
+        let reason = item
+            .runs
+            .iter()
+            .find(|run| run.trigger == Trigger::Auto && run.outcome == Outcome::Failed)
+            .map(|run| run.reason.to_string())
+            .unwrap_or_default();
+        let detail = if reason.is_empty() {
+            failed.to_string()
+        } else {
+            format!("{} ({})", failed, reason)
+        };
+        issues.push(Issue {
+            status,
+            kind: IssueKind::FailedRuns,
+            detail,
+        });
+    }
+
+    match item.backups.iter().map(|backup| backup.time).max() {
+        None => issues.push(Issue {
+            status: HealthStatus::Warn,
+            kind: IssueKind::NeverBackedUp,
+            detail: String::new(),
+        }),
+        Some(last) => {
+            let days = (chrono::Local::now().timestamp_millis() - last) / DAY_MILLIS;
+            if setting.stale_days > 0 && days >= setting.stale_days as i64 {
+                issues.push(Issue {
+                    status: HealthStatus::Warn,
+                    kind: IssueKind::Stale,
+                    detail: format!("{}d", days),
+                });
+            }
+        }
+    }
+
+    Health {
+        item_id: item.id.to_string(),
+        item_name: item.name.to_string(),
+        status: issues
+            .iter()
+            .map(|issue| issue.status)
+            .max()
+            .unwrap_or_default(),
+        issues,
+    }
+}
+
+/// 检查所有未归档的存档
+pub fn check_all(data: &Data, setting: &HealthSetting) -> Vec<Health> {
+    data.monitors
+        .iter()
+        .filter(|item| item.status == Status::Valid)
+        .map(|item| check(item, setting))
+        .collect()
+}
+
+/// 最严重的状态，用作 check 命令的退出码
+pub fn worst(healths: &[Health]) -> HealthStatus {
+    healths
+        .iter()
+        .map(|health| health.status)
+        .max()
+        .unwrap_or_default()
+}
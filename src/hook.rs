@@ -0,0 +1,218 @@
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::Backup,
+    data::SaveItem,
+    log::{Entry, Level},
+};
+
+/// 备份、恢复前后执行的命令，为空表示不执行
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Hooks {
+    pub pre_backup: String,
+    pub post_backup: String,
+    pub pre_restore: String,
+    pub post_restore: String,
+    pub timeout: u64, // 秒
+}
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            pre_backup: String::new(),
+            post_backup: String::new(),
+            pre_restore: String::new(),
+            post_restore: String::new(),
+            timeout: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookKind {
+    PreBackup,
+    PostBackup,
+    PreRestore,
+    PostRestore,
+}
+impl HookKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookKind::PreBackup => "pre_backup",
+            HookKind::PostBackup => "post_backup",
+            HookKind::PreRestore => "pre_restore",
+            HookKind::PostRestore => "post_restore",
+        }
+    }
+}
+
+impl Hooks {
+    pub fn command(&self, kind: HookKind) -> &str {
+        match kind {
+            HookKind::PreBackup => &self.pre_backup,
+            HookKind::PostBackup => &self.post_backup,
+            HookKind::PreRestore => &self.pre_restore,
+            HookKind::PostRestore => &self.post_restore,
+        }
+    }
+}
+
+/// 执行存档配置的钩子，命令未配置时直接返回成功
+pub fn run(
+    save_item: &SaveItem,
+    kind: HookKind,
+    backup: &Backup,
+    outcome: &str,
+) -> Result<(), String> {
+    let command = save_item.hooks.command(kind).trim();
+    if command.is_empty() {
+        return Ok(());
+    }
+    let envs = [
+        ("AUTO_BACKUP_HOOK", kind.name().to_string()),
+        ("AUTO_BACKUP_ITEM_ID", save_item.id.to_string()),
+        ("AUTO_BACKUP_ITEM_NAME", save_item.name.to_string()),
+        ("AUTO_BACKUP_ID", backup.id.to_string()),
+        ("AUTO_BACKUP_FOLDER", backup.backup_folder.to_string()),
+        ("AUTO_BACKUP_REMARK", backup.remark.to_string()),
+        ("AUTO_BACKUP_OUTCOME", outcome.to_string()),
+    ];
+    let result = execute(command, &envs, save_item.hooks.timeout);
+    match &result {
+        Ok(_) => Entry::new(Level::Info, format!("hook {} ok", kind.name()))
+            .item(&save_item.name)
+            .backup(&backup.id)
+            .write(),
+        Err(e) => Entry::new(Level::Error, format!("hook {} error, e={}", kind.name(), e))
+            .item(&save_item.name)
+            .backup(&backup.id)
+            .write(),
+    }
+    result
+}
+
+fn execute(command: &str, envs: &[(&str, String)], timeout: u64) -> Result<(), String> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    for (key, value) in envs {
+        cmd.env(key, value);
+    }
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("start `{}` error: {}", command, e))?;
+
+    // 单独读取 stderr，避免输出过多时子进程被管道阻塞
+    let mut stderr = child.stderr.take();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_string(&mut text);
+        }
+        let _ = tx.send(text);
+    });
+
+    let start = Instant::now();
+    let timeout = Duration::from_secs(timeout);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {
+                if start.elapsed() >= timeout {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!(
+                        "`{}` timeout after {}s",
+                        command,
+                        timeout.as_secs()
+                    ));
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => return Err(format!("wait `{}` error: {}", command, e)),
+        }
+    };
+    // 钩子在后台启动的进程会继承 stderr，管道不会关闭，最多等到超时，之后不再读取
+    let stderr = rx
+        .recv_timeout(timeout.saturating_sub(start.elapsed()))
+        .unwrap_or_default();
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "`{}` exit with {}; {}",
+            command,
+            status,
+            stderr.trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_notify::ToastLevel;
+
+    use super::*;
+    use crate::backup::{Outcome, Trigger};
+
+    #[test]
+    fn failed_pre_backup_aborts_backup() {
+        let save_item = SaveItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: "hook".to_string(),
+            hooks: Hooks {
+                pre_backup: "exit 3".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let folder = crate::backup::new_backup_folder(&save_item.id);
+        let (msgs, backup, run) = Backup::run(save_item, String::new(), Trigger::Manual);
+        assert!(backup.is_none());
+        assert_eq!(run.outcome, Outcome::Failed);
+        assert!(run.backup_id.is_empty());
+        assert!(run.reason.contains("exit 3"));
+        assert!(msgs.iter().all(|(level, _)| *level == ToastLevel::Error));
+        let folder = std::path::Path::new(&folder);
+        assert!(!folder.exists() && !folder.parent().unwrap().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_hook() {
+        let start = Instant::now();
+        let result = execute("sleep 5", &[], 1);
+        assert!(result.unwrap_err().contains("timeout after 1s"));
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn background_child_does_not_block() {
+        // 后台进程继承了 stderr，钩子退出后管道仍然打开
+        let start = Instant::now();
+        let result = execute("sleep 5 & echo failed >&2; exit 1", &[], 1);
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+}
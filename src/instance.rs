@@ -0,0 +1,220 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::mpsc::Sender,
+    time::Duration,
+};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{cli, entity::Command, log, util};
+
+const LOCK_PATH: &str = "./data/instance.lock";
+const PORT_PATH: &str = "./data/instance.port";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10); // 读取请求、发送请求和回复
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60 * 60); // 备份大文件可能很久
+
+/// 只允许一个实例使用数据目录，后启动的实例把命令转发给正在运行的实例
+pub enum Instance {
+    Primary(InstanceLock),
+    Secondary,
+}
+
+/// 持有期间独占数据目录，进程退出后由系统释放
+pub struct InstanceLock {
+    _file: Option<File>,
+    listening: bool, // 写入了端口文件，退出时删除
+}
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if self.listening {
+            let _ = std::fs::remove_file(PORT_PATH);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct IpcRequest {
+    token: String,
+    args: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IpcResponse {
+    ok: bool,
+    data: Value,
+    error: String,
+}
+
+fn open_lock_file() -> std::io::Result<File> {
+    util::check_dir_and_create("./data");
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(LOCK_PATH)
+}
+
+pub fn acquire() -> Instance {
+    match open_lock_file() {
+        Ok(file) => {
+            if file.try_lock_exclusive().is_ok() {
+                Instance::Primary(InstanceLock {
+                    _file: Some(file),
+                    listening: false,
+                })
+            } else {
+                Instance::Secondary
+            }
+        }
+        Err(e) => {
+            // 无法加锁时不阻止启动
+            log::log_err(format!("open lock file {} error: {}", LOCK_PATH, e));
+            Instance::Primary(InstanceLock {
+                _file: None,
+                listening: false,
+            })
+        }
+    }
+}
+
+/// 等待正在运行的实例退出后再占用数据目录
+pub fn wait() -> InstanceLock {
+    match open_lock_file() {
+        Ok(file) => {
+            if let Err(e) = file.lock_exclusive() {
+                log::log_err(format!("lock {} error: {}", LOCK_PATH, e));
+            }
+            InstanceLock {
+                _file: Some(file),
+                listening: false,
+            }
+        }
+        Err(e) => {
+            log::log_err(format!("open lock file {} error: {}", LOCK_PATH, e));
+            InstanceLock {
+                _file: None,
+                listening: false,
+            }
+        }
+    }
+}
+
+/// 监听本地端口，接收其他实例转发过来的命令
+pub fn listen(lock: &mut InstanceLock, sender: Sender<Command>) {
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::log_err(format!("start ipc listener error: {}", e));
+            return;
+        }
+    };
+    let port = match listener.local_addr() {
+        Ok(addr) => addr.port(),
+        Err(e) => {
+            log::log_err(format!("get ipc listener addr error: {}", e));
+            return;
+        }
+    };
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    if util::write_data(PORT_PATH, format!("{}\n{}", port, token)).is_err() {
+        return;
+    }
+    lock.listening = true;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            let token = token.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle(stream, &sender, &token) {
+                    log::log_err(format!("ipc handle error: {}", e));
+                }
+            });
+        }
+    });
+}
+
+fn handle(stream: TcpStream, sender: &Sender<Command>, token: &str) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    if line.trim().is_empty() {
+        // 只是检查实例是否在运行
+        return Ok(());
+    }
+    let result = match serde_json::from_str::<IpcRequest>(&line) {
+        Ok(request) if request.token == token => {
+            log::log_info(format!("ipc command: {:?}", request.args));
+            cli::run(&request.args, sender)
+        }
+        Ok(_) => Err("invalid token".to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let response = match result {
+        Ok(data) => IpcResponse {
+            ok: true,
+            data,
+            error: String::new(),
+        },
+        Err(error) => IpcResponse {
+            ok: false,
+            data: Value::Null,
+            error,
+        },
+    };
+    let mut stream = stream;
+    writeln!(stream, "{}", serde_json::to_string(&response)?)
+}
+
+/// 连接正在运行的实例，只执行一次命令行的实例不接收转发
+fn connect() -> Option<(TcpStream, String)> {
+    let data = util::read_data(PORT_PATH).ok()?;
+    let mut lines = data.lines();
+    let port = lines.next().unwrap_or_default().trim().to_string();
+    let token = lines.next().unwrap_or_default().trim().to_string();
+    let port: u16 = port.parse().ok()?;
+    // 端口文件可能是异常退出的实例留下的，端口已经被其他程序占用，不能无限等待
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).ok()?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT)).ok()?;
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT)).ok()?;
+    Some((stream, token))
+}
+
+/// 是否有可以接收转发的实例在运行
+pub fn reachable() -> bool {
+    connect().is_some()
+}
+
+/// 把命令转发给正在运行的实例，无法连接时返回 None
+pub fn forward(args: &[String]) -> Option<Result<Value, String>> {
+    let (stream, token) = connect()?;
+    Some(send(stream, token, args))
+}
+
+fn send(mut stream: TcpStream, token: String, args: &[String]) -> Result<Value, String> {
+    let request = IpcRequest {
+        token,
+        args: args.to_vec(),
+    };
+    let request = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+    writeln!(stream, "{}", request).map_err(|e| e.to_string())?;
+
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .map_err(|e| e.to_string())?;
+    let response: IpcResponse = serde_json::from_str(&line)
+        .map_err(|e| format!("invalid response from running instance: {}", e))?;
+    if response.ok {
+        Ok(response.data)
+    } else {
+        Err(response.error)
+    }
+}
@@ -9,15 +9,17 @@ use std::{
 // use cron_job::{CronJob, Job};
 
 use crate::{
-    backup::{self, Backup},
+    backup::{Backup, Run, Trigger},
     data::{AutoStatus, Data, SaveItem, Status},
-    log,
+    log::{self, Entry, Level},
+    notify,
 };
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum DataSignal {
     Terminated,
-    Sync(String, Backup),
+    Sync(String, Run, Option<Backup>), // 被跳过或中止的任务没有备份
 }
 
 #[derive(Debug, Default, Clone)]
@@ -37,18 +39,29 @@ pub fn receive_data_signal(
             // println!("got a signal = {:?}", signal);
             match signal {
                 DataSignal::Terminated => return,
-                DataSignal::Sync(id, backup) => {
+                DataSignal::Sync(id, run, mut backup) => {
                     if let Ok(mut data) = data.lock() {
                         for item in &mut data.monitors {
                             if id == item.id {
-                                item.backups.insert(0, backup.clone());
+                                if let Some(backup) = &mut backup {
+                                    // 任务中的存档可能是切换配置之前的
+                                    backup.profile = item.profile.to_string();
+                                    item.backups.insert(0, backup.clone());
+                                }
+                                notify::notify(item, &run);
+                                item.push_run(run.clone());
                                 data.save();
                                 break;
                             }
                         }
                     }
                     if let Ok(mut active) = active.lock() {
-                        active.backups.insert(0, backup);
+                        if active.id == id {
+                            if let Some(backup) = backup {
+                                active.backups.insert(0, backup);
+                            }
+                            active.push_run(run);
+                        }
                     }
                 }
             }
@@ -64,19 +77,37 @@ pub struct BackupJob {
 
 impl Job for BackupJob {
     fn run(&mut self) {
-        log::log_time(format!(
-            "job start, name={} monitros={:?}",
-            self.save_item.name, self.save_item.monitors
-        ));
+        let cron = self.save_item.auto.cron.to_string();
+        Entry::new(
+            Level::Debug,
+            format!("job start, monitors={:?}", self.save_item.monitors),
+        )
+        .item(&self.save_item.name)
+        .job(&cron)
+        .write();
         let id = self.save_item.id.to_string();
-        let remark = "Auto Backup".to_string();
-        let backup =
-            backup::backup_file(&id, remark, self.save_item.monitors.to_vec(), &mut vec![]);
-        if let Err(e) = self.sender.send(DataSignal::Sync(id, backup)) {
-            log::log_err(format!(
-                "auto backup success, but associate to data error; e: {}",
-                e
-            ));
+        let signal = match self.save_item.auto.condition.check() {
+            Ok(_) => {
+                let remark = "Auto Backup".to_string();
+                let (_, backup, run) = Backup::run(self.save_item.clone(), remark, Trigger::Auto);
+                DataSignal::Sync(id, run, backup)
+            }
+            Err(reason) => {
+                Entry::new(Level::Info, format!("job skipped, reason={}", reason))
+                    .item(&self.save_item.name)
+                    .job(&cron)
+                    .write();
+                DataSignal::Sync(id, Run::skipped(Trigger::Auto, reason), None)
+            }
+        };
+        if let Err(e) = self.sender.send(signal) {
+            Entry::new(
+                Level::Error,
+                format!("auto backup success, but associate to data error; e: {}", e),
+            )
+            .item(&self.save_item.name)
+            .job(&cron)
+            .write();
             // println!("auto bk err {}", e);
         }
     }
@@ -1,12 +1,37 @@
+pub mod api;
+pub mod background;
 pub mod backup;
+pub mod bulk;
+pub mod bundle;
+pub mod catalog;
+pub mod changelog;
+pub mod cli;
+pub mod condition;
 pub mod data;
 pub mod entity;
+pub mod filehistory;
+pub mod filter;
+pub mod gamedb;
+pub mod gitrepo;
+pub mod health;
+pub mod hook;
 pub mod il8n;
+pub mod instance;
 pub mod job;
 pub mod log;
+pub mod manifest;
+pub mod notify;
+pub mod profile;
+pub mod rescan;
+pub mod screenshot;
+pub mod search;
 pub mod setting;
 pub mod statis;
+pub mod steam;
+pub mod storage;
 pub mod style;
+pub mod timeline;
+pub mod trash;
 pub mod util;
 pub mod view;
 pub mod windows;
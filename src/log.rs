@@ -1,46 +1,364 @@
-#![allow(unused)]
+use std::{
+    fmt::Display,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
 
-use std::io::Write;
-use std::{fmt::Debug, fs::OpenOptions};
+use serde::{Deserialize, Serialize};
 
-use chrono::Local;
+use crate::{setting::LogSetting, util};
 
-use crate::util;
+/// 日志保存在 ./log，每行一条 json
+const LOG_FOLDER: &str = "log";
+const LOG_FILE: &str = "auto_backup.log";
+/// 跨天或超过大小后改名为 auto_backup.<时间>.log
+const ROTATED_PREFIX: &str = "auto_backup.";
+const ROTATED_FMT: &str = "%Y%m%d_%H%M%S";
 
-pub fn log_time(arg: impl Debug) {
-    let time = chrono::Local::now();
-    println!("{} {:?}", time.format("%m-%d %H:%M:%S%.3f"), arg);
+static SETTING: Mutex<Option<LogSetting>> = Mutex::new(None);
+static WRITING: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Level {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|level| level.to_string().eq_ignore_ascii_case(text))
+            .ok_or(format!(
+                "unknown level: {}, expected debug, info, warn or error",
+                text
+            ))
+    }
+
+    fn to_log(self) -> ::log::Level {
+        match self {
+            Level::Debug => ::log::Level::Debug,
+            Level::Info => ::log::Level::Info,
+            Level::Warn => ::log::Level::Warn,
+            Level::Error => ::log::Level::Error,
+        }
+    }
+}
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Level::Debug => "Debug",
+            Level::Info => "Info",
+            Level::Warn => "Warn",
+            Level::Error => "Error",
+        })
+    }
+}
+
+/// 一条日志，存档、备份和任务为空时不记录
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Entry {
+    pub time: i64,
+    pub level: Level,
+    pub message: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub item: String, // 存档名称
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub backup: String, // 备份 id
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub job: String, // 自动备份任务的 cron
+}
+impl Entry {
+    pub fn new(level: Level, message: impl Display) -> Self {
+        Self {
+            time: chrono::Local::now().timestamp_millis(),
+            level,
+            message: message.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn item(mut self, item: &str) -> Self {
+        self.item = item.to_string();
+        self
+    }
+
+    pub fn backup(mut self, backup: &str) -> Self {
+        self.backup = backup.to_string();
+        self
+    }
+
+    pub fn job(mut self, job: &str) -> Self {
+        self.job = job.to_string();
+        self
+    }
+
+    /// 低于设置的级别时不记录
+    pub fn write(self) {
+        let setting = setting();
+        if self.level < setting.level {
+            return;
+        }
+        ::log::log!(target: "auto_backup", self.level.to_log(), "{}", self.text());
+        let Ok(line) = serde_json::to_string(&self) else {
+            return;
+        };
+        let _writing = WRITING.lock();
+        let folder = log_folder();
+        if let Err(e) = std::fs::create_dir_all(&folder) {
+            eprintln!("create log folder {} error: {}", folder, e);
+            return;
+        }
+        let path = format!("{}/{}", folder, LOG_FILE);
+        rotate(&path, &setting);
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(mut file) => {
+                let _ = writeln!(file, "{}", line);
+            }
+            Err(e) => eprintln!("open log file {} error: {}", path, e),
+        }
+    }
+
+    /// 单行文本，用于控制台输出
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for (key, value) in [
+            ("item", &self.item),
+            ("backup", &self.backup),
+            ("job", &self.job),
+        ] {
+            if !value.is_empty() {
+                text.push_str(&format!("{}={} ", key, value));
+            }
+        }
+        text.push_str(&self.message);
+        text
+    }
+}
+
+pub fn log_debug(arg: impl Display) {
+    Entry::new(Level::Debug, arg).write();
+}
+
+pub fn log_info(arg: impl Display) {
+    Entry::new(Level::Info, arg).write();
+}
+
+pub fn log_err(arg: impl Display) {
+    Entry::new(Level::Error, arg).write();
+}
+
+pub fn log_folder() -> String {
+    format!("{}/{}", util::current_dir(), LOG_FOLDER)
 }
-pub fn log_info(arg: impl Debug) {
-    do_log("info", arg);
+
+fn setting() -> LogSetting {
+    SETTING
+        .lock()
+        .ok()
+        .and_then(|setting| setting.clone())
+        .unwrap_or_default()
 }
-pub fn log_debug(arg: impl Debug) {
-    do_log("debug", arg);
+
+/// 启动时调用：应用日志设置，控制台输出由 RUST_LOG 控制，默认不输出
+pub fn init(setting: &LogSetting) {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("off"))
+        .try_init();
+    configure(setting);
+}
+
+/// 修改设置后立即生效，并清理过期的日志
+pub fn configure(setting: &LogSetting) {
+    if let Ok(mut current) = SETTING.lock() {
+        *current = Some(setting.clone());
+    }
+    let _writing = WRITING.lock();
+    clean(setting);
 }
 
-pub fn log_err(arg: impl Debug) {
-    do_log("error", arg);
+/// 当前日志不是今天写的，或超过大小后改名，大小为 0 表示不限制
+fn rotate(path: &str, setting: &LogSetting) {
+    let Ok(meta) = std::fs::metadata(path) else {
+        return;
+    };
+    let Ok(modified) = meta.modified().map(chrono::DateTime::<chrono::Local>::from) else {
+        return;
+    };
+    let max = setting.max_size_mb * 1024 * 1024;
+    let oversize = max > 0 && meta.len() >= max;
+    if !oversize && modified.date_naive() == chrono::Local::now().date_naive() {
+        return;
+    }
+    let rotated = rotated_path(&modified);
+    if let Err(e) = std::fs::rename(path, &rotated) {
+        eprintln!("rotate log file {} error: {}", path, e);
+    }
+    clean(setting);
 }
 
-const TIME_FMT: &str = "%m-%d %H:%M:%S";
-fn do_log(level: &str, arg: impl Debug) {
-    // let fmt = "%Y年%m月%d日 %H:%M:%S";
-    let time = chrono::Local::now();
-    let mut arg = format!("[{}] {} {:?}", level, time.format(TIME_FMT), arg);
-    if level.eq("info") {
-        arg = arg
-            .replace("\\\\", "/")
-            .replace("\\\"", "")
-            .replace("\"", "");
+/// 按最后写入的时间命名，跨天改名的文件名就是那一天
+///
+/// 同一秒改名多次时加上序号，序号补零，按文件名排序仍然是旧的在前
+fn rotated_path(modified: &chrono::DateTime<chrono::Local>) -> String {
+    let name = format!(
+        "{}/{}{}",
+        log_folder(),
+        ROTATED_PREFIX,
+        modified.format(ROTATED_FMT)
+    );
+    let mut path = format!("{}.log", name);
+    let mut index = 1;
+    while util::file_exist(&path) {
+        path = format!("{}_{:03}.log", name, index);
+        index += 1;
     }
+    path
+}
 
-    let fmt = "%Y-%m";
-    let now = Local::now().format(fmt);
-    let path = format!("./log/{}.txt", now);
-    if !util::file_exist(&path) {
-        let _create = std::fs::File::create(&path);
+/// 改名后的日志文件，旧的在前
+///
+/// 写日志时会调用，不能使用会写日志的 util::read_dir
+fn rotated_files() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(log_folder()) else {
+        return vec![];
+    };
+    let mut files: Vec<String> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| {
+            name.starts_with(ROTATED_PREFIX) && name.ends_with(".log") && name != LOG_FILE
+        })
+        .map(|name| format!("{}/{}", log_folder(), name))
+        .collect();
+    files.sort();
+    files
+}
+
+/// 删除超过保留天数的日志，0 表示永久保留
+fn clean(setting: &LogSetting) {
+    if setting.keep_days == 0 {
+        return;
+    }
+    let keep = Duration::from_secs(setting.keep_days as u64 * 24 * 60 * 60);
+    for file in rotated_files() {
+        let expired = std::fs::metadata(&file)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > keep);
+        if expired {
+            let _ = std::fs::remove_file(&file);
+        }
     }
-    if let Ok(mut file) = OpenOptions::new().append(true).open(path) {
-        let _ = writeln!(file, "{}", arg);
+}
+
+/// 日志的筛选条件，空的条件不限制
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogFilter {
+    pub level: Level,    // 最低级别
+    pub item: String,    // 存档名称
+    pub keyword: String, // 内容、备份或任务包含的文字，不区分大小写
+    pub limit: usize,    // 最多返回的条数，0 表示不限制
+}
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            level: Level::Debug,
+            item: String::new(),
+            keyword: String::new(),
+            limit: 500,
+        }
+    }
+}
+impl LogFilter {
+    pub fn matches(&self, entry: &Entry) -> bool {
+        if entry.level < self.level {
+            return false;
+        }
+        if !self.item.is_empty() && !entry.item.eq_ignore_ascii_case(&self.item) {
+            return false;
+        }
+        let keyword = self.keyword.trim().to_lowercase();
+        keyword.is_empty()
+            || [&entry.message, &entry.backup, &entry.job]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&keyword))
+    }
+}
+
+/// 读取日志，新的在前
+pub fn read(filter: &LogFilter) -> Vec<Entry> {
+    let mut files = rotated_files();
+    files.push(format!("{}/{}", log_folder(), LOG_FILE));
+    let mut entries = vec![];
+    for file in files.iter().rev() {
+        if !Path::new(file).is_file() {
+            continue;
+        }
+        let Ok(reader) = std::fs::File::open(file).map(BufReader::new) else {
+            continue;
+        };
+        let mut lines: Vec<Entry> = reader
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str::<Entry>(&line).ok())
+            .filter(|entry| filter.matches(entry))
+            .collect();
+        lines.reverse();
+        entries.extend(lines);
+        if filter.limit > 0 && entries.len() >= filter.limit {
+            entries.truncate(filter.limit);
+            break;
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn rotated_names_are_unique() {
+        util::check_dir_and_create(&log_folder());
+        // 不会和真正改名的日志冲突的时间
+        let time = chrono::Local.with_ymd_and_hms(2001, 2, 3, 4, 5, 6).unwrap();
+        let mut paths = vec![];
+        for _ in 0..3 {
+            let path = rotated_path(&time);
+            std::fs::write(&path, "").unwrap();
+            paths.push(path);
+        }
+        let names: Vec<&str> = paths
+            .iter()
+            .map(|path| path.rsplit('/').next().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "auto_backup.20010203_040506.log",
+                "auto_backup.20010203_040506_001.log",
+                "auto_backup.20010203_040506_002.log",
+            ]
+        );
+        let rotated: Vec<String> = rotated_files()
+            .into_iter()
+            .filter(|file| file.contains("20010203_040506"))
+            .collect();
+        assert_eq!(rotated, paths);
+        for path in paths {
+            let _ = std::fs::remove_file(path);
+        }
     }
 }
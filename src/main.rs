@@ -2,33 +2,132 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 #![allow(rustdoc::missing_crate_level_docs)] // it's an example
 
+mod api;
+mod background;
 mod backup;
+mod bulk;
+mod bundle;
+mod catalog;
+mod changelog;
+mod cli;
+mod condition;
 mod data;
 mod entity;
+mod filehistory;
+mod filter;
+mod gamedb;
+mod gitrepo;
+mod health;
+mod hook;
 mod il8n;
+mod instance;
 mod job;
 mod log;
+mod manifest;
+mod notify;
+mod profile;
+mod rescan;
+mod screenshot;
+mod search;
 mod setting;
 mod statis;
+mod steam;
+mod storage;
 mod style;
+mod timeline;
+mod trash;
 mod util;
 mod view;
 mod windows;
 
-use std::sync::mpsc::Receiver;
+use std::sync::{mpsc::Receiver, Arc, Mutex};
+
+use backup::{Backup, Outcome, Trigger};
+use bulk::BulkAction;
+use changelog::Change;
+use data::{Data, SaveItem};
 
 use eframe::{
     egui::{CentralPanel, Context, Vec2, ViewportBuilder, WindowLevel},
     Frame, HardwareAcceleration,
 };
-use entity::{AutoBackup, Command, Navigation};
+use egui_notify::ToastLevel;
+use entity::{respond, AutoBackup, Command, Navigation};
+use job::JobHandle;
+use log::{Entry, Level};
+use serde_json::{json, Value};
+use setting::Setting;
 use windows::ShowConfirm;
 
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // 不带参数或 show 打开主窗口，daemon 只在后台运行
+    let gui = args.is_empty() || args == ["show"];
+    let daemon = args == ["daemon"];
+    let setting = Setting::default();
+    log::init(&setting.log);
+    notify::configure(&setting.notify);
+
+    // 同一个数据目录只能有一个实例，已经有实例运行时把命令转发过去
+    let mut lock = match instance::acquire() {
+        instance::Instance::Primary(lock) => lock,
+        instance::Instance::Secondary => {
+            if daemon && instance::reachable() {
+                println!("AutoBackup is already running");
+                return Ok(());
+            }
+            if !daemon {
+                let args = if gui {
+                    vec!["show".to_string()]
+                } else {
+                    args.clone()
+                };
+                if let Some(result) = instance::forward(&args) {
+                    std::process::exit(cli::finish(&args, result));
+                }
+            }
+            // 正在运行的是只执行一次的命令行，等待它结束
+            instance::wait()
+        }
+    };
+
     let mut auto_backup = AutoBackup::default();
 
     auto_backup.init();
 
+    if !gui && !daemon {
+        let result = cli::run(&args, auto_backup.command.as_ref().unwrap());
+        notify::wait();
+        std::process::exit(cli::finish(&args, result));
+    }
+    instance::listen(&mut lock, auto_backup.command.clone().unwrap());
+    auto_backup.start_service();
+
+    let window = auto_backup.window.clone();
+    if daemon {
+        log::log_info("run in background".to_string());
+    }
+    // 窗口只是前端，关闭后根据设置决定是否继续在后台运行
+    let mut show = !daemon || window.wait();
+    while show {
+        run_window(auto_backup.attach())?;
+        window.closed();
+        let background = daemon || Setting::default().background;
+        if window.quitting() || !background {
+            break;
+        }
+        log::log_info("window closed, keep running in background".to_string());
+        show = window.wait();
+    }
+    if window.quitting() {
+        // 留一点时间把回复发给请求退出的一方
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    Ok(())
+}
+
+/// 打开主窗口，关闭窗口后返回
+fn run_window(mut auto_backup: AutoBackup) -> Result<(), eframe::Error> {
     let viewport = ViewportBuilder {
         title: None,
         app_id: Some("AutoBackup".to_string()),
@@ -68,7 +167,7 @@ fn main() -> Result<(), eframe::Error> {
         renderer: eframe::Renderer::Glow,
         follow_system_theme: false,
         default_theme: auto_backup.setting.get_theme(),
-        run_and_return: true, // 关闭窗口退出程序
+        run_and_return: true, // 关闭窗口后返回，由后台设置决定是否退出程序
         event_loop_builder: None,
         window_builder: None,
         shader_version: None,
@@ -85,6 +184,7 @@ fn main() -> Result<(), eframe::Error> {
             style::load_fonts(&cc.egui_ctx);
             // 控制缩放
             cc.egui_ctx.set_pixels_per_point(1.5);
+            auto_backup.window.opened(&cc.egui_ctx);
 
             Box::new(auto_backup)
         }),
@@ -93,6 +193,8 @@ fn main() -> Result<(), eframe::Error> {
 
 impl eframe::App for AutoBackup {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        self.receive_replies(ctx);
+        self.refresh_health();
         CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut self.control.nav, Navigation::Home, self.t.get("Home"));
@@ -115,6 +217,37 @@ impl eframe::App for AutoBackup {
                     Navigation::Archive,
                     self.t.get("Archive"),
                 );
+                ui.selectable_value(
+                    &mut self.control.nav,
+                    Navigation::Search,
+                    self.t.get("Search"),
+                );
+                ui.selectable_value(
+                    &mut self.control.nav,
+                    Navigation::Timeline,
+                    self.t.get("Timeline"),
+                );
+                ui.selectable_value(
+                    &mut self.control.nav,
+                    Navigation::Storage,
+                    self.t.get("Storage"),
+                );
+                if ui
+                    .selectable_value(
+                        &mut self.control.nav,
+                        Navigation::Trash,
+                        self.t.get("Trash"),
+                    )
+                    .clicked()
+                {
+                    self.control.trash = None;
+                }
+                if ui
+                    .selectable_value(&mut self.control.nav, Navigation::Logs, self.t.get("Logs"))
+                    .clicked()
+                {
+                    self.control.logs = None;
+                }
                 ui.selectable_value(
                     &mut self.control.nav,
                     Navigation::Setting,
@@ -128,6 +261,11 @@ impl eframe::App for AutoBackup {
                 Navigation::New => self.new_view(ui),
                 Navigation::Edit => self.new_view(ui),
                 Navigation::Archive => self.archive_view(ctx, ui),
+                Navigation::Search => self.search_view(ui),
+                Navigation::Timeline => self.timeline_view(ui),
+                Navigation::Storage => self.storage_view(ui),
+                Navigation::Trash => self.trash_view(ui),
+                Navigation::Logs => self.logs_view(ui),
                 Navigation::Setting => self.setting_view(ctx, ui),
             }
         });
@@ -148,6 +286,33 @@ impl eframe::App for AutoBackup {
         if self.control.show_confirm_totally_del.is_show() {
             self.confirm_totally_delete(ctx);
         }
+        if self.control.show_confirm_purge_trash.is_show() {
+            self.confirm_purge_trash(ctx);
+        }
+        if self.control.show_confirm_switch_profile.is_show() {
+            self.confirm_switch_profile(ctx);
+        }
+        if self.control.show_export.is_show() {
+            self.export_window(ctx);
+        }
+        if self.control.show_confirm_import.is_show() {
+            self.confirm_import(ctx);
+        }
+        if self.control.show_rescan.is_show() {
+            self.rescan_window(ctx);
+        }
+        if self.control.show_scan_games.is_show() {
+            self.scan_games_window(ctx);
+        }
+        if self.control.show_git.is_show() {
+            self.git_window(ctx);
+        }
+        if self.control.show_file_history.is_show() {
+            self.file_history_window(ctx);
+        }
+        if self.control.show_item_history.is_show() {
+            self.item_history_window(ctx);
+        }
 
         self.toasts.show(ctx);
         // ui.image(egui::include_image!("./assets/icon.ico"));
@@ -156,89 +321,781 @@ impl eframe::App for AutoBackup {
 
 impl AutoBackup {
     fn init(&mut self) {
-        self.form.new = true;
-        self.control.show_backup_remark = -1;
-        self.t.change(&self.setting.lang);
+        self.init_view();
 
         let (tx, rx) = std::sync::mpsc::channel();
         self.command = Some(tx);
         self.command_proc(rx);
+    }
+
+    fn init_view(&mut self) {
+        self.form.new = true;
+        self.control.show_backup_remark = -1;
+        self.t.change(&self.setting.lang);
+    }
+
+    /// 创建新的窗口前端，和后台共享数据、备份任务和命令通道
+    fn attach(&self) -> AutoBackup {
+        let mut auto_backup = AutoBackup {
+            control: Default::default(),
+            statis: Default::default(),
+            setting: Setting::default(),
+            t: Default::default(),
+            job_handle: self.job_handle.clone(),
+            form: Default::default(),
+            active: self.active.clone(),
+            data: self.data.clone(),
+            toasts: Default::default(),
+            command: self.command.clone(),
+            window: self.window.clone(),
+        };
+        auto_backup.init_view();
+        auto_backup
+    }
+
+    /// 启动自动备份任务和本地接口，只执行一次命令行时不需要
+    fn start_service(&self) {
         let _ = self.command.clone().unwrap().send(Command::StartJob);
+        let _ = self.command.clone().unwrap().send(Command::MeasureStorage);
+
+        // 定时清理回收站中过期的内容
+        let command = self.command.clone().unwrap();
+        std::thread::spawn(move || {
+            while command.send(Command::PurgeExpiredTrash).is_ok() {
+                std::thread::sleep(std::time::Duration::from_secs(60 * 60));
+            }
+        });
+
+        if self.setting.api.open {
+            api::start(&self.setting.api, self.command.clone().unwrap());
+        }
     }
 
     fn command_proc(&self, rx: Receiver<Command>) {
         let active = self.active.clone();
         let data = self.data.clone();
         let job_handle = self.job_handle.clone();
+        let window = self.window.clone();
         let _ = std::thread::spawn(move || loop {
             if let Ok(commamd) = rx.recv() {
                 match commamd {
-                    Command::StartJob => {
-                        if let Ok(_data) = data.try_lock() {
-                            if let Ok(mut job_handle) = job_handle.try_lock() {
-                                let job_rx = job_handle.start_job(_data.list());
-                                job::receive_data_signal(data.clone(), active.clone(), job_rx);
-                                if let Ok(start_infos) = job_handle.start_infos.try_lock() {
-                                    
-                                    for item in start_infos.to_vec() {
-                                        if !item.success {
-                                            let error = item.error;
-                                            log::log_err(format!("start job error = {:?}", error));
+                    Command::StartJob => start_job(&data, &active, &job_handle),
+                    Command::ProtectBackup {
+                        id,
+                        backup_id,
+                        protect,
+                        reply,
+                    } => {
+                        let result = match data.lock() {
+                            Ok(mut data) => data.protect_backup(&id, &backup_id, protect),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let result = result.map(|item| {
+                            sync_active(&active, &item);
+                            json!({ "id": backup_id, "protect": protect })
+                        });
+                        respond(&reply, result);
+                    }
+                    Command::DeleteBackup {
+                        id,
+                        backup_id,
+                        reply,
+                    } => {
+                        // 删除的备份移到回收站
+                        let result =
+                            match data.lock() {
+                                Ok(mut data) => data.remove_backup(&id, &backup_id).and_then(
+                                    |(item, backup)| match trash::trash_backup(&item, &backup) {
+                                        Ok(entry) => Ok((item, entry)),
+                                        Err(e) => {
+                                            let _ = data.insert_backup(&item.id, backup);
+                                            Err(e)
                                         }
-                                    }
+                                    },
+                                ),
+                                Err(e) => Err(e.to_string()),
+                            };
+                        let result = result.map(|(item, entry)| {
+                            sync_active(&active, &item);
+                            json!({ "id": backup_id, "trash_id": entry.id })
+                        });
+                        if let Err(e) = &result {
+                            log::log_err(format!("delete backup error: {}", e));
+                        }
+                        respond(&reply, result);
+                    }
+                    Command::BulkBackups { id, request, reply } => {
+                        let now = chrono::Local::now().timestamp_millis();
+                        let selected = match data.lock() {
+                            Ok(data) => data
+                                .find(&id)
+                                .cloned()
+                                .ok_or(format!("save item not found: {}", id)),
+                            Err(e) => Err(e.to_string()),
+                        }
+                        .and_then(|item| {
+                            let backups: Vec<Backup> = request
+                                .selector
+                                .select(&item.backups, now)?
+                                .into_iter()
+                                .cloned()
+                                .collect();
+                            Ok((item, backups))
+                        });
+                        let Ok((item, backups)) = selected else {
+                            respond(&reply, selected.map(|_| json!(null)));
+                            continue;
+                        };
+                        let ids: Vec<String> = backups.iter().map(|b| b.id.to_string()).collect();
+                        if request.dry_run || ids.is_empty() {
+                            let backups: Vec<Value> = backups
+                                .iter()
+                                .map(|b| {
+                                    json!({ "id": b.id, "time": b.time, "trigger": b.trigger,
+                                            "protect": b.protect, "remark": b.remark })
+                                })
+                                .collect();
+                            respond(
+                                &reply,
+                                Ok(json!({ "action": request.action.name(), "dry_run": true,
+                                           "matched": ids.len(), "backups": backups })),
+                            );
+                            continue;
+                        }
+                        // 删除和保护需要修改数据，导出和校验可能很久，不占用数据锁
+                        let result = match &request.action {
+                            BulkAction::Delete | BulkAction::Protect | BulkAction::Unprotect => {
+                                match data.lock() {
+                                    Ok(mut data) => match request.action {
+                                        BulkAction::Delete => {
+                                            bulk::delete(&mut data, &item.id, &ids)
+                                        }
+                                        _ => bulk::protect(
+                                            &mut data,
+                                            &item.id,
+                                            &ids,
+                                            request.action == BulkAction::Protect,
+                                        ),
+                                    },
+                                    Err(e) => Err(e.to_string()),
                                 }
+                                .map(|(item, results)| {
+                                    sync_active(&active, &item);
+                                    results
+                                })
+                            }
+                            BulkAction::Export { path } => {
+                                bundle::export(&item, &ids, path).map(|_| bulk::succeeded(&ids))
                             }
+                            BulkAction::Verify => Ok(bulk::verify_all(&backups)),
+                        };
+                        let result = result.map(|results| {
+                            let failed = results.iter().filter(|r| r.error.is_some()).count();
+                            Entry::new(
+                                Level::Info,
+                                format!(
+                                    "bulk {}, matched={}, failed={}",
+                                    request.action.name(),
+                                    ids.len(),
+                                    failed
+                                ),
+                            )
+                            .item(&item.name)
+                            .write();
+                            json!({ "action": request.action.name(), "dry_run": false,
+                                    "matched": ids.len(), "failed": failed, "results": results })
+                        });
+                        if let Err(e) = &result {
+                            log::log_err(format!("bulk {} error: {}", request.action.name(), e));
                         }
+                        respond(&reply, result);
                     }
-                    Command::ProtectBackup { id, protect } => {
-                        if let Ok(mut active) = active.try_lock() {
-                            for item in &mut active.backups {
-                                if item.id == id {
-                                    item.protect = protect;
-                                    break;
+                    Command::TotallyDelete { save_item } => {
+                        // 存档和所有备份移到回收站，然后剔除掉删除的数据
+                        match trash::trash_save_item(&save_item) {
+                            Ok(_) => {
+                                if let Ok(mut data) = data.lock() {
+                                    data.monitors.retain(|item| item.id != save_item.id);
+                                    data.save();
                                 }
                             }
+                            Err(e) => log::log_err(format!("totally delete error: {}", e)),
                         }
                     }
-                    Command::DeleteBackup { id, backup_id } => {
-                        let mut save = None;
-                        if let Ok(mut active) = active.try_lock() {
-                            for (index, item) in active.backups.to_vec().iter().enumerate() {
-                                if item.id == backup_id {
-                                    active.backups.remove(index);
-                                    save = Some(active.clone());
-                                    util::delete_dirs(&item.backup_folder);
-                                    break;
+                    Command::ListSaveItems { filter, reply } => {
+                        let result = match data.lock() {
+                            Ok(data) => Ok(filter
+                                .apply(&data.monitors)
+                                .iter()
+                                .map(|item| item.summary())
+                                .collect()),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::ListBackups { id, keyword, reply } => {
+                        let result = match data.lock() {
+                            Ok(data) => match data.find(&id) {
+                                Some(item) => Ok(json!(item
+                                    .backups
+                                    .iter()
+                                    .filter(|backup| backup.matches(&keyword))
+                                    .collect::<Vec<_>>())),
+                                None => Err(format!("save item not found: {}", id)),
+                            },
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::UpdateBackup {
+                        id,
+                        backup_id,
+                        mut update,
+                        reply,
+                    } => {
+                        // 截图先复制到存档的截图目录
+                        if let Some(source) = update.screenshot.clone().filter(|s| !s.is_empty()) {
+                            let item_id = data
+                                .lock()
+                                .ok()
+                                .and_then(|data| data.find(&id).map(|item| item.id.to_string()));
+                            let attached = item_id
+                                .ok_or(format!("save item not found: {}", id))
+                                .and_then(|item_id| {
+                                    screenshot::attach(&item_id, &backup_id, &source)
+                                });
+                            match attached {
+                                Ok(path) => update.screenshot = Some(path),
+                                Err(e) => {
+                                    respond(&reply, Err(e));
+                                    continue;
                                 }
                             }
                         }
-                        if let Some(active) = save {
-                            if let Ok(mut data) = data.try_lock() {
-                                data.set_monitor(id.to_string(), active)
+                        let result = match data.lock() {
+                            Ok(mut data) => data.update_backup(&id, &backup_id, update),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let result = result.and_then(|item| {
+                            sync_active(&active, &item);
+                            item.backups
+                                .iter()
+                                .find(|backup| backup.id == backup_id)
+                                .map(|backup| json!(backup))
+                                .ok_or(format!("backup not found: {}", backup_id))
+                        });
+                        respond(&reply, result);
+                    }
+                    Command::Backup { id, remark, reply } => {
+                        // 复制文件可能很久，不能一直持有锁
+                        let save_item = data.lock().ok().and_then(|data| data.find(&id).cloned());
+                        let Some(save_item) = save_item else {
+                            respond(&reply, Err(format!("save item not found: {}", id)));
+                            continue;
+                        };
+                        let (msgs, backup, run) = Backup::run(save_item, remark, Trigger::Manual);
+                        let outcome = run.outcome.clone();
+                        let mut item = None;
+                        if let Ok(mut data) = data.lock() {
+                            if let Some(save_item) = data.find_mut(&id) {
+                                if let Some(backup) = &backup {
+                                    save_item.backups.insert(0, backup.clone());
+                                }
+                                notify::notify(save_item, &run);
+                                save_item.push_run(run.clone());
+                                item = Some(save_item.clone());
+                                data.save();
                             }
                         }
+                        if let Some(item) = item {
+                            sync_active(&active, &item);
+                        }
+                        let result = match outcome {
+                            Outcome::Success => {
+                                let warnings: Vec<String> = msgs
+                                    .into_iter()
+                                    .filter(|(level, _)| *level == ToastLevel::Warning)
+                                    .map(|(_, msg)| msg)
+                                    .collect();
+                                Ok(json!({ "run": run, "backup": backup, "warnings": warnings }))
+                            }
+                            _ => Err(msgs
+                                .into_iter()
+                                .map(|(_, msg)| msg)
+                                .collect::<Vec<String>>()
+                                .join("\n")),
+                        };
+                        respond(&reply, result);
                     }
-                    Command::TotallyDelete { save_item } => {
-                        // 根据id删掉对应文件夹
-                        println!("{save_item:?}");
-                        let current_dir = util::current_dir();
-                        let backup_folder =
-                            format!("{}/data/backup/{}", current_dir, save_item.id.to_string());
-                        util::delete_dirs(&backup_folder);
-
-                        // 提出掉删除的数据，然后存档
-                        if let Ok(mut data) = data.try_lock() {
-                            for (index, item) in data.monitors.to_vec().iter().enumerate() {
-                                if item.id == save_item.id {
-                                    data.monitors.remove(index);
-                                    data.save();
-                                    break;
+                    Command::Restore {
+                        id,
+                        backup_id,
+                        reply,
+                    } => {
+                        let found = data.lock().ok().and_then(|data| {
+                            let item = data.find(&id)?;
+                            let backup = item.backups.iter().find(|b| b.id == backup_id)?;
+                            Some((item.clone(), backup.clone()))
+                        });
+                        let Some((item, backup)) = found else {
+                            respond(&reply, Err(format!("backup not found: {}", backup_id)));
+                            continue;
+                        };
+                        let mut msgs = vec![];
+                        let result = if backup::restore(&item, &backup, &mut msgs) {
+                            let warnings: Vec<String> = msgs
+                                .into_iter()
+                                .filter(|(level, _)| *level == ToastLevel::Warning)
+                                .map(|(_, msg)| msg)
+                                .collect();
+                            Ok(json!({ "id": backup.id, "warnings": warnings }))
+                        } else {
+                            Err(msgs
+                                .into_iter()
+                                .map(|(_, msg)| msg)
+                                .collect::<Vec<String>>()
+                                .join("\n"))
+                        };
+                        respond(&reply, result);
+                    }
+                    Command::AddProfile { id, name, reply } => {
+                        let result = match data.lock() {
+                            Ok(mut data) => profile::add(&mut data, &id, &name),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let result = result.map(|item| {
+                            sync_active(&active, &item);
+                            json!({ "profile": item.profile, "profiles": item.profiles })
+                        });
+                        respond(&reply, result);
+                    }
+                    Command::ListProfiles { id, reply } => {
+                        let result = match data.lock() {
+                            Ok(data) => match data.find(&id) {
+                                Some(item) => Ok(profile::list(item)),
+                                None => Err(format!("save item not found: {}", id)),
+                            },
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::SwitchProfile {
+                        id,
+                        profile: target,
+                        reply,
+                    } => {
+                        // 复制文件可能很久，不能一直持有锁
+                        let save_item = data.lock().ok().and_then(|data| data.find(&id).cloned());
+                        let Some(save_item) = save_item else {
+                            respond(&reply, Err(format!("save item not found: {}", id)));
+                            continue;
+                        };
+                        let target = profile::parse(&target);
+                        let switched = profile::switch(&save_item, &target);
+                        let mut item = None;
+                        if let Ok(mut data) = data.lock() {
+                            if let Some(save_item) = data.find_mut(&id) {
+                                if let Some(backup) = &switched.backup {
+                                    save_item.backups.insert(0, backup.clone());
+                                }
+                                if let Some(run) = &switched.run {
+                                    notify::notify(save_item, run);
+                                    save_item.push_run(run.clone());
+                                }
+                                if switched.error.is_none() {
+                                    save_item.profile = target.to_string();
+                                }
+                                item = Some(save_item.clone());
+                                data.save();
+                            }
+                        }
+                        if let Some(item) = item {
+                            sync_active(&active, &item);
+                        }
+                        let result = match switched.error {
+                            None => Ok(json!({
+                                "profile": target,
+                                "backup": switched.backup.map(|backup| backup.id),
+                                "restored": switched.restored,
+                            })),
+                            Some(e) => {
+                                log::log_err(format!("switch profile error: {}", e));
+                                Err(e)
+                            }
+                        };
+                        respond(&reply, result);
+                    }
+                    Command::GitHistory { id, reply } => {
+                        let found = data.lock().ok().and_then(|data| data.find(&id).cloned());
+                        let result = match found {
+                            Some(item) => gitrepo::history(&item.id),
+                            None => Err(format!("save item not found: {}", id)),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::GitDiff {
+                        id,
+                        backup_id,
+                        reply,
+                    } => {
+                        let result = find_git_backup(&data, &id, &backup_id)
+                            .and_then(|backup| gitrepo::diff(&backup))
+                            .map(Value::String);
+                        let _ = reply.send(result);
+                    }
+                    Command::GitBlame {
+                        id,
+                        backup_id,
+                        file,
+                        reply,
+                    } => {
+                        let result =
+                            find_git_backup(&data, &id, &backup_id).and_then(|backup| match file {
+                                Some(file) => gitrepo::blame(&backup, &file).map(Value::String),
+                                None => gitrepo::files(&backup).map(|files| json!(files)),
+                            });
+                        let _ = reply.send(result);
+                    }
+                    Command::FileHistory { id, path, reply } => {
+                        // 计算哈希可能很久，不能一直持有锁
+                        let save_item = data.lock().ok().and_then(|data| data.find(&id).cloned());
+                        let result = match save_item {
+                            Some(item) => filehistory::history(&item, &path).map(|h| json!(h)),
+                            None => Err(format!("save item not found: {}", id)),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::RestoreFile {
+                        id,
+                        backup_id,
+                        path,
+                        reply,
+                    } => {
+                        let found = data.lock().ok().and_then(|data| {
+                            let item = data.find(&id)?;
+                            let backup = item.backups.iter().find(|b| b.id == backup_id)?;
+                            Some((item.clone(), backup.clone()))
+                        });
+                        let Some((item, backup)) = found else {
+                            respond(&reply, Err(format!("backup not found: {}", backup_id)));
+                            continue;
+                        };
+                        let result = filehistory::restore(&item, &backup, &path)
+                            .map(|_| json!({ "id": backup.id, "path": path }));
+                        respond(&reply, result);
+                    }
+                    Command::JobStatus { reply } => {
+                        let mut jobs = vec![];
+                        if let Ok(job_handle) = job_handle.lock() {
+                            if let Ok(start_infos) = job_handle.start_infos.lock() {
+                                for info in start_infos.iter() {
+                                    jobs.push(json!({
+                                        "id": info.save_item.id,
+                                        "name": info.save_item.name,
+                                        "cron": info.save_item.auto.cron,
+                                        "success": info.success,
+                                        "error": info.error,
+                                        "next": job::parse_time(&info.save_item.auto.cron).first(),
+                                    }));
                                 }
                             }
                         }
+                        let _ = reply.send(Ok(json!(jobs)));
+                    }
+                    Command::Export {
+                        id,
+                        backup_ids,
+                        path,
+                        reply,
+                    } => {
+                        let item = match data.lock() {
+                            Ok(data) => data
+                                .find(&id)
+                                .cloned()
+                                .ok_or(format!("save item not found: {}", id)),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        // 导出可能很久，不占用数据锁
+                        let result =
+                            item.and_then(|item| bundle::export(&item, &backup_ids, &path));
+                        if let Err(e) = &result {
+                            log::log_err(format!("export error: {}", e));
+                        }
+                        respond(&reply, result);
+                    }
+                    Command::Import { path, mode, reply } => {
+                        // 解压备份可能很久，只在检查冲突和写入数据时持有数据锁
+                        let plan = match data.lock() {
+                            Ok(data) => bundle::prepare(&path, &data, mode),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let result = plan.and_then(|mut plan| {
+                            bundle::unpack(&path, &mut plan)?;
+                            match data.lock() {
+                                Ok(mut data) => bundle::apply(plan, &mut data),
+                                Err(e) => Err(e.to_string()),
+                            }
+                        });
+                        let result = result.map(|item| {
+                            sync_active(&active, &item);
+                            json!({ "id": item.id, "name": item.name, "backups": item.backups.len() })
+                        });
+                        if let Err(e) = &result {
+                            log::log_err(format!("import error: {}", e));
+                        }
+                        respond(&reply, result);
+                    }
+                    Command::ScanGames { reply } => {
+                        let result = match data.lock() {
+                            Ok(data) => Ok(json!(gamedb::scan(&data))),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::AddGames { names, reply } => {
+                        let result = match data.lock() {
+                            Ok(mut data) => Ok(gamedb::add(&mut data, &names)
+                                .iter()
+                                .map(|item| item.summary())
+                                .collect()),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        respond(&reply, result);
+                    }
+                    Command::SteamApps { reply } => {
+                        let _ = reply.send(Ok(json!(steam::installed_apps())));
+                    }
+                    Command::SteamPath {
+                        app_id,
+                        path,
+                        reply,
+                    } => {
+                        let _ = reply.send(steam::resolve_app_path(app_id, &path).map(
+                            |path| json!({ "path": path, "exists": util::file_exist(&path) }),
+                        ));
+                    }
+                    Command::Rescan { apply, reply } => {
+                        let result = match data.lock() {
+                            Ok(mut data) => {
+                                let report = rescan::rescan(&mut data, apply);
+                                let active_id = match active.lock() {
+                                    Ok(active) => active.id.to_string(),
+                                    Err(_) => String::new(),
+                                };
+                                if let Some(item) = data.monitors.iter().find(|m| m.id == active_id)
+                                {
+                                    sync_active(&active, item);
+                                }
+                                serde_json::to_value(report).map_err(|e| e.to_string())
+                            }
+                            Err(e) => Err(e.to_string()),
+                        };
+                        respond(&reply, result);
+                    }
+                    Command::ItemChanges { id, reply } => {
+                        let item_id = data
+                            .lock()
+                            .ok()
+                            .and_then(|data| data.find(&id).map(|item| item.id.to_string()));
+                        let result = match item_id {
+                            Some(item_id) => catalog::changes(&item_id).map(|changes| {
+                                json!(changes.iter().map(Change::summary).collect::<Vec<_>>())
+                            }),
+                            None => Err(format!("save item not found: {}", id)),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::RevertChange {
+                        id,
+                        change_id,
+                        reply,
+                    } => {
+                        let result = match data.lock() {
+                            Ok(mut data) => changelog::revert(&mut data, &id, &change_id),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let result = result.map(|item| {
+                            sync_active(&active, &item);
+                            json!({ "id": item.id, "change": change_id })
+                        });
+                        // 定时和监听设置可能改回去了，重新启动任务
+                        if result.is_ok() {
+                            start_job(&data, &active, &job_handle);
+                        }
+                        respond(&reply, result);
+                    }
+                    Command::ExportData { path, reply } => {
+                        let result = match data.lock() {
+                            Ok(data) => data.export(&path),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        respond(&reply, result);
+                    }
+                    Command::Storage { reply } => {
+                        let result = match data.lock() {
+                            Ok(mut data) => {
+                                storage::fill_missing(&mut data);
+                                serde_json::to_value(storage::report(&data))
+                                    .map_err(|e| e.to_string())
+                            }
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::Prune { id, reply } => {
+                        let result = match data.lock() {
+                            Ok(mut data) => storage::prune(&mut data, &id),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let result = result.map(|(item, removed)| {
+                            let mut size = 0;
+                            for backup in &removed {
+                                if let Err(e) = trash::trash_backup(&item, backup) {
+                                    log::log_err(format!("prune backup error: {}", e));
+                                }
+                                // git 格式的备份只删除记录，不释放空间
+                                if !backup.is_git() {
+                                    size += backup.size;
+                                }
+                            }
+                            sync_active(&active, &item);
+                            json!({ "id": item.id, "backups": removed.len(), "size": size })
+                        });
+                        respond(&reply, result);
+                    }
+                    Command::MeasureStorage => {
+                        if let Ok(mut data) = data.lock() {
+                            storage::fill_missing(&mut data);
+                        }
+                    }
+                    Command::Search { query, reply } => {
+                        // 文件名从索引中查询，不在数据锁中读取备份目录
+                        let pending = match data.lock() {
+                            Ok(data) => Ok(search::search(&data, &query)),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let result = pending
+                            .and_then(|pending| pending.files())
+                            .map(|hits| json!(hits));
+                        let _ = reply.send(result);
+                    }
+                    Command::Logs { mut filter, reply } => {
+                        // 可以用存档的 id 筛选，日志中记录的是名称
+                        if let Some(item) = data.lock().ok().and_then(|data| {
+                            data.find(&filter.item).map(|item| item.name.to_string())
+                        }) {
+                            filter.item = item;
+                        }
+                        let _ = reply.send(Ok(json!(log::read(&filter))));
+                    }
+                    Command::Health { id, reply } => {
+                        let setting = Setting::default().health;
+                        let result = match data.lock() {
+                            Ok(data) => match id {
+                                Some(id) => data
+                                    .find(&id)
+                                    .map(|item| json!([health::check(item, &setting)]))
+                                    .ok_or(format!("save item not found: {}", id)),
+                                None => Ok(json!(health::check_all(&data, &setting))),
+                            },
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Command::TestNotify { reply } => {
+                        let _ = reply.send(notify::test().map(|deliveries| json!(deliveries)));
+                    }
+                    Command::ListTrash { reply } => {
+                        let days = Setting::default().trash.purge_days;
+                        let entries: Vec<_> = trash::list()
+                            .iter()
+                            .map(|entry| entry.summary(days))
+                            .collect();
+                        let _ = reply.send(Ok(json!(entries)));
+                    }
+                    Command::RestoreTrash { id, reply } => {
+                        let result = match data.lock() {
+                            Ok(mut data) => trash::restore(&id, &mut data),
+                            Err(e) => Err(e.to_string()),
+                        };
+                        let result = result.map(|item| {
+                            sync_active(&active, &item);
+                            json!({ "id": id, "item_id": item.id })
+                        });
+                        if let Err(e) = &result {
+                            log::log_err(format!("restore trash error: {}", e));
+                        }
+                        respond(&reply, result);
+                    }
+                    Command::PurgeTrash { id, reply } => {
+                        let result = trash::purge(&id).map(|entry| json!({ "id": entry.id }));
+                        respond(&reply, result);
+                    }
+                    Command::PurgeExpiredTrash => {
+                        let days = Setting::default().trash.purge_days;
+                        let count = trash::purge_expired(days);
+                        if count > 0 {
+                            log::log_info(format!("purge {} expired trash", count));
+                        }
+                    }
+                    Command::Show { reply } => {
+                        window.show();
+                        respond(&reply, Ok(json!("show main window")));
+                    }
+                    Command::Quit { reply } => {
+                        respond(&reply, Ok(json!("quit")));
+                        window.quit();
                     }
                 }
             }
         });
     }
 }
+
+/// 查找 git 格式的备份
+fn find_git_backup(data: &Arc<Mutex<Data>>, id: &str, backup_id: &str) -> Result<Backup, String> {
+    let data = data.lock().map_err(|e| e.to_string())?;
+    let item = data
+        .find(id)
+        .ok_or(format!("save item not found: {}", id))?;
+    let backup = item
+        .backups
+        .iter()
+        .find(|backup| backup.id == backup_id)
+        .ok_or(format!("backup not found: {}", backup_id))?;
+    if !backup.is_git() {
+        return Err(format!("not a git backup: {}", backup_id));
+    }
+    Ok(backup.clone())
+}
+
+/// 按最新的存档列表重新启动自动备份任务
+fn start_job(
+    data: &Arc<Mutex<Data>>,
+    active: &Arc<Mutex<SaveItem>>,
+    job_handle: &Arc<Mutex<JobHandle>>,
+) {
+    if let Ok(_data) = data.try_lock() {
+        if let Ok(mut job_handle) = job_handle.try_lock() {
+            let job_rx = job_handle.start_job(_data.list());
+            job::receive_data_signal(data.clone(), active.clone(), job_rx);
+            if let Ok(start_infos) = job_handle.start_infos.try_lock() {
+                for item in start_infos.to_vec() {
+                    if !item.success {
+                        let error = item.error;
+                        log::log_err(format!("start job error = {:?}", error));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 数据有变化时，同步到当前正在查看的存档
+fn sync_active(active: &Arc<Mutex<SaveItem>>, item: &SaveItem) {
+    if let Ok(mut active) = active.lock() {
+        if active.id == item.id {
+            *active = item.clone();
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::Backup,
+    catalog,
+    filehistory::{hash_file, modified},
+    gitrepo, log, util,
+};
+
+/// 备份中的一个文件，备份完成时记录到索引，搜索文件时不需要读取备份目录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: String, // 相对备份目录的路径，git 格式是仓库中的路径
+    pub size: u64,
+    pub mtime: Option<i64>, // 文件的修改时间，git 格式的备份没有
+    pub hash: String,       // 和 git 相同的对象 id
+}
+
+/// 读取备份中的所有文件
+pub fn scan(backup: &Backup) -> Result<Vec<FileEntry>, String> {
+    if backup.is_git() {
+        return Ok(gitrepo::tree(backup)?
+            .into_iter()
+            .map(|(path, hash, size)| FileEntry {
+                path,
+                size,
+                mtime: None,
+                hash,
+            })
+            .collect());
+    }
+    let mut paths = vec![];
+    util::get_files(&backup.backup_folder, &mut paths)
+        .map_err(|e| format!("read {} error: {}", backup.backup_folder, e))?;
+    let mut files = vec![];
+    for path in paths {
+        let file = Path::new(&path);
+        if !file.is_file() {
+            continue;
+        }
+        let Ok(relative) = file.strip_prefix(&backup.backup_folder) else {
+            continue;
+        };
+        files.push(FileEntry {
+            path: relative.display().to_string().replace('\\', "/"),
+            size: file.metadata().map(|meta| meta.len()).unwrap_or_default(),
+            mtime: modified(file),
+            hash: hash_file(&path)?,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// 扫描并记录备份的文件清单，失败时只记录日志，搜索时会重新扫描
+pub fn record(backup: &Backup) {
+    let result = scan(backup).and_then(|files| catalog::record_manifest(&backup.id, &files));
+    if let Err(e) = result {
+        log::log_err(format!(
+            "record manifest error, backup={}, e={}",
+            backup.id, e
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_folder_backup() {
+        let folder = format!("{}/manifest_{}", util::current_dir(), uuid::Uuid::new_v4());
+        util::check_dir_and_create(&format!("{}/save/empty", folder));
+        std::fs::write(format!("{}/save/slot.sav", folder), "hello\n").unwrap();
+        let backup = Backup::new(vec![], folder, String::new());
+        let files = scan(&backup).unwrap();
+        // 只列出文件，不包括文件夹
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "save/slot.sav");
+        assert_eq!(files[0].size, 6);
+        assert!(files[0].mtime.is_some());
+        // 和 git hash-object 相同
+        assert_eq!(files[0].hash, "ce013625030ba8dba906f756967f9e9ca394464a");
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    sync::Mutex,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::{Outcome, Run, Trigger},
+    data::SaveItem,
+    log::{Entry, Level},
+    setting::NotifySetting,
+};
+
+const APP_NAME: &str = "AutoBackup";
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+static SETTING: Mutex<Option<NotifySetting>> = Mutex::new(None);
+/// 还在发送的通知，只执行一次的命令行退出前要等待
+static PENDING: Mutex<Vec<JoinHandle<()>>> = Mutex::new(vec![]);
+
+/// 每个存档的通知规则：备份成功、失败、跳过时是否通知
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NotifyRule {
+    pub success: bool,
+    pub failure: bool,
+    pub skipped: bool,
+}
+impl Default for NotifyRule {
+    fn default() -> Self {
+        Self {
+            success: false,
+            failure: true,
+            skipped: false,
+        }
+    }
+}
+impl NotifyRule {
+    pub fn matches(&self, outcome: &Outcome) -> bool {
+        match outcome {
+            Outcome::Success => self.success,
+            Outcome::Failed => self.failure,
+            Outcome::Skipped => self.skipped,
+        }
+    }
+}
+
+/// 一条通知，也是 webhook 发送的 json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notice {
+    pub event: String, // backup 或 test
+    pub item: String,
+    pub item_id: String,
+    pub outcome: Outcome,
+    pub trigger: Trigger,
+    pub reason: String,
+    pub backup_id: String,
+    pub time: i64,
+}
+impl Notice {
+    fn new(save_item: &SaveItem, run: &Run) -> Self {
+        Self {
+            event: "backup".to_string(),
+            item: save_item.name.to_string(),
+            item_id: save_item.id.to_string(),
+            outcome: run.outcome.clone(),
+            trigger: run.trigger.clone(),
+            reason: run.reason.to_string(),
+            backup_id: run.backup_id.to_string(),
+            time: run.time,
+        }
+    }
+
+    fn test() -> Self {
+        Self {
+            event: "test".to_string(),
+            item: APP_NAME.to_string(),
+            item_id: String::new(),
+            outcome: Outcome::Success,
+            trigger: Trigger::Manual,
+            reason: "test notification".to_string(),
+            backup_id: String::new(),
+            time: chrono::Local::now().timestamp_millis(),
+        }
+    }
+
+    fn summary(&self) -> String {
+        format!("{}: {:?} backup {}", self.item, self.trigger, self.outcome)
+    }
+
+    fn body(&self) -> String {
+        if self.reason.is_empty() {
+            self.backup_id.to_string()
+        } else {
+            self.reason.to_string()
+        }
+    }
+}
+
+/// 一个渠道的发送结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    pub channel: String,
+    pub error: Option<String>,
+}
+
+fn setting() -> NotifySetting {
+    SETTING
+        .lock()
+        .ok()
+        .and_then(|setting| setting.clone())
+        .unwrap_or_default()
+}
+
+/// 启动和修改设置后调用
+pub fn configure(setting: &NotifySetting) {
+    if let Ok(mut current) = SETTING.lock() {
+        *current = Some(setting.clone());
+    }
+}
+
+/// 按存档的规则发送备份结果，在后台线程中发送，失败时只记录日志
+pub fn notify(save_item: &SaveItem, run: &Run) {
+    if !save_item.notify.matches(&run.outcome) {
+        return;
+    }
+    let notice = Notice::new(save_item, run);
+    let setting = setting();
+    let handle = thread::spawn(move || {
+        for delivery in send(&setting, &notice) {
+            if let Some(e) = delivery.error {
+                Entry::new(
+                    Level::Warn,
+                    format!("{} notification error: {}", delivery.channel, e),
+                )
+                .item(&notice.item)
+                .write();
+            }
+        }
+    });
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.retain(|handle| !handle.is_finished());
+        pending.push(handle);
+    }
+}
+
+/// 等待所有通知发送完
+pub fn wait() {
+    let pending = PENDING
+        .lock()
+        .map(|mut pending| std::mem::take(&mut *pending))
+        .unwrap_or_default();
+    for handle in pending {
+        let _ = handle.join();
+    }
+}
+
+/// 通过所有启用的渠道发送一条测试通知
+pub fn test() -> Result<Vec<Delivery>, String> {
+    let deliveries = send(&setting(), &Notice::test());
+    if deliveries.is_empty() {
+        return Err("no notification channel is enabled".to_string());
+    }
+    Ok(deliveries)
+}
+
+fn send(setting: &NotifySetting, notice: &Notice) -> Vec<Delivery> {
+    let mut deliveries = vec![];
+    if setting.desktop {
+        deliveries.push(Delivery {
+            channel: "desktop".to_string(),
+            error: desktop(notice).err(),
+        });
+    }
+    let url = setting.webhook.trim();
+    if !url.is_empty() {
+        deliveries.push(Delivery {
+            channel: "webhook".to_string(),
+            error: webhook(url, notice).err(),
+        });
+    }
+    deliveries
+}
+
+/// 通过 D-Bus 发送 freedesktop 桌面通知，失败的备份使用紧急级别
+#[cfg(target_os = "linux")]
+fn desktop(notice: &Notice) -> Result<(), String> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    let urgency: u8 = match notice.outcome {
+        Outcome::Failed => 2,
+        _ => 1,
+    };
+    let hints = HashMap::from([("urgency", Value::from(urgency))]);
+    let connection = zbus::blocking::Connection::session().map_err(|e| e.to_string())?;
+    connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                APP_NAME,
+                0u32,
+                "",
+                notice.summary(),
+                notice.body(),
+                Vec::<&str>::new(),
+                hints,
+                5000i32,
+            ),
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn desktop(_notice: &Notice) -> Result<(), String> {
+    Err("desktop notifications are only supported on Linux".to_string())
+}
+
+/// 把通知以 json 格式 POST 到 webhook
+fn webhook(url: &str, notice: &Notice) -> Result<(), String> {
+    let body = serde_json::to_string(notice).map_err(|e| e.to_string())?;
+    ureq::post(url)
+        .set("Content-Type", "application/json")
+        .timeout(WEBHOOK_TIMEOUT)
+        .send_string(&body)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_posts_notice() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        configure(&NotifySetting {
+            desktop: false,
+            webhook: format!("http://127.0.0.1:{}/hook", port),
+        });
+        let mut save_item = SaveItem {
+            id: "n1".to_string(),
+            name: "notify".to_string(),
+            notify: NotifyRule {
+                success: true,
+                failure: true,
+                skipped: true,
+            },
+            ..Default::default()
+        };
+        let runs = [
+            Run::new(
+                Trigger::Manual,
+                Outcome::Success,
+                String::new(),
+                "b1".to_string(),
+            ),
+            Run::new(
+                Trigger::Auto,
+                Outcome::Failed,
+                "disk full".to_string(),
+                String::new(),
+            ),
+            Run::new(
+                Trigger::Auto,
+                Outcome::Skipped,
+                "game is running".to_string(),
+                String::new(),
+            ),
+        ];
+        for run in &runs {
+            notify(&save_item, run);
+        }
+
+        let mut notices = vec![];
+        for _ in &runs {
+            let mut request = server
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .expect("webhook request");
+            assert_eq!(request.url(), "/hook");
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            notices.push(serde_json::from_str::<Notice>(&body).unwrap());
+            request.respond(tiny_http::Response::empty(200)).unwrap();
+        }
+        wait();
+        // 每条通知在单独的线程中发送，到达的顺序不固定
+        notices.sort_by_key(|notice| notice.outcome.to_string());
+        let mut expected: Vec<&Run> = runs.iter().collect();
+        expected.sort_by_key(|run| run.outcome.to_string());
+        for (notice, run) in notices.iter().zip(expected) {
+            assert_eq!(notice.event, "backup");
+            assert_eq!(notice.item, "notify");
+            assert_eq!(notice.item_id, "n1");
+            assert_eq!(notice.outcome, run.outcome);
+            assert_eq!(notice.trigger, run.trigger);
+            assert_eq!(notice.reason, run.reason);
+            assert_eq!(notice.backup_id, run.backup_id);
+            assert_eq!(notice.time, run.time);
+        }
+
+        // 规则不匹配时不发送
+        save_item.notify = NotifyRule::default();
+        notify(&save_item, &runs[0]);
+        wait();
+        assert!(server
+            .recv_timeout(Duration::from_millis(200))
+            .unwrap()
+            .is_none());
+    }
+}
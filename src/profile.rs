@@ -0,0 +1,278 @@
+use fs_more::{directory::DirectoryCopyOptions, file::FileCopyOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::{Backup, BackupData, Outcome, Run, Trigger, NOTHING_TO_RESTORE},
+    data::{Data, FileType, SaveItem},
+    gitrepo,
+    hook::{self, HookKind},
+    log::{self, Entry, Level},
+    util,
+};
+
+const STAGING_SUFFIX: &str = ".auto_backup_switching";
+
+/// 默认配置的名称为空，界面上显示为 Default
+pub fn display(name: &str) -> &str {
+    if name.is_empty() {
+        "Default"
+    } else {
+        name
+    }
+}
+
+/// 把输入的名称转换成配置名称，Default 表示默认配置
+pub fn parse(name: &str) -> String {
+    let name = name.trim();
+    if name.eq_ignore_ascii_case(display("")) {
+        String::new()
+    } else {
+        name.to_string()
+    }
+}
+
+/// 存档的所有配置，默认配置在最前面
+pub fn names(save_item: &SaveItem) -> Vec<String> {
+    let mut names = vec![String::new()];
+    names.extend(save_item.profiles.iter().cloned());
+    names
+}
+
+/// 配置最新的备份，备份按时间倒序排列
+pub fn latest<'a>(save_item: &'a SaveItem, name: &str) -> Option<&'a Backup> {
+    save_item
+        .backups
+        .iter()
+        .find(|backup| backup.profile == name)
+}
+
+/// 配置列表，包括每个配置的备份数量和最新的备份
+pub fn list(save_item: &SaveItem) -> serde_json::Value {
+    let profiles: Vec<serde_json::Value> = names(save_item)
+        .iter()
+        .map(|name| {
+            let latest = latest(save_item, name);
+            serde_json::json!({
+                "name": display(name),
+                "current": *name == save_item.profile,
+                "backups": save_item.backups.iter().filter(|b| b.profile == *name).count(),
+                "latest": latest.map(|backup| backup.id.to_string()),
+                "latest_time": latest.map(|backup| backup.time),
+            })
+        })
+        .collect();
+    serde_json::json!(profiles)
+}
+
+/// 添加配置，返回更新后的存档
+pub fn add(data: &mut Data, id: &str, name: &str) -> Result<SaveItem, String> {
+    let name = name.trim();
+    if name.is_empty() || name.eq_ignore_ascii_case(display("")) {
+        return Err(format!("invalid profile name: {}", name));
+    }
+    let item = data
+        .find_mut(id)
+        .ok_or(format!("save item not found: {}", id))?;
+    if item.profiles.iter().any(|p| p.eq_ignore_ascii_case(name)) {
+        return Err(format!("profile already exists: {}", name));
+    }
+    item.profiles.push(name.to_string());
+    let item = item.clone();
+    data.save();
+    log::log_info(format!("add profile, name={}, profile={}", item.name, name));
+    Ok(item)
+}
+
+/// 切换配置的结果，备份和运行记录由调用方保存
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Switched {
+    pub backup: Option<Backup>,   // 切换前当前文件的备份，属于原来的配置
+    pub run: Option<Run>,         // 这次备份的运行记录
+    pub restored: Option<String>, // 恢复的目标配置的备份，目标配置没有备份时保留当前文件
+    pub error: Option<String>,    // 有错误时配置不变
+}
+
+/// 切换配置：先把当前文件备份到当前配置，再用目标配置最新的备份替换监听的文件
+///
+/// 替换失败时会还原当前文件，配置不变
+pub fn switch(save_item: &SaveItem, target: &str) -> Switched {
+    let mut switched = Switched::default();
+    if save_item.profile == target {
+        switched.error = Some(format!("already using profile: {}", display(target)));
+        return switched;
+    }
+    if !names(save_item).iter().any(|name| name == target) {
+        switched.error = Some(format!("profile not found: {}", target));
+        return switched;
+    }
+    // 和恢复一样，没有记录文件的备份不能替换当前文件，在备份当前文件前放弃切换
+    if latest(save_item, target).is_some_and(|backup| backup.datas.is_empty()) {
+        switched.error = Some(NOTHING_TO_RESTORE.to_string());
+        return switched;
+    }
+
+    let remark = format!("Switch to {}", display(target));
+    let (msgs, backup, run) = Backup::run(save_item.clone(), remark, Trigger::PreRestore);
+    let failed = run.outcome != Outcome::Success;
+    switched.backup = backup;
+    switched.run = Some(run);
+    if failed {
+        let errors: Vec<String> = msgs.into_iter().map(|(_, msg)| msg).collect();
+        switched.error = Some(format!(
+            "backup current files error, profile not switched\n{}",
+            errors.join("\n")
+        ));
+        return switched;
+    }
+
+    if let Some(backup) = latest(save_item, target) {
+        if let Err(e) = hook::run(save_item, HookKind::PreRestore, backup, "") {
+            switched.error = Some(format!("Hook Error\n{}", e));
+            return switched;
+        }
+        let result = if backup.is_git() {
+            gitrepo::checkout(backup).and_then(|checkout| replace(&checkout.datas))
+        } else {
+            replace(&backup.datas)
+        };
+        let outcome = if result.is_ok() {
+            Outcome::Success
+        } else {
+            Outcome::Failed
+        };
+        if let Err(e) = hook::run(
+            save_item,
+            HookKind::PostRestore,
+            backup,
+            &outcome.to_string(),
+        ) {
+            log::log_err(format!("post restore hook error: {}", e));
+        }
+        if let Err(e) = result {
+            switched.error = Some(e);
+            return switched;
+        }
+        switched.restored = Some(backup.id.to_string());
+    }
+    Entry::new(
+        Level::Info,
+        format!(
+            "switch profile, from={}, to={}",
+            display(&save_item.profile),
+            display(target)
+        ),
+    )
+    .item(&save_item.name)
+    .backup(switched.restored.as_deref().unwrap_or_default())
+    .write();
+    switched
+}
+
+fn copy(data: &BackupData) -> Result<(), String> {
+    let result = match data.file_type {
+        FileType::Folder => fs_more::directory::copy_directory(
+            &data.target,
+            &data.source,
+            DirectoryCopyOptions {
+                target_directory_rule: fs_more::directory::TargetDirectoryRule::AllowEmpty,
+                maximum_copy_depth: Some(1000),
+            },
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string()),
+        FileType::File => fs_more::file::copy_file(
+            &data.target,
+            &data.source,
+            FileCopyOptions {
+                overwrite_existing: true,
+                skip_existing: false,
+            },
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string()),
+    };
+    result.map_err(|e| format!("restore {} error: {}", data.source, e))
+}
+
+fn remove(path: &str) {
+    let path = std::path::Path::new(path);
+    let _ = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+}
+
+/// 用备份替换监听的文件，和 overwrite 不同，不会留下备份中没有的文件
+///
+/// 当前文件先移到旁边，全部复制成功后再删除，失败时移回原处
+fn replace(datas: &[BackupData]) -> Result<(), String> {
+    let mut staged: Vec<(&BackupData, Option<String>)> = vec![];
+    let mut error = None;
+    for data in datas {
+        let staging = format!("{}{}", data.source, STAGING_SUFFIX);
+        let moved = if util::file_exist(&data.source) {
+            remove(&staging);
+            if let Err(e) = std::fs::rename(&data.source, &staging) {
+                error = Some(format!("move {} error: {}", data.source, e));
+                break;
+            }
+            Some(staging)
+        } else {
+            None
+        };
+        staged.push((data, moved));
+        if let Err(e) = copy(data) {
+            error = Some(e);
+            break;
+        }
+    }
+
+    match error {
+        None => {
+            for (_, moved) in staged {
+                if let Some(staging) = moved {
+                    remove(&staging);
+                }
+            }
+            Ok(())
+        }
+        Some(e) => {
+            // 还原已经替换的文件
+            for (data, moved) in staged.into_iter().rev() {
+                remove(&data.source);
+                if let Some(staging) = moved {
+                    if let Err(e) = std::fs::rename(&staging, &data.source) {
+                        log::log_err(format!("rollback {} error: {}", data.source, e));
+                    }
+                }
+            }
+            log::log_err(format!("switch profile error: {}", e));
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switch_to_empty_backup_is_aborted() {
+        // 重新扫描得到的备份没有记录文件
+        let mut backup = Backup::new(vec![], String::new(), String::new());
+        backup.profile = "second".to_string();
+        let save_item = SaveItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: "profile".to_string(),
+            profiles: vec!["second".to_string()],
+            backups: vec![backup],
+            ..Default::default()
+        };
+        let switched = switch(&save_item, "second");
+        assert_eq!(switched.error.as_deref(), Some(NOTHING_TO_RESTORE));
+        // 放弃切换时不会备份当前文件
+        assert!(switched.backup.is_none() && switched.run.is_none());
+        assert!(switched.restored.is_none());
+    }
+}
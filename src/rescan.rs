@@ -0,0 +1,268 @@
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::{Backup, BackupData, BACKUP_FOLDER_FMT},
+    data::{Data, Monitor, SaveItem, Status},
+    gitrepo, log, screenshot, storage, util,
+};
+
+/// 扫描备份目录的结果
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RescanReport {
+    pub applied: bool,
+    pub adopted: Vec<Found>,         // 没有记录的备份文件夹，重新创建记录
+    pub relinked: Vec<Found>,        // 记录的路径不对，但同名文件夹存在
+    pub rebuilt: Vec<Found>,         // 记录缺少备份内容，根据监听路径重新生成
+    pub recovered_items: Vec<Found>, // 没有对应存档的 data/backup/<id>，恢复成归档的存档
+    pub orphan_folders: Vec<String>, // 无法识别的文件夹
+    pub dangling: Vec<Found>,        // 备份记录对应的文件夹不存在
+}
+impl RescanReport {
+    /// 是否有可以修复的内容
+    pub fn can_apply(&self) -> bool {
+        !self.adopted.is_empty()
+            || !self.relinked.is_empty()
+            || !self.rebuilt.is_empty()
+            || !self.recovered_items.is_empty()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.adopted.is_empty()
+            && self.relinked.is_empty()
+            && self.rebuilt.is_empty()
+            && self.recovered_items.is_empty()
+            && self.orphan_folders.is_empty()
+            && self.dangling.is_empty()
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Found {
+    pub item_id: String,
+    pub item_name: String,
+    pub backup_id: String,
+    pub folder: String,
+}
+
+fn backup_root() -> String {
+    format!("{}/data/backup", util::current_dir())
+}
+
+fn sub_dirs(path: &str) -> Vec<String> {
+    let mut dirs = vec![];
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                dirs.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+    }
+    dirs.sort();
+    dirs
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// 根据文件夹名称解析备份时间，导入时可能带有 _1 之类的后缀
+fn parse_folder_time(name: &str) -> Option<i64> {
+    let len = "20240101_000000".len();
+    let time = NaiveDateTime::parse_from_str(name.get(..len)?, BACKUP_FOLDER_FMT).ok()?;
+    let suffix = &name[len..];
+    let valid = match suffix.strip_prefix('_') {
+        Some(index) => index.chars().all(|c| c.is_ascii_digit()),
+        None => suffix.is_empty(),
+    };
+    if !valid {
+        return None;
+    }
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|time| time.timestamp_millis())
+}
+
+/// 根据存档的监听路径找出备份文件夹中对应的内容
+fn rebuild_datas(monitors: &[Monitor], folder: &str) -> Vec<BackupData> {
+    let mut datas = vec![];
+    for monitor in monitors {
+        let name = file_name(&monitor.path);
+        if name.is_empty() {
+            continue;
+        }
+        let target = format!("{}/{}", folder, name);
+        if util::file_exist(&target) {
+            datas.push(BackupData {
+                source: monitor.path.to_string(),
+                target,
+                file_type: monitor.backup_type.clone(),
+            });
+        }
+    }
+    datas
+}
+
+fn found(item_id: &str, item_name: &str, backup_id: &str, folder: &str) -> Found {
+    Found {
+        item_id: item_id.to_string(),
+        item_name: item_name.to_string(),
+        backup_id: backup_id.to_string(),
+        folder: folder.to_string(),
+    }
+}
+
+/// 为没有记录的备份文件夹创建记录
+fn adopt(monitors: &[Monitor], folder: &str, time: i64) -> Backup {
+    let mut backup = Backup::new(
+        rebuild_datas(monitors, folder),
+        folder.to_string(),
+        "Rescan".to_string(),
+    );
+    backup.time = time;
+    (backup.size, backup.files) = storage::measure(folder);
+    backup
+}
+
+/// 备份文件夹被移动后，改写记录中的路径
+fn relink(backup: &mut Backup, folder: &str) {
+    for data in &mut backup.datas {
+        if let Some(relative) = data.target.strip_prefix(&backup.backup_folder) {
+            data.target = format!("{}{}", folder, relative);
+        }
+    }
+    backup.backup_folder = folder.to_string();
+}
+
+/// 备份按时间倒序排列
+fn insert(item: &mut SaveItem, backup: Backup) {
+    let index = item
+        .backups
+        .iter()
+        .position(|b| b.time < backup.time)
+        .unwrap_or(item.backups.len());
+    item.backups.insert(index, backup);
+}
+
+/// 扫描 data/backup/<id>/<time>，和存档记录对比。apply 为 false 时只报告不修改
+pub fn rescan(data: &mut Data, apply: bool) -> RescanReport {
+    let mut report = RescanReport {
+        applied: apply,
+        ..Default::default()
+    };
+    let root = backup_root();
+    let item_dirs = sub_dirs(&root);
+
+    for item in data.monitors.iter_mut() {
+        let item_dir = format!("{}/{}", root, item.id);
+        let folders = sub_dirs(&item_dir);
+        let (id, name, monitors) = (
+            item.id.to_string(),
+            item.name.to_string(),
+            item.monitors.clone(),
+        );
+
+        // 已有的记录
+        for backup in item.backups.iter_mut() {
+            if util::file_exist(&backup.backup_folder) {
+                if backup.datas.is_empty() {
+                    let datas = rebuild_datas(&monitors, &backup.backup_folder);
+                    if !datas.is_empty() {
+                        report
+                            .rebuilt
+                            .push(found(&id, &name, &backup.id, &backup.backup_folder));
+                        if apply {
+                            backup.datas = datas;
+                        }
+                    }
+                }
+                continue;
+            }
+            let folder_name = file_name(&backup.backup_folder);
+            if !folder_name.is_empty() && folders.contains(&folder_name) {
+                // 程序目录移动过，改写成现在的路径
+                let folder = format!("{}/{}", item_dir, folder_name);
+                report.relinked.push(found(&id, &name, &backup.id, &folder));
+                if apply {
+                    relink(backup, &folder);
+                }
+            } else {
+                report
+                    .dangling
+                    .push(found(&id, &name, &backup.id, &backup.backup_folder));
+            }
+        }
+
+        // 没有记录的文件夹
+        for folder_name in folders {
+            // git 仓库和截图目录不是备份文件夹
+            if folder_name == gitrepo::REPO_FOLDER
+                || folder_name == screenshot::SCREENSHOT_FOLDER
+                || item
+                    .backups
+                    .iter()
+                    .any(|b| file_name(&b.backup_folder) == folder_name)
+            {
+                continue;
+            }
+            let folder = format!("{}/{}", item_dir, folder_name);
+            let Some(time) = parse_folder_time(&folder_name) else {
+                report.orphan_folders.push(folder);
+                continue;
+            };
+            let backup = adopt(&monitors, &folder, time);
+            report.adopted.push(found(&id, &name, &backup.id, &folder));
+            if apply {
+                insert(item, backup);
+            }
+        }
+    }
+
+    // 没有对应存档的文件夹
+    for id in item_dirs {
+        if data.monitors.iter().any(|item| item.id == id) {
+            continue;
+        }
+        let item_dir = format!("{}/{}", root, id);
+        let mut item = SaveItem {
+            id: id.to_string(),
+            name: format!("Recovered {}", id),
+            status: Status::Archive,
+            ..Default::default()
+        };
+        for name in sub_dirs(&item_dir) {
+            let folder = format!("{}/{}", item_dir, name);
+            match parse_folder_time(&name) {
+                Some(time) => insert(&mut item, adopt(&[], &folder, time)),
+                None => report.orphan_folders.push(folder),
+            }
+        }
+        report
+            .recovered_items
+            .push(found(&item.id, &item.name, "", &item_dir));
+        if apply {
+            data.monitors.push(item);
+        }
+    }
+
+    if apply && !report.is_clean() {
+        data.save();
+    }
+    log::log_info(format!(
+        "rescan storage, apply={}, adopted={}, relinked={}, rebuilt={}, recovered={}, orphan={}, dangling={}",
+        apply,
+        report.adopted.len(),
+        report.relinked.len(),
+        report.rebuilt.len(),
+        report.recovered_items.len(),
+        report.orphan_folders.len(),
+        report.dangling.len()
+    ));
+    report
+}
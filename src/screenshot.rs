@@ -0,0 +1,94 @@
+use std::{path::Path, time::UNIX_EPOCH};
+
+use crate::{data::SaveItem, log, steam, util};
+
+/// 截图保存在 ./data/backup/<id>/screenshots
+pub const SCREENSHOT_FOLDER: &str = "screenshots";
+
+/// 没有上一次备份时，只使用这段时间内的截图
+const RECENT_MILLIS: i64 = 30 * 60 * 1000;
+
+pub const EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "webp", "bmp"];
+
+pub fn is_image(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// 存档的截图目录，没有设置时使用 Steam 的截图目录
+pub fn source_folders(save_item: &SaveItem) -> Vec<String> {
+    if !save_item.screenshot_folder.is_empty() {
+        return vec![save_item.screenshot_folder.to_string()];
+    }
+    steam::parse_launch_url(&save_item.startup_path)
+        .map(steam::screenshot_folders)
+        .unwrap_or_default()
+}
+
+fn modified(path: &Path) -> Option<i64> {
+    let time = path.metadata().ok()?.modified().ok()?;
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64)
+}
+
+/// 备份时附加的截图：上一次备份之后最新的截图，复制到截图目录，没有时返回空
+pub fn capture(save_item: &SaveItem, backup_id: &str) -> String {
+    let since = save_item
+        .backups
+        .first()
+        .map(|backup| backup.time)
+        .unwrap_or(chrono::Local::now().timestamp_millis() - RECENT_MILLIS);
+    let latest = source_folders(save_item)
+        .iter()
+        .flat_map(|folder| util::read_dir(folder))
+        .map(|entry| entry.path())
+        .filter(|path| is_image(&path.display().to_string()))
+        .filter_map(|path| Some((modified(&path)?, path)))
+        .filter(|(time, _)| *time > since)
+        .max_by_key(|(time, _)| *time);
+    let Some((_, path)) = latest else {
+        return String::new();
+    };
+    match attach(&save_item.id, backup_id, &path.display().to_string()) {
+        Ok(path) => path,
+        Err(e) => {
+            log::log_err(format!("attach screenshot error: {}", e));
+            String::new()
+        }
+    }
+}
+
+/// 把图片复制到存档的截图目录，返回复制后的路径
+///
+/// 文件名带有时间，替换截图后缩略图不会使用旧的缓存
+pub fn attach(id: &str, backup_id: &str, source: &str) -> Result<String, String> {
+    if !is_image(source) {
+        return Err(format!("not an image: {}", source));
+    }
+    let ext = Path::new(source)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let folder = format!(
+        "{}/data/backup/{}/{}",
+        util::current_dir(),
+        id,
+        SCREENSHOT_FOLDER
+    );
+    util::check_dir_and_create(&folder);
+    let target = format!(
+        "{}/{}_{}.{}",
+        folder,
+        backup_id,
+        chrono::Local::now().timestamp_millis(),
+        ext
+    );
+    std::fs::copy(source, &target).map_err(|e| format!("copy {} error: {}", source, e))?;
+    Ok(target)
+}
+
+/// 缩略图使用的 uri
+pub fn uri(path: &str) -> String {
+    format!("file://{}", path.replace('\\', "/"))
+}
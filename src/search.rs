@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::Backup,
+    catalog,
+    data::{Data, SaveItem},
+    manifest, util,
+};
+
+/// 匹配的位置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HitKind {
+    Item,   // 存档的名称、描述、标签等
+    Backup, // 备份的标题、备注、日期等
+    File,   // 备份中的文件名
+}
+
+/// 一条搜索结果，backup_id 为空时匹配的是存档本身
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hit {
+    pub item_id: String,
+    pub item_name: String,
+    pub backup_id: Option<String>,
+    pub kind: HitKind,
+    pub field: String, // 匹配的字段，例如 Name、Remark、File
+    pub text: String,  // 匹配的内容
+    pub time: Option<i64>,
+}
+
+/// 在数据锁中匹配的存档和备份，文件名在释放锁之后从索引中查询
+pub struct Matches {
+    query: String,
+    rows: Vec<Row>, // 按存档、备份的顺序
+}
+
+struct Row {
+    hit: Option<Hit>,             // 存档或备份匹配的字段
+    files: Option<(Hit, Backup)>, // 备份行的文件名匹配结果模板和备份，存档行为空
+}
+
+/// 在所有存档和备份中搜索，不区分大小写
+///
+/// 每个存档、备份只返回第一个匹配的字段，文件名的匹配每个文件一条
+pub fn search(data: &Data, query: &str) -> Matches {
+    let query = query.trim().to_lowercase();
+    let mut rows = vec![];
+    if query.is_empty() {
+        return Matches { query, rows };
+    }
+    for item in &data.monitors {
+        let hit = item_field(item, &query).map(|(field, text)| Hit {
+            item_id: item.id.to_string(),
+            item_name: item.name.to_string(),
+            backup_id: None,
+            kind: HitKind::Item,
+            field: field.to_string(),
+            text,
+            time: item.backups.first().map(|backup| backup.time),
+        });
+        rows.push(Row { hit, files: None });
+        for backup in &item.backups {
+            let hit = |kind, field: &str, text: String| Hit {
+                item_id: item.id.to_string(),
+                item_name: item.name.to_string(),
+                backup_id: Some(backup.id.to_string()),
+                kind,
+                field: field.to_string(),
+                text,
+                time: Some(backup.time),
+            };
+            rows.push(Row {
+                hit: backup_field(backup, &query)
+                    .map(|(field, text)| hit(HitKind::Backup, field, text)),
+                files: Some((hit(HitKind::File, "File", String::new()), backup.clone())),
+            });
+        }
+    }
+    Matches { query, rows }
+}
+
+impl Matches {
+    /// 查询文件名的匹配，没有文件清单的备份先扫描并记录，不需要持有数据锁
+    pub fn files(self) -> Result<Vec<Hit>, String> {
+        let mut hits = vec![];
+        if self.query.is_empty() {
+            return Ok(hits);
+        }
+        let recorded = catalog::manifest_ids()?;
+        for (_, backup) in self.rows.iter().filter_map(|row| row.files.as_ref()) {
+            if !recorded.contains(&backup.id) {
+                manifest::record(backup);
+            }
+        }
+        let mut files: HashMap<String, Vec<String>> = HashMap::new();
+        for (backup_id, path) in catalog::find_files(&self.query)? {
+            files.entry(backup_id).or_default().push(path);
+        }
+        for row in self.rows {
+            hits.extend(row.hit);
+            let Some((file_hit, backup)) = row.files else {
+                continue;
+            };
+            for path in files.remove(&backup.id).unwrap_or_default() {
+                hits.push(Hit {
+                    text: path,
+                    ..file_hit.clone()
+                });
+            }
+        }
+        Ok(hits)
+    }
+}
+
+fn first_match<'a>(
+    fields: impl IntoIterator<Item = (&'a str, String)>,
+    query: &str,
+) -> Option<(&'a str, String)> {
+    fields
+        .into_iter()
+        .find(|(_, text)| text.to_lowercase().contains(query))
+}
+
+fn item_field(item: &SaveItem, query: &str) -> Option<(&'static str, String)> {
+    let mut fields = vec![
+        ("Name", item.name.to_string()),
+        ("Description", item.description.to_string()),
+        ("Group", item.group.to_string()),
+        ("Platform", item.platform.to_string()),
+    ];
+    fields.extend(item.tags.iter().map(|tag| ("Tag", tag.to_string())));
+    first_match(fields, query)
+}
+
+fn backup_field(backup: &Backup, query: &str) -> Option<(&'static str, String)> {
+    let fields = [
+        ("Title", backup.title.to_string()),
+        ("Remark", backup.remark.to_string()),
+        ("Notes", backup.notes.to_string()),
+        ("Time", util::format_time(backup.time).unwrap_or_default()),
+        ("Profile", backup.profile.to_string()),
+    ];
+    first_match(fields, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_files_in_manifest() {
+        let folder = format!("{}/search_{}", util::current_dir(), uuid::Uuid::new_v4());
+        util::check_dir_and_create(&format!("{}/save", folder));
+        std::fs::write(format!("{}/save/Slot1.sav", folder), "slot").unwrap();
+        std::fs::write(format!("{}/save/config.ini", folder), "config").unwrap();
+        let mut backup = Backup::new(vec![], folder, String::new());
+        backup.remark = "before slot boss".to_string();
+        let mut data: Data = serde_json::from_value(serde_json::json!({ "monitors": [] })).unwrap();
+        data.monitors.push(SaveItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: "search".to_string(),
+            backups: vec![backup.clone()],
+            ..Default::default()
+        });
+
+        // 没有文件清单的备份在查询时补上
+        let hits = search(&data, " SLOT ").files().unwrap();
+        let hits: Vec<(HitKind, &str)> = hits
+            .iter()
+            .map(|hit| (hit.kind, hit.text.as_str()))
+            .collect();
+        assert_eq!(
+            hits,
+            [
+                (HitKind::Backup, "before slot boss"),
+                (HitKind::File, "save/Slot1.sav")
+            ]
+        );
+        assert!(catalog::manifest_ids().unwrap().contains(&backup.id));
+        assert!(search(&data, "").files().unwrap().is_empty());
+    }
+}
@@ -3,7 +3,7 @@
 use eframe::egui::Visuals;
 use serde::{Deserialize, Serialize};
 
-use crate::{il8n::Lang, util};
+use crate::{il8n::Lang, log::Level, util};
 
 const PATH: &str = "./data/setting.json";
 
@@ -12,6 +12,18 @@ pub struct Setting {
     pub left_side_width: f32,
     pub theme: Theme,
     pub lang: Lang,
+    #[serde(default)]
+    pub api: ApiSetting,
+    #[serde(default)]
+    pub background: bool, // 关闭窗口后继续在后台运行
+    #[serde(default)]
+    pub trash: TrashSetting,
+    #[serde(default)]
+    pub log: LogSetting,
+    #[serde(default)]
+    pub notify: NotifySetting,
+    #[serde(default)]
+    pub health: HealthSetting,
 }
 impl Setting {
     pub fn get_theme(&mut self) -> eframe::Theme {
@@ -22,6 +34,84 @@ impl Setting {
     }
 }
 
+/// 本地 HTTP 接口，只监听 127.0.0.1，请求需要携带 token
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ApiSetting {
+    pub open: bool,
+    pub port: u16,
+    pub token: String,
+}
+impl Default for ApiSetting {
+    fn default() -> Self {
+        Self {
+            open: false,
+            port: 7878,
+            token: uuid::Uuid::new_v4().simple().to_string(),
+        }
+    }
+}
+
+/// 回收站中的内容保留的天数，0 表示永久保留
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrashSetting {
+    pub purge_days: u32,
+}
+impl Default for TrashSetting {
+    fn default() -> Self {
+        Self { purge_days: 30 }
+    }
+}
+
+/// 日志的最低级别，以及按大小和天数清理，0 表示不限制
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LogSetting {
+    pub level: Level,
+    pub max_size_mb: u64, // 当前日志超过后换一个新文件
+    pub keep_days: u32,   // 旧的日志文件保留的天数
+}
+impl Default for LogSetting {
+    fn default() -> Self {
+        Self {
+            level: Level::Info,
+            max_size_mb: 5,
+            keep_days: 30,
+        }
+    }
+}
+
+/// 备份结果的通知渠道，webhook 为空时不发送
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NotifySetting {
+    pub desktop: bool, // Linux 桌面通知
+    pub webhook: String,
+}
+impl Default for NotifySetting {
+    fn default() -> Self {
+        Self {
+            desktop: cfg!(target_os = "linux"),
+            webhook: String::new(),
+        }
+    }
+}
+
+/// 健康检查的阈值，超过天数没有备份时警告，连续失败次数达到时报错
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct HealthSetting {
+    pub stale_days: u32, // 0 表示不检查
+    pub failed_runs: usize,
+}
+impl Default for HealthSetting {
+    fn default() -> Self {
+        Self {
+            stale_days: 14,
+            failed_runs: 3,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq)]
 pub enum Theme {
     Dark,
@@ -42,6 +132,12 @@ impl Setting {
             left_side_width: 80.0,
             theme: Theme::Dark,
             lang: Lang::Cn,
+            api: ApiSetting::default(),
+            background: false,
+            trash: TrashSetting::default(),
+            log: LogSetting::default(),
+            notify: NotifySetting::default(),
+            health: HealthSetting::default(),
         }
     }
 
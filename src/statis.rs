@@ -1,4 +1,16 @@
-use crate::job::StartInfo;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    health::{Health, HealthStatus},
+    job::StartInfo,
+    setting::HealthSetting,
+};
+
+/// 数据没有变化时重新检查健康状态的间隔，监听路径可能在程序外被删除
+const HEALTH_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Default)]
 pub struct Statis {
@@ -6,5 +18,83 @@ pub struct Statis {
     pub active: usize,
     pub archive: usize,
     pub auto_backup: usize,
+    pub storage: u64, // 所有备份占用的空间
     pub jobs: Vec<StartInfo>,
+    pub unhealthy: usize,                 // 有警告或错误的存档数量
+    pub healths: HashMap<String, Health>, // 存档 id 对应的健康状态
+    health_checked: Option<(u64, HealthSetting, Instant)>, // 检查时的索引版本、设置和时间
+}
+impl Statis {
+    /// 只清空数量，缓存的健康状态保留
+    pub fn reset_counts(&mut self) {
+        self.total = 0;
+        self.active = 0;
+        self.archive = 0;
+        self.auto_backup = 0;
+        self.storage = 0;
+        self.jobs.clear();
+    }
+
+    /// 索引没有写入、设置没有变化并且没有超过间隔时，缓存的健康状态仍然可用
+    pub fn health_fresh(&self, revision: u64, setting: &HealthSetting) -> bool {
+        self.health_checked
+            .as_ref()
+            .is_some_and(|(checked, checked_setting, time)| {
+                *checked == revision
+                    && checked_setting == setting
+                    && time.elapsed() < HEALTH_INTERVAL
+            })
+    }
+
+    pub fn set_healths(&mut self, healths: Vec<Health>, revision: u64, setting: &HealthSetting) {
+        self.unhealthy = healths
+            .iter()
+            .filter(|health| health.status != HealthStatus::Ok)
+            .count();
+        self.healths = healths
+            .into_iter()
+            .map(|health| (health.item_id.to_string(), health))
+            .collect();
+        self.health_checked = Some((revision, setting.clone(), Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(id: &str, status: HealthStatus) -> Health {
+        Health {
+            item_id: id.to_string(),
+            item_name: id.to_string(),
+            status,
+            issues: vec![],
+        }
+    }
+
+    #[test]
+    fn cached_healths() {
+        let mut statis = Statis::default();
+        let setting = HealthSetting::default();
+        assert!(!statis.health_fresh(1, &setting));
+
+        let healths = vec![
+            health("a", HealthStatus::Ok),
+            health("b", HealthStatus::Warn),
+        ];
+        statis.set_healths(healths, 1, &setting);
+        assert_eq!(statis.unhealthy, 1);
+        assert_eq!(statis.healths["b"].status, HealthStatus::Warn);
+        assert!(statis.health_fresh(1, &setting));
+        // 数据写入或者设置变化后重新检查
+        assert!(!statis.health_fresh(2, &setting));
+        let changed = HealthSetting {
+            stale_days: 1,
+            ..Default::default()
+        };
+        assert!(!statis.health_fresh(1, &changed));
+
+        statis.reset_counts();
+        assert_eq!(statis.healths.len(), 2);
+    }
 }
@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{gamedb, util};
+
+const PROTON_USER: &str = "steamuser";
+
+/// Valve KeyValues（vdf、acf）中的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Vdf {
+    Str(String),
+    Map(Vec<(String, Vdf)>),
+}
+impl Vdf {
+    /// 按键名查找子节点，不区分大小写
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Vdf::Str(_) => None,
+        }
+    }
+
+    pub fn str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Vdf::Str(value) => Some(value),
+            Vdf::Map(_) => None,
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Map(entries) => entries,
+            Vdf::Str(_) => &[],
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => tokens.push(c.to_string()),
+            '"' => {
+                let mut token = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => token.push('\n'),
+                            Some('t') => token.push('\t'),
+                            Some(c) => token.push(c),
+                            None => return Err("unexpected end of vdf".to_string()),
+                        },
+                        Some(c) => token.push(c),
+                        None => return Err("unclosed quote in vdf".to_string()),
+                    }
+                }
+                tokens.push(token);
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                // 注释到行尾
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// 解析 vdf 文本，返回最外层的 Map
+pub fn parse(text: &str) -> Result<Vdf, String> {
+    fn map(tokens: &[String], index: &mut usize, nested: bool) -> Result<Vdf, String> {
+        let mut entries = vec![];
+        while *index < tokens.len() {
+            let key = &tokens[*index];
+            *index += 1;
+            if key == "}" {
+                if nested {
+                    return Ok(Vdf::Map(entries));
+                }
+                return Err("unexpected } in vdf".to_string());
+            }
+            let value = tokens
+                .get(*index)
+                .ok_or(format!("missing value of {} in vdf", key))?;
+            *index += 1;
+            let value = if value == "{" {
+                map(tokens, index, true)?
+            } else {
+                Vdf::Str(value.to_string())
+            };
+            entries.push((key.to_string(), value));
+        }
+        if nested {
+            return Err("unclosed { in vdf".to_string());
+        }
+        Ok(Vdf::Map(entries))
+    }
+    let tokens = tokenize(text)?;
+    map(&tokens, &mut 0, false)
+}
+
+fn read_vdf(path: &str) -> Result<Vdf, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("read {} error: {}", path, e))?;
+    parse(&text).map_err(|e| format!("parse {} error: {}", path, e))
+}
+
+/// 所有 Steam 游戏库，包括 libraryfolders.vdf 中的其他磁盘
+pub fn libraries() -> Vec<String> {
+    let mut libraries: Vec<String> = vec![];
+    let mut push = |path: &str| {
+        let path = path.replace('\\', "/");
+        let path = std::fs::canonicalize(&path)
+            .map(|path| path.display().to_string())
+            .unwrap_or(path);
+        if util::file_exist(&format!("{}/steamapps", path)) && !libraries.contains(&path) {
+            libraries.push(path);
+        }
+    };
+    for root in gamedb::steam_roots() {
+        push(&root);
+        let Ok(vdf) = read_vdf(&format!("{}/steamapps/libraryfolders.vdf", root)) else {
+            continue;
+        };
+        let folders = vdf.get("libraryfolders").or(vdf.get("LibraryFolders"));
+        for (_, folder) in folders.map(|f| f.entries()).unwrap_or_default() {
+            match folder {
+                // 新版本：{ "path" "..." "apps" {...} }
+                Vdf::Map(_) => {
+                    if let Some(path) = folder.str("path") {
+                        push(path);
+                    }
+                }
+                // 旧版本："1" "D:\\SteamLibrary"
+                Vdf::Str(path) => push(path),
+            }
+        }
+    }
+    libraries
+}
+
+/// 通过 appmanifest 找到的已安装游戏
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SteamApp {
+    pub app_id: u32,
+    pub name: String,
+    pub install_dir: String, // 完整的安装路径
+    pub library: String,
+    pub prefix: Option<String>, // Proton 前缀，原生运行的游戏没有
+}
+impl SteamApp {
+    pub fn launch_url(&self) -> String {
+        launch_url(self.app_id)
+    }
+}
+
+pub fn launch_url(app_id: u32) -> String {
+    format!("steam://rungameid/{}", app_id)
+}
+
+pub fn parse_launch_url(url: &str) -> Option<u32> {
+    url.strip_prefix("steam://rungameid/")?.parse().ok()
+}
+
+fn read_app(libraries: &[String], library: &str, path: &str) -> Option<SteamApp> {
+    let vdf = read_vdf(path).ok()?;
+    let state = vdf.get("AppState")?;
+    let app_id = state.str("appid")?.parse().ok()?;
+    let install_dir = state.str("installdir")?;
+    Some(SteamApp {
+        app_id,
+        name: state.str("name").unwrap_or(install_dir).to_string(),
+        install_dir: format!("{}/steamapps/common/{}", library, install_dir),
+        library: library.to_string(),
+        prefix: find_prefix(libraries, app_id),
+    })
+}
+
+/// 所有游戏库中的已安装游戏，按名称排序，不包括 Proton 等工具
+pub fn installed_apps() -> Vec<SteamApp> {
+    let mut apps: Vec<SteamApp> = vec![];
+    let libraries = libraries();
+    for library in &libraries {
+        let steamapps = format!("{}/steamapps", library);
+        for entry in util::read_dir(&steamapps) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with("appmanifest_") || !name.ends_with(".acf") {
+                continue;
+            }
+            let path = format!("{}/{}", steamapps, name);
+            if let Some(app) = read_app(&libraries, library, &path) {
+                if !is_tool(&app) && !apps.iter().any(|a| a.app_id == app.app_id) {
+                    apps.push(app);
+                }
+            }
+        }
+    }
+    apps.sort_by_key(|app| app.name.to_lowercase());
+    apps
+}
+
+fn is_tool(app: &SteamApp) -> bool {
+    let name = app.name.to_lowercase();
+    name.starts_with("proton") || name.starts_with("steam linux runtime") || app.app_id == 228980
+}
+
+/// 游戏的 Proton 前缀 <library>/steamapps/compatdata/<appid>/pfx
+pub fn prefix(app_id: u32) -> Option<String> {
+    find_prefix(&libraries(), app_id)
+}
+
+fn find_prefix(libraries: &[String], app_id: u32) -> Option<String> {
+    libraries
+        .iter()
+        .map(|library| format!("{}/steamapps/compatdata/{}/pfx", library, app_id))
+        .find(|prefix| util::file_exist(prefix))
+}
+
+/// 所有登录过的 Steam 用户目录 <root>/userdata/<id>
+fn user_dirs() -> Vec<String> {
+    let mut dirs = vec![];
+    for root in gamedb::steam_roots() {
+        let userdata = format!("{}/userdata", root);
+        for entry in util::read_dir(&userdata) {
+            if entry.path().is_dir() {
+                dirs.push(format!(
+                    "{}/{}",
+                    userdata,
+                    entry.file_name().to_string_lossy()
+                ));
+            }
+        }
+    }
+    dirs
+}
+
+/// 游戏的总游玩时间（分钟），多个用户时取最大值
+pub fn playtime(app_id: u32) -> Option<u64> {
+    user_dirs()
+        .iter()
+        .filter_map(|dir| read_vdf(&format!("{}/config/localconfig.vdf", dir)).ok())
+        .filter_map(|vdf| {
+            vdf.get("UserLocalConfigStore")?
+                .get("Software")?
+                .get("Valve")?
+                .get("Steam")?
+                .get("apps")?
+                .get(&app_id.to_string())?
+                .str("Playtime")?
+                .parse()
+                .ok()
+        })
+        .max()
+}
+
+/// Steam 截图的保存位置 <root>/userdata/<id>/760/remote/<appid>/screenshots
+pub fn screenshot_folders(app_id: u32) -> Vec<String> {
+    user_dirs()
+        .iter()
+        .map(|dir| format!("{}/760/remote/{}/screenshots", dir, app_id))
+        .filter(|folder| util::file_exist(folder))
+        .collect()
+}
+
+/// 把 Windows 风格的路径解析成 Proton 前缀中的路径
+///
+/// 支持 `%APPDATA%` 之类的环境变量、游戏数据库中的 `<winAppData>` 之类的变量和盘符
+pub fn resolve_windows_path(prefix: &str, path: &str) -> String {
+    let home = format!("C:/users/{}", PROTON_USER);
+    let vars = [
+        ("%USERPROFILE%", home.to_string()),
+        ("%APPDATA%", format!("{}/AppData/Roaming", home)),
+        ("%LOCALAPPDATA%", format!("{}/AppData/Local", home)),
+        ("%PUBLIC%", "C:/users/Public".to_string()),
+        ("%PROGRAMDATA%", "C:/ProgramData".to_string()),
+        ("<home>", home.to_string()),
+        ("<winAppData>", format!("{}/AppData/Roaming", home)),
+        ("<winLocalAppData>", format!("{}/AppData/Local", home)),
+        ("<winDocuments>", format!("{}/Documents", home)),
+    ];
+    let mut path = path.trim().replace('\\', "/");
+    for (var, value) in &vars {
+        if let Some(index) = find_ignore_case(&path, var) {
+            path.replace_range(index..index + var.len(), value);
+        }
+    }
+    let path = path.replace("~/", &format!("{}/", home));
+    match path.split_once(':') {
+        Some((drive, rest)) if drive.len() == 1 => {
+            let drive = drive.to_lowercase();
+            if drive == "c" {
+                format!("{}/drive_c{}", prefix, rest)
+            } else {
+                format!("{}/dosdevices/{}:{}", prefix, drive, rest)
+            }
+        }
+        // 没有盘符时当作相对于用户目录的路径
+        _ => format!(
+            "{}/drive_c/users/{}/{}",
+            prefix,
+            PROTON_USER,
+            path.trim_start_matches('/')
+        ),
+    }
+}
+
+/// 不区分大小写查找只有 ASCII 字符的变量，返回在原字符串中的位置
+/// 不能在 to_lowercase 的结果中查找，非 ASCII 字符转换后字节长度可能变化
+fn find_ignore_case(text: &str, pattern: &str) -> Option<usize> {
+    text.char_indices().map(|(index, _)| index).find(|index| {
+        text.get(*index..*index + pattern.len())
+            .is_some_and(|part| part.eq_ignore_ascii_case(pattern))
+    })
+}
+
+/// 把游戏的 Windows 风格路径解析成实际的路径，游戏没有 Proton 前缀时返回错误
+pub fn resolve_app_path(app_id: u32, path: &str) -> Result<String, String> {
+    let prefix = prefix(app_id).ok_or(format!("proton prefix not found: {}", app_id))?;
+    Ok(resolve_windows_path(&prefix, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_nested_maps() {
+        let text = r#"
+"libraryfolders"
+{
+    // 注释
+    "0"
+    {
+        "path"      "/home/user/.local/share/Steam"
+        "apps"
+        {
+            "1091500"   "69661082512"
+        }
+    }
+    "1" { "path" "/mnt/games\\Steam" }
+}
+"#;
+        let vdf = parse(text).unwrap();
+        let folders = vdf.get("LibraryFolders").unwrap();
+        assert_eq!(folders.entries().len(), 2);
+        let first = folders.get("0").unwrap();
+        assert_eq!(first.str("path"), Some("/home/user/.local/share/Steam"));
+        assert_eq!(
+            first.get("apps").unwrap().str("1091500"),
+            Some("69661082512")
+        );
+        assert_eq!(
+            folders.get("1").unwrap().str("path"),
+            Some("/mnt/games\\Steam")
+        );
+        assert_eq!(first.str("apps"), None);
+    }
+
+    #[test]
+    fn parse_unquoted_tokens() {
+        let vdf = parse("AppState { appid 620 name \"Portal 2\" }").unwrap();
+        let state = vdf.get("appstate").unwrap();
+        assert_eq!(state.str("appid"), Some("620"));
+        assert_eq!(state.str("name"), Some("Portal 2"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("\"a\" { \"b\" \"c\"").is_err());
+        assert!(parse("\"a\" \"b\" }").is_err());
+        assert!(parse("\"a\"").is_err());
+        assert!(parse("\"a\" \"b").is_err());
+    }
+
+    #[test]
+    fn resolve_variables_and_drives() {
+        let prefix = "/pfx";
+        assert_eq!(
+            resolve_windows_path(prefix, r"%AppData%\Game\Saves"),
+            "/pfx/drive_c/users/steamuser/AppData/Roaming/Game/Saves"
+        );
+        assert_eq!(
+            resolve_windows_path(prefix, "<winDocuments>/My Games"),
+            "/pfx/drive_c/users/steamuser/Documents/My Games"
+        );
+        assert_eq!(
+            resolve_windows_path(prefix, r"D:\Games\save"),
+            "/pfx/dosdevices/d:/Games/save"
+        );
+        assert_eq!(
+            resolve_windows_path(prefix, "Saved Games/Game"),
+            "/pfx/drive_c/users/steamuser/Saved Games/Game"
+        );
+    }
+
+    #[test]
+    fn resolve_non_ascii_paths() {
+        // 'İ' 转成小写后字节长度会变化
+        assert_eq!(
+            resolve_windows_path("/pfx", r"%LOCALAPPDATA%\İstanbul\存档"),
+            "/pfx/drive_c/users/steamuser/AppData/Local/İstanbul/存档"
+        );
+        assert_eq!(find_ignore_case("İİ%AppData%", "%APPDATA%"), Some(4));
+        assert_eq!(find_ignore_case("İİ", "%APPDATA%"), None);
+    }
+}
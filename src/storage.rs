@@ -0,0 +1,307 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::Backup,
+    data::{Data, SaveItem},
+    log, util,
+};
+
+const LARGEST_LIMIT: usize = 10;
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// 备份保留策略，0 表示不限制，受保护的备份不会被清理
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Retention {
+    pub keep_last: usize, // 每个配置保留最近的几个备份，不包括保护的备份
+    pub keep_days: u32,   // 保留最近几天的备份
+}
+impl Retention {
+    pub fn is_set(&self) -> bool {
+        self.keep_last > 0 || self.keep_days > 0
+    }
+
+    /// 超出保留策略的备份，备份按时间倒序排列
+    ///
+    /// 每个配置分别计数，保护的备份不会过期，也不占用保留的数量
+    pub fn expired<'a>(&self, backups: &'a [Backup]) -> Vec<&'a Backup> {
+        if !self.is_set() {
+            return vec![];
+        }
+        let now = chrono::Local::now().timestamp_millis();
+        let mut kept: HashMap<&str, usize> = HashMap::new();
+        backups
+            .iter()
+            .filter(|backup| !backup.protect)
+            .filter(|backup| {
+                let count = kept.entry(backup.profile.as_str()).or_default();
+                *count += 1;
+                let over_count = self.keep_last > 0 && *count > self.keep_last;
+                let over_days =
+                    self.keep_days > 0 && now - backup.time > self.keep_days as i64 * DAY_MILLIS;
+                over_count || over_days
+            })
+            .collect()
+    }
+}
+
+/// 统计文件夹的大小和文件数量
+pub fn measure(path: &str) -> (u64, u64) {
+    fn walk(path: &Path, size: &mut u64, files: &mut u64) {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                walk(&entry.path(), size, files);
+            } else {
+                *size += meta.len();
+                *files += 1;
+            }
+        }
+    }
+    let (mut size, mut files) = (0, 0);
+    walk(Path::new(path), &mut size, &mut files);
+    (size, files)
+}
+
+/// 统计旧版本没有记录大小的备份，返回更新的数量
+pub fn fill_missing(data: &mut Data) -> usize {
+    let mut count = 0;
+    for item in data.monitors.iter_mut() {
+        for backup in item.backups.iter_mut() {
+            if backup.files == 0 && !backup.is_git() && util::file_exist(&backup.backup_folder) {
+                (backup.size, backup.files) = measure(&backup.backup_folder);
+                count += 1;
+            }
+        }
+    }
+    if count > 0 {
+        data.save();
+        log::log_info(format!("measure {} backups", count));
+    }
+    count
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ItemUsage {
+    pub id: String,
+    pub name: String,
+    pub backups: usize,
+    pub size: u64,
+    pub files: u64,
+    pub retention: Retention,
+    pub reclaimable: u64, // 按保留策略可以释放的空间，git 格式的备份只删除记录，不计入
+    pub reclaimable_backups: usize,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BackupUsage {
+    pub item_id: String,
+    pub item_name: String,
+    pub backup_id: String,
+    pub time: i64,
+    pub remark: String,
+    pub size: u64,
+    pub files: u64,
+}
+
+/// 按月统计新增的备份大小以及累计的大小，只统计现存的备份
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MonthUsage {
+    pub month: String,
+    pub added: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StorageReport {
+    pub size: u64,
+    pub files: u64,
+    pub backups: usize,
+    pub reclaimable: u64,
+    pub items: Vec<ItemUsage>,
+    pub largest: Vec<BackupUsage>,
+    pub timeline: Vec<MonthUsage>,
+}
+
+pub fn report(data: &Data) -> StorageReport {
+    let mut report = StorageReport::default();
+    let mut all = vec![];
+    for item in &data.monitors {
+        let (size, files) = item.storage();
+        let expired = item.retention.expired(&item.backups);
+        let usage = ItemUsage {
+            id: item.id.to_string(),
+            name: item.name.to_string(),
+            backups: item.backups.len(),
+            size,
+            files,
+            retention: item.retention.clone(),
+            reclaimable: expired
+                .iter()
+                .filter(|backup| !backup.is_git())
+                .map(|backup| backup.size)
+                .sum(),
+            reclaimable_backups: expired.len(),
+        };
+        report.size += usage.size;
+        report.files += usage.files;
+        report.backups += usage.backups;
+        report.reclaimable += usage.reclaimable;
+        report.items.push(usage);
+        all.extend(item.backups.iter().map(|backup| (item, backup)));
+    }
+    report
+        .items
+        .sort_by_key(|item| std::cmp::Reverse(item.size));
+
+    let mut months: BTreeMap<String, u64> = BTreeMap::new();
+    for (_, backup) in &all {
+        let month = util::format_time(backup.time)
+            .and_then(|time| time.get(..7).map(str::to_string))
+            .unwrap_or_default();
+        *months.entry(month).or_default() += backup.size;
+    }
+    let mut total = 0;
+    for (month, added) in months {
+        total += added;
+        report.timeline.push(MonthUsage {
+            month,
+            added,
+            total,
+        });
+    }
+
+    all.sort_by_key(|(_, backup)| std::cmp::Reverse(backup.size));
+    report.largest = all
+        .iter()
+        .take(LARGEST_LIMIT)
+        .map(|(item, backup)| BackupUsage {
+            item_id: item.id.to_string(),
+            item_name: item.name.to_string(),
+            backup_id: backup.id.to_string(),
+            time: backup.time,
+            remark: backup.remark.to_string(),
+            size: backup.size,
+            files: backup.files,
+        })
+        .collect();
+    report
+}
+
+/// 按保留策略移除备份记录，返回更新后的存档和移除的备份，文件由调用方处理
+pub fn prune(data: &mut Data, id: &str) -> Result<(SaveItem, Vec<Backup>), String> {
+    let item = data
+        .find_mut(id)
+        .ok_or(format!("save item not found: {}", id))?;
+    if !item.retention.is_set() {
+        return Err(format!("no retention policy: {}", item.name));
+    }
+    let expired: Vec<String> = item
+        .retention
+        .expired(&item.backups)
+        .iter()
+        .map(|backup| backup.id.to_string())
+        .collect();
+    let (removed, kept) = item
+        .backups
+        .drain(..)
+        .partition(|backup| expired.contains(&backup.id));
+    item.backups = kept;
+    let item = item.clone();
+    data.save();
+    Ok((item, removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// days 天前的备份
+    fn backup(days: i64, profile: &str, protect: bool) -> Backup {
+        let mut backup = Backup::new(vec![], String::new(), String::new());
+        backup.time = chrono::Local::now().timestamp_millis() - days * DAY_MILLIS - 1;
+        backup.profile = profile.to_string();
+        backup.protect = protect;
+        backup
+    }
+
+    fn expired_ids(retention: &Retention, backups: &[Backup]) -> Vec<String> {
+        retention
+            .expired(backups)
+            .iter()
+            .map(|backup| backup.id.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn keep_last_per_profile() {
+        let backups = vec![
+            backup(0, "", false),
+            backup(1, "Speedrun", false),
+            backup(2, "", true),
+            backup(3, "", false),
+            backup(4, "Speedrun", false),
+            backup(5, "", false),
+        ];
+        let retention = Retention {
+            keep_last: 2,
+            keep_days: 0,
+        };
+        // 默认配置保留 0 和 3，保护的 2 不占名额，Speedrun 的两个都保留
+        assert_eq!(
+            expired_ids(&retention, &backups),
+            [backups[5].id.to_string()]
+        );
+        assert!(expired_ids(&Retention::default(), &backups).is_empty());
+    }
+
+    #[test]
+    fn keep_days() {
+        let backups = vec![
+            backup(0, "", false),
+            backup(3, "", false),
+            backup(5, "", false),
+        ];
+        let retention = Retention {
+            keep_last: 0,
+            keep_days: 3,
+        };
+        assert_eq!(
+            expired_ids(&retention, &backups),
+            [backups[1].id.to_string(), backups[2].id.to_string()]
+        );
+        // 同时设置时超出任意一个就过期
+        let retention = Retention {
+            keep_last: 1,
+            keep_days: 4,
+        };
+        assert_eq!(
+            expired_ids(&retention, &backups),
+            [backups[1].id.to_string(), backups[2].id.to_string()]
+        );
+    }
+
+    #[test]
+    fn protected_never_expire() {
+        let backups = vec![
+            backup(0, "", false),
+            backup(30, "", true),
+            backup(40, "", true),
+        ];
+        let retention = Retention {
+            keep_last: 1,
+            keep_days: 7,
+        };
+        assert!(expired_ids(&retention, &backups).is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::{Backup, Outcome, Run, Trigger},
+    data::{Data, SaveItem, Status},
+};
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// 时间轴上点的类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PointKind {
+    Manual,
+    Auto,
+    PreRestore,
+    Failed, // 失败的运行，可能留下了不完整的备份
+}
+impl PointKind {
+    pub const ALL: [PointKind; 4] = [
+        PointKind::Manual,
+        PointKind::Auto,
+        PointKind::PreRestore,
+        PointKind::Failed,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PointKind::Manual => "Manual",
+            PointKind::Auto => "Auto",
+            PointKind::PreRestore => "Pre Restore",
+            PointKind::Failed => "Failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Point {
+    pub time: i64,
+    pub kind: PointKind,
+    pub size: u64,
+    pub backup_id: Option<String>, // 失败的运行为空
+    pub text: String,              // 备份的标题或备注，失败的原因
+}
+
+/// 一个存档的所有点，旧的在前
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lane {
+    pub item_id: String,
+    pub item_name: String,
+    pub points: Vec<Point>,
+}
+
+pub fn lane(item: &SaveItem) -> Lane {
+    let failed: Vec<&Run> = item
+        .runs
+        .iter()
+        .filter(|run| run.outcome == Outcome::Failed)
+        .collect();
+    // 失败但留下了备份记录的运行只画一次，画成失败的点，点击仍然定位到备份
+    let failed_backup = |backup: &Backup| {
+        failed
+            .iter()
+            .find(|run| !run.backup_id.is_empty() && run.backup_id == backup.id)
+    };
+    let mut points: Vec<Point> = item
+        .backups
+        .iter()
+        .map(|backup| Point {
+            time: backup.time,
+            kind: match (failed_backup(backup), &backup.trigger) {
+                (Some(_), _) => PointKind::Failed,
+                (None, Trigger::Manual) => PointKind::Manual,
+                (None, Trigger::Auto) => PointKind::Auto,
+                (None, Trigger::PreRestore) => PointKind::PreRestore,
+            },
+            size: backup.size,
+            backup_id: Some(backup.id.to_string()),
+            text: match failed_backup(backup) {
+                Some(run) => run.reason.to_string(),
+                None if backup.title.is_empty() => backup.remark.to_string(),
+                None => backup.title.to_string(),
+            },
+        })
+        .collect();
+    points.extend(
+        failed
+            .iter()
+            .filter(|run| !item.backups.iter().any(|backup| backup.id == run.backup_id))
+            .map(|run| Point {
+                time: run.time,
+                kind: PointKind::Failed,
+                size: 0,
+                backup_id: None,
+                text: run.reason.to_string(),
+            }),
+    );
+    points.sort_by_key(|point| point.time);
+    Lane {
+        item_id: item.id.to_string(),
+        item_name: item.name.to_string(),
+        points,
+    }
+}
+
+/// 未归档的存档，item 为空时返回全部
+pub fn lanes(data: &Data, item: &str) -> Vec<Lane> {
+    data.monitors
+        .iter()
+        .filter(|save_item| save_item.status == Status::Valid)
+        .filter(|save_item| item.is_empty() || save_item.id == item)
+        .map(lane)
+        .collect()
+}
+
+/// 显示的时间范围，days 为 0 时从最早的点开始，没有点时返回 None
+pub fn range(lanes: &[Lane], days: u32, now: i64) -> Option<(i64, i64)> {
+    if days > 0 {
+        return Some((now - days as i64 * DAY_MILLIS, now));
+    }
+    let start = lanes
+        .iter()
+        .filter_map(|lane| lane.points.first())
+        .map(|point| point.time)
+        .min()?;
+    // 只有一个点或者都在同一时刻时留出一天
+    Some((start.min(now - DAY_MILLIS), now))
+}
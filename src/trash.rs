@@ -0,0 +1,244 @@
+use std::{fmt::Display, path::Path};
+
+use fs_more::directory::DirectoryCopyOptions;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    backup::Backup,
+    data::{Data, SaveItem},
+    log::{self, Entry, Level},
+    util,
+};
+
+const TRASH_DIR: &str = "./data/trash";
+const META_FILE: &str = "meta.json";
+const FILES_DIR: &str = "files";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TrashKind {
+    Backup,
+    SaveItem,
+}
+impl Display for TrashKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            TrashKind::Backup => "Backup",
+            TrashKind::SaveItem => "Save Item",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// 回收站中的一项，文件放在 data/trash/<id>/files，元数据放在 data/trash/<id>/meta.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub kind: TrashKind,
+    pub deleted_at: i64,
+    pub item_id: String,
+    pub item_name: String,
+    pub backup: Option<Backup>,      // 删除的备份
+    pub save_item: Option<SaveItem>, // 彻底删除的存档，包含所有备份记录
+}
+impl TrashEntry {
+    fn new(kind: TrashKind, save_item: &SaveItem) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            kind,
+            deleted_at: chrono::Local::now().timestamp_millis(),
+            item_id: save_item.id.to_string(),
+            item_name: save_item.name.to_string(),
+            backup: None,
+            save_item: None,
+        }
+    }
+
+    fn dir(&self) -> String {
+        format!("{}/{}", TRASH_DIR, self.id)
+    }
+
+    fn files(&self) -> String {
+        format!("{}/{}", self.dir(), FILES_DIR)
+    }
+
+    /// 按照保留天数计算的过期时间，0 表示永久保留
+    pub fn expire_at(&self, days: u32) -> Option<i64> {
+        if days == 0 {
+            return None;
+        }
+        Some(self.deleted_at + days as i64 * 24 * 60 * 60 * 1000)
+    }
+
+    pub fn summary(&self, days: u32) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "kind": self.kind.to_string(),
+            "deleted_at": self.deleted_at,
+            "expire_at": self.expire_at(days),
+            "item_id": self.item_id,
+            "item_name": self.item_name,
+            "backup_id": self.backup.as_ref().map(|backup| backup.id.to_string()),
+            "remark": self.backup.as_ref().map(|backup| backup.remark.to_string()),
+        })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        util::check_dir_and_create(&self.dir());
+        let data = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        util::write_data(&format!("{}/{}", self.dir(), META_FILE), data)
+    }
+}
+
+/// 移动文件夹，跨磁盘无法重命名时复制后删除
+fn move_dir(source: &str, target: &str) -> Result<(), String> {
+    if !util::file_exist(source) {
+        return Ok(());
+    }
+    if let Some(parent) = Path::new(target).parent() {
+        util::check_dir_and_create(&parent.display().to_string());
+    }
+    if std::fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    fs_more::directory::copy_directory(
+        source,
+        target,
+        DirectoryCopyOptions {
+            target_directory_rule: fs_more::directory::TargetDirectoryRule::AllowEmpty,
+            maximum_copy_depth: Some(1000),
+        },
+    )
+    .map_err(|e| format!("move {} to {} error: {}", source, target, e))?;
+    std::fs::remove_dir_all(source).map_err(|e| format!("delete {} error: {}", source, e))
+}
+
+/// 把删除的备份移到回收站
+pub fn trash_backup(save_item: &SaveItem, backup: &Backup) -> Result<TrashEntry, String> {
+    let mut entry = TrashEntry::new(TrashKind::Backup, save_item);
+    entry.backup = Some(backup.clone());
+    // git 格式的备份共用仓库，只删除记录，提交保留在仓库中
+    if !backup.is_git() {
+        move_dir(&backup.backup_folder, &entry.files())?;
+    }
+    entry.save()?;
+    Entry::new(Level::Info, "move backup to trash")
+        .item(&save_item.name)
+        .backup(&backup.id)
+        .write();
+    Ok(entry)
+}
+
+/// 把彻底删除的存档和它的所有备份移到回收站
+pub fn trash_save_item(save_item: &SaveItem) -> Result<TrashEntry, String> {
+    let mut entry = TrashEntry::new(TrashKind::SaveItem, save_item);
+    entry.save_item = Some(save_item.clone());
+    move_dir(&backup_dir(&save_item.id), &entry.files())?;
+    entry.save()?;
+    log::log_info(format!("move save item to trash, name={}", save_item.name));
+    Ok(entry)
+}
+
+fn backup_dir(id: &str) -> String {
+    format!("{}/data/backup/{}", util::current_dir(), id)
+}
+
+/// 回收站中的所有内容，最近删除的在前
+pub fn list() -> Vec<TrashEntry> {
+    let mut entries = vec![];
+    let Ok(dirs) = std::fs::read_dir(TRASH_DIR) else {
+        return entries;
+    };
+    for dir in dirs.flatten() {
+        let meta = dir.path().join(META_FILE);
+        let Ok(data) = util::read_data(&meta.display().to_string()) else {
+            continue;
+        };
+        match serde_json::from_str::<TrashEntry>(&data) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::log_err(format!("read {} error: {}", meta.display(), e)),
+        }
+    }
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+    entries
+}
+
+pub fn find(id: &str) -> Result<TrashEntry, String> {
+    list()
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or(format!("trash not found: {}", id))
+}
+
+/// 从回收站恢复，返回恢复后的存档
+pub fn restore(id: &str, data: &mut Data) -> Result<SaveItem, String> {
+    let entry = find(id)?;
+    let item = match entry.kind {
+        TrashKind::Backup => {
+            let backup = entry.backup.clone().ok_or("invalid trash: no backup")?;
+            if data.find(&entry.item_id).is_none() {
+                return Err(format!(
+                    "save item not found: {}, restore the save item first",
+                    entry.item_name
+                ));
+            }
+            if !backup.is_git() {
+                if util::file_exist(&backup.backup_folder) {
+                    return Err(format!("{} already exists", backup.backup_folder));
+                }
+                move_dir(&entry.files(), &backup.backup_folder)?;
+            }
+            data.insert_backup(&entry.item_id, backup)?
+        }
+        TrashKind::SaveItem => {
+            let item = entry
+                .save_item
+                .clone()
+                .ok_or("invalid trash: no save item")?;
+            if data.monitors.iter().any(|monitor| monitor.id == item.id) {
+                return Err(format!("save item already exists: {}", item.name));
+            }
+            let folder = backup_dir(&item.id);
+            if util::file_exist(&folder) {
+                return Err(format!("{} already exists", folder));
+            }
+            move_dir(&entry.files(), &folder)?;
+            data.push_monitor(item.clone());
+            item
+        }
+    };
+    util::delete_dirs(&entry.dir());
+    log::log_info(format!("restore from trash, name={}", entry.item_name));
+    Ok(item)
+}
+
+/// 从回收站永久删除
+pub fn purge(id: &str) -> Result<TrashEntry, String> {
+    let entry = find(id)?;
+    std::fs::remove_dir_all(entry.dir()).map_err(|e| e.to_string())?;
+    // 截图可能不在回收站中（例如删除备份时截图留在存档的截图目录），永久删除时一起删除
+    let backups = entry
+        .backup
+        .iter()
+        .chain(entry.save_item.iter().flat_map(|item| item.backups.iter()));
+    for backup in backups {
+        if !backup.screenshot.is_empty() {
+            util::delete_file(&backup.screenshot);
+        }
+    }
+    log::log_info(format!("purge trash, name={}", entry.item_name));
+    Ok(entry)
+}
+
+/// 永久删除超过保留天数的内容，返回删除的数量
+pub fn purge_expired(days: u32) -> usize {
+    let now = chrono::Local::now().timestamp_millis();
+    let mut count = 0;
+    for entry in list() {
+        match entry.expire_at(days) {
+            Some(expire_at) if expire_at <= now && purge(&entry.id).is_ok() => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
@@ -11,6 +11,10 @@ use crate::{backup::BackupData, log};
 
 // 获取当前程序运行路径
 pub fn current_dir() -> String {
+    // 测试时日志和备份写到临时目录，不在仓库中留下文件
+    if cfg!(test) {
+        return env::temp_dir().join("auto_backup_test").display().to_string();
+    }
     match env::current_dir() {
         Ok(path) => path.display().to_string(),
         Err(_) => ".".to_string(),
@@ -48,7 +52,7 @@ pub fn read_dir(path: &str) -> Vec<DirEntry> {
     if let Ok(paths) = fs::read_dir(path) {
         for path in paths {
             if let Ok(path) = path {
-                log::log_info(format!("init music dir: {}", path.path().display()));
+                log::log_debug(format!("read dir: {}", path.path().display()));
                 vec.push(path);
             }
         }
@@ -182,6 +186,31 @@ pub fn time_eq(t1: &DateTime<Local>, t2: &DateTime<Local>) -> bool {
         && t1.second() == t2.second()
 }
 
+/// 字节数格式化为 KB、MB、GB
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// 毫秒时间戳格式化为本地时间
+pub fn format_time(millis: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp_millis(millis).map(|time| {
+        time.with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    })
+}
+
 pub fn delete_dirs(path: &str) {
     if file_exist(&path) {
         fs::remove_dir_all(path);
@@ -1,23 +1,41 @@
+use std::{sync::mpsc::TryRecvError, time::Duration};
+
 use chrono::Local;
 use eframe::egui::{
-    Align, CentralPanel, Color32, Context, CursorIcon, Label, Layout, RichText, ScrollArea, Sense,
-    SidePanel, TextEdit, TextStyle, Ui, Vec2, Widget,
+    Align, Align2, CentralPanel, CollapsingHeader, Color32, ComboBox, Context, CursorIcon,
+    DragValue, FontId, Image, Key, Label, Layout, Pos2, ProgressBar, Rect, Response, RichText,
+    ScrollArea, Sense, SidePanel, Stroke, TextEdit, TextStyle, Ui, Vec2, Widget,
 };
 use egui_extras::{Column, Size, StripBuilder, TableBuilder};
 use egui_notify::ToastLevel;
 use rfd::FileDialog;
+use serde_json::Value;
 
 use crate::{
-    backup::Backup,
+    backup::{Backup, BackupFormat},
+    bulk::{BackupSelector, BulkAction, BulkRequest, BulkResult},
+    bundle::{self, ImportMode},
+    catalog::{self, BackupSort, Page, PageQuery, PAGE_SIZES},
+    condition::{Condition, ProcessRule},
     data::{AutoStatus, FileType, Monitor, SaveItem, Status},
-    entity::{AutoBackup, Command, Navigation},
-    il8n::Lang,
+    entity::{AutoBackup, Command, Navigation, PendingRequest, Reply},
+    filter::{self, ItemFilter, SortBy},
+    health::{self, Health, HealthStatus},
+    il8n::{Lang, Translator},
     job,
+    log::{self, Level},
+    notify::{self, Delivery},
+    profile, screenshot,
+    search::Hit,
     setting::Theme,
-    statis::Statis,
-    style, util,
+    steam::{self, SteamApp},
+    storage, style,
+    timeline::{self, Point, PointKind},
+    trash, util,
     windows::{
-        ShowConfirm, ShowConfirmDelBackup, ShowConfirmOverwriteBackup, ShowConfirmTotoallyDel,
+        ShowConfirm, ShowConfirmDelBackup, ShowConfirmImport, ShowConfirmOverwriteBackup,
+        ShowConfirmPurgeTrash, ShowConfirmSwitchProfile, ShowConfirmTotoallyDel, ShowExport,
+        ShowFileHistory, ShowGit, ShowItemHistory, ShowRescan, ShowScanGames,
     },
 };
 
@@ -26,13 +44,16 @@ pub struct Form {
     pub new: bool,
     open: bool,
     save_item: SaveItem,
+    new_tag: String,
+    steam_apps: Option<Vec<SteamApp>>, // 已安装的 Steam 游戏，为空时重新读取
+    windows_path: String,              // 需要在 Proton 前缀中解析的路径
 }
 
 impl AutoBackup {
     pub fn home_view(&mut self, ui: &mut Ui) {
         let theme = &self.setting.get_theme();
         let col_num = 4;
-        let mut detail_card = |ui: &mut Ui, item: &job::StartInfo| {
+        let mut detail_card = |ui: &mut Ui, item: &job::StartInfo, health: Option<&Health>| {
             let size = Vec2::new(150.0, 20.0);
             let name = item.save_item.name.to_string();
             let name = RichText::new(name).strong();
@@ -48,6 +69,9 @@ impl AutoBackup {
                 self.control.nav = Navigation::Manage;
                 self.control.manage = item.save_item.name.to_string();
             }
+            if let Some(health) = health {
+                health_badge(ui, &self.t, health);
+            }
 
             let parse_time = job::parse_time(&item.save_item.auto.cron);
             let parse_time = Label::new(parse_time[0].to_string()).truncate(true);
@@ -77,7 +101,7 @@ impl AutoBackup {
 
         CentralPanel::default().show_inside(ui, |ui| {
             ui.vertical(|ui| {
-                self.statis = Statis::default();
+                self.statis.reset_counts();
                 if let Ok(data) = self.data.try_lock() {
                     for item in &data.monitors {
                         match item.status {
@@ -86,6 +110,7 @@ impl AutoBackup {
                             _ => {}
                         }
                         self.statis.total += 1;
+                        self.statis.storage += item.storage().0;
                     }
                 }
                 if let Ok(job_hanlde) = self.job_handle.try_lock() {
@@ -103,20 +128,40 @@ impl AutoBackup {
                         "Active" => style::rgb(127, 158, 122),
                         "Archive" => style::rgb(158, 153, 122),
                         "Auto Backup" => style::rgb(122, 148, 158),
+                        "Storage" => style::rgb(148, 122, 158),
+                        "Needs Attention" => style::rgb(158, 122, 122),
                         _ => Color32::TRANSPARENT,
                     };
                     for (label, num, _nav) in [
-                        ("Total", self.statis.total, Navigation::Home),
-                        ("Active", self.statis.active, Navigation::Manage),
-                        ("Archive", self.statis.archive, Navigation::Archive),
-                        ("Auto Backup", self.statis.auto_backup, Navigation::Manage),
+                        ("Total", self.statis.total.to_string(), Navigation::Home),
+                        ("Active", self.statis.active.to_string(), Navigation::Manage),
+                        (
+                            "Archive",
+                            self.statis.archive.to_string(),
+                            Navigation::Archive,
+                        ),
+                        (
+                            "Auto Backup",
+                            self.statis.auto_backup.to_string(),
+                            Navigation::Manage,
+                        ),
+                        (
+                            "Storage",
+                            util::format_size(self.statis.storage),
+                            Navigation::Storage,
+                        ),
+                        (
+                            "Needs Attention",
+                            self.statis.unhealthy.to_string(),
+                            Navigation::Manage,
+                        ),
                     ] {
                         let width = 140.0;
                         card_frame.show(ui, |ui| {
                             ui.vertical(|ui| {
                                 let color = color(label);
                                 let label = RichText::new(self.t.get(&label)).strong();
-                                let num = RichText::new(num.to_string()).color(color).size(32.0);
+                                let num = RichText::new(num).color(color).size(32.0);
                                 let label = Label::new(label).selectable(false);
                                 let num = Label::new(num).selectable(false);
                                 ui.add_sized(Vec2::new(width, 18.0), label);
@@ -155,7 +200,8 @@ impl AutoBackup {
                                     let ui: &mut Ui = &mut ui[i];
                                     // ui.vertical_centered_justified(|ui| {
                                     line_frame.clone().show(ui, |ui| {
-                                        detail_card(ui, &item);
+                                        let health = self.statis.healths.get(&item.save_item.id);
+                                        detail_card(ui, &item, health);
                                     });
                                     // });
                                 }
@@ -189,38 +235,40 @@ impl AutoBackup {
     }
 
     pub fn manage_view(&mut self, _ctx: &Context, ui: &mut Ui) {
-        let theme = &self.setting.get_theme();
-        let goto_new_backup = style::btn_primary_round(self.t.get("New Backup"), theme);
-        if let Ok(data) = self.data.try_lock() {
-            if data.monitors.is_empty() {
-                if goto_new_backup
-                    .ui(ui)
-                    .on_hover_cursor(CursorIcon::PointingHand)
-                    .clicked()
-                {
-                    self.control.nav = Navigation::New;
-                }
-                return;
-            }
+        let empty = match self.data.try_lock() {
+            Ok(data) => data.monitors.is_empty(),
+            Err(_) => false,
+        };
+        if empty {
+            ui.horizontal(|ui| {
+                self.new_backup_button(ui);
+                self.import_button(ui);
+                self.scan_games_button(ui);
+            });
+            return;
         }
 
         SidePanel::left("backup_nav")
             // .exact_width(120.0)
             .show_inside(ui, |ui| {
-                if goto_new_backup
-                    .ui(ui)
-                    .on_hover_cursor(CursorIcon::PointingHand)
-                    .clicked()
-                {
-                    self.control.nav = Navigation::New;
-                }
+                ui.horizontal_wrapped(|ui| {
+                    self.new_backup_button(ui);
+                    self.import_button(ui);
+                    self.scan_games_button(ui);
+                });
                 if let Ok(data) = self.data.try_lock() {
                     ui.separator();
-                    ScrollArea::vertical().show(ui, |ui| {
-                        for item in &data.monitors {
-                            if item.status != Status::Valid {
-                                continue;
-                            }
+                    let valid: Vec<&SaveItem> = data
+                        .monitors
+                        .iter()
+                        .filter(|item| item.status == Status::Valid)
+                        .collect();
+                    filter_bar(ui, &self.t, &mut self.control.item_filter, &valid);
+                    ui.separator();
+                    let items = self.control.item_filter.apply(valid);
+                    let groups = filter::all_groups(items.iter().copied());
+                    let mut nav_item = |ui: &mut Ui, item: &SaveItem| {
+                        ui.horizontal(|ui| {
                             if ui
                                 .selectable_value(
                                     &mut self.control.manage,
@@ -228,13 +276,58 @@ impl AutoBackup {
                                     item.name.to_string(),
                                 )
                                 .on_hover_cursor(CursorIcon::PointingHand)
+                                .on_hover_text(item.tags.join(", "))
                                 .clicked()
                             {
                                 if let Ok(mut active) = self.active.try_lock() {
                                     *active = item.clone();
                                 }
+                                self.control.jump_backup.clear();
+                            }
+                            if let Some(health) = self.statis.healths.get(&item.id) {
+                                health_badge(ui, &self.t, health);
+                            }
+                        });
+                        ui.separator();
+                    };
+                    ScrollArea::vertical().show(ui, |ui| {
+                        if groups.is_empty() {
+                            for item in &items {
+                                nav_item(ui, item);
                             }
-                            ui.separator();
+                            return;
+                        }
+                        // 按分组展示，未分组的放在最后
+                        let group_of = |item: &SaveItem| {
+                            groups
+                                .iter()
+                                .position(|group| group.eq_ignore_ascii_case(item.group.trim()))
+                        };
+                        for (index, group) in groups.iter().enumerate() {
+                            CollapsingHeader::new(group)
+                                .id_source(("save_item_group", group))
+                                .default_open(true)
+                                .show(ui, |ui| {
+                                    for item in
+                                        items.iter().filter(|item| group_of(item) == Some(index))
+                                    {
+                                        nav_item(ui, item);
+                                    }
+                                });
+                        }
+                        let ungrouped: Vec<_> = items
+                            .iter()
+                            .filter(|item| group_of(item).is_none())
+                            .collect();
+                        if !ungrouped.is_empty() {
+                            CollapsingHeader::new(self.t.get("Ungrouped"))
+                                .id_source("save_item_ungrouped")
+                                .default_open(true)
+                                .show(ui, |ui| {
+                                    for item in ungrouped {
+                                        nav_item(ui, item);
+                                    }
+                                });
                         }
                     });
                 }
@@ -269,6 +362,35 @@ impl AutoBackup {
                     }
                 }
             });
+            ui.horizontal(|ui| {
+                ui.add_sized(label_size, Label::new(self.t.get("Backup Format")));
+                let format = &mut self.form.save_item.format;
+                ui.selectable_value(format, BackupFormat::Folder, self.t.get("Folder"))
+                    .on_hover_text(self.t.get("Copy the monitored files into a new folder each time"));
+                ui.selectable_value(format, BackupFormat::Git, self.t.get("Git"))
+                    .on_hover_text(self.t.get(
+                        "Commit the monitored files into a git repository each time, suitable for text configs, requires git",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.add_sized(label_size, Label::new(self.t.get("Screenshot Folder")));
+                TextEdit::singleline(&mut self.form.save_item.screenshot_folder)
+                    .hint_text(self.t.get("Empty to use the Steam screenshots"))
+                    .ui(ui);
+                if style::btn_primary_round(self.t.get("Select"), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    if let Some(path) = FileDialog::new().pick_folder() {
+                        self.form.save_item.screenshot_folder = path.display().to_string();
+                    }
+                }
+            });
+            self.classify_form(ui, label_size);
+            self.steam_form(ui, label_size);
+            self.hooks_form(ui, label_size);
+            self.notify_form(ui);
 
             ui.separator();
 
@@ -378,8 +500,12 @@ impl AutoBackup {
                                         return;
                                     }
                                     self.form.save_item.clone().save(self);
-                                    if let Ok(mut active) = self.active.try_lock() {
-                                        *active = self.form.save_item.clone();
+                                    // 表单中的备份和运行记录可能已经过期，以保存后的数据为准
+                                    let saved = self.data.try_lock().ok().and_then(|data| {
+                                        data.find(&self.form.save_item.id).cloned()
+                                    });
+                                    if let (Some(saved), Ok(mut active)) = (saved, self.active.try_lock()) {
+                                        *active = saved;
                                     }
                                     self.form.new = true;
                                     self.control.nav = Navigation::Manage;
@@ -406,503 +532,2717 @@ impl AutoBackup {
     pub fn archive_view(&mut self, _ctx: &Context, ui: &mut Ui) {
         let theme = &self.setting.get_theme();
 
-        TextEdit::singleline(&mut self.control.save_item_filter)
-            .hint_text(self.t.get("Filter by Name or Descirption"))
-            .ui(ui);
+        let items: Vec<SaveItem> = match self.data.try_lock() {
+            Ok(data) => {
+                let archived: Vec<&SaveItem> = data
+                    .monitors
+                    .iter()
+                    .filter(|item| item.status == Status::Archive)
+                    .collect();
+                filter_bar(ui, &self.t, &mut self.control.save_item_filter, &archived);
+                self.control
+                    .save_item_filter
+                    .apply(archived)
+                    .into_iter()
+                    .cloned()
+                    .collect()
+            }
+            Err(_) => return,
+        };
 
-        let filter_text = &self.control.save_item_filter.to_lowercase();
         ScrollArea::vertical().show(ui, |ui| {
             let mut save = (false, SaveItem::default());
-            if let Ok(mut data) = self.data.try_lock() {
-                for item in &mut data.monitors {
-                    if item.status != Status::Archive {
-                        continue;
-                    }
+            for mut item in items {
+                style::shadow_frame(theme).show(ui, |ui| {
+                    ui.strong(item.name.to_string())
+                        .on_hover_text(item.description.to_string());
 
-                    if !item.name.to_lowercase().contains(filter_text)
-                        && !item.description.to_lowercase().contains(filter_text)
-                    {
-                        continue;
-                    }
+                    ui.label(format!("{}: {}", self.t.get("Backup"), item.backups.len()));
+                    item_labels(ui, &self.t, &item);
+
+                    ui.horizontal(|ui| {
+                        if style::btn_success_round(self.t.get("Reactivate"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            item.status = Status::Valid;
+                            save.0 = true;
+                            save.1 = item.clone();
+                        }
+                        let totally_del_btn =
+                            style::btn_danger_round(self.t.get("Totally Delete"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand);
+                        if totally_del_btn.clicked() {
+                            self.control.show_confirm_totally_del = ShowConfirmTotoallyDel::new(
+                                item.clone(),
+                                totally_del_btn.interact_pointer_pos(),
+                            );
+                            self.control.show_confirm_totally_del.show();
+                        }
+                    });
+                });
+            }
+            if save.0 {
+                save.1.save(self);
+            }
+        });
+    }
 
-                    style::shadow_frame(theme).show(ui, |ui| {
-                        ui.strong(item.name.to_string())
-                            .on_hover_text(item.description.to_string());
+    /// 全局搜索，结果和命令行的 search 相同
+    pub fn search_view(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        ui.horizontal(|ui| {
+            let input =
+                TextEdit::singleline(&mut self.control.search)
+                    .hint_text(self.t.get(
+                        "Search save items, backup titles, remarks, notes, dates and file names",
+                    ))
+                    .desired_width(360.0)
+                    .ui(ui);
+            let enter = input.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            if (enter
+                || style::btn_primary_round(self.t.get("Search"), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked())
+                && !self.control.search.trim().is_empty()
+            {
+                let query = self.control.search.to_string();
+                self.request(
+                    "search",
+                    |reply| Command::Search { query, reply },
+                    |app, result| {
+                        let hits = result.and_then(|value| {
+                            serde_json::from_value(value).map_err(|e| e.to_string())
+                        });
+                        app.control.search_hits = Some(hits);
+                    },
+                );
+            }
+        });
+        ui.separator();
 
-                        ui.label(format!("{}: {}", self.t.get("Backup"), item.backups.len()));
+        let hits = match &self.control.search_hits {
+            None => return,
+            Some(Err(e)) => {
+                ui.colored_label(Color32::RED, e.as_str());
+                return;
+            }
+            Some(Ok(hits)) if hits.is_empty() => {
+                ui.label(self.t.get("No results"));
+                return;
+            }
+            Some(Ok(hits)) => hits.clone(),
+        };
+        ui.label(format!("{}: {}", self.t.get("Results"), hits.len()));
 
-                        ui.horizontal(|ui| {
-                            if style::btn_success_round(self.t.get("Reactivate"), theme)
+        let mut jump = None;
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::auto().at_least(120.0))
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto().at_least(80.0))
+            .column(Column::auto().clip(true).at_least(240.0))
+            .column(Column::auto())
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.strong(self.t.get("Name"));
+                });
+                header.col(|ui| {
+                    ui.strong(self.t.get("Bakup Time"));
+                });
+                header.col(|ui| {
+                    ui.strong(self.t.get("Field"));
+                });
+                header.col(|ui| {
+                    ui.strong(self.t.get("Content"));
+                });
+                header.col(|ui| {
+                    ui.strong(self.t.get("Operate"));
+                });
+            })
+            .body(|mut body| {
+                for hit in &hits {
+                    body.row(18.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(hit.item_name.to_string());
+                        });
+                        row.col(|ui| {
+                            let time = hit
+                                .backup_id
+                                .as_ref()
+                                .and(hit.time)
+                                .and_then(util::format_time)
+                                .unwrap_or_default();
+                            ui.label(time);
+                        });
+                        row.col(|ui| {
+                            ui.label(self.t.get(&hit.field));
+                        });
+                        row.col(|ui| {
+                            ui.label(hit.text.lines().next().unwrap_or_default())
+                                .on_hover_text(hit.text.to_string());
+                        });
+                        row.col(|ui| {
+                            if style::btn_info(self.t.get("Open"), theme)
                                 .ui(ui)
                                 .on_hover_cursor(CursorIcon::PointingHand)
                                 .clicked()
                             {
-                                item.status = Status::Valid;
-                                save.0 = true;
-                                save.1 = item.clone();
-                            }
-                            let totally_del_btn =
-                                style::btn_danger_round(self.t.get("Totally Delete"), theme)
-                                    .ui(ui)
-                                    .on_hover_cursor(CursorIcon::PointingHand);
-                            if totally_del_btn.clicked() {
-                                self.control.show_confirm_totally_del = ShowConfirmTotoallyDel::new(
-                                    item.clone(),
-                                    totally_del_btn.interact_pointer_pos(),
-                                );
-                                self.control.show_confirm_totally_del.show();
+                                jump = Some(hit.clone());
                             }
                         });
                     });
                 }
+            });
+
+        if let Some(hit) = jump {
+            self.jump_to(&hit);
+        }
+    }
+
+    /// 打开搜索结果所在的存档，并在备份列表中定位到匹配的备份
+    fn jump_to(&mut self, hit: &Hit) {
+        self.jump(&hit.item_id, hit.backup_id.as_deref().unwrap_or_default());
+    }
+
+    /// 跳转到备份管理中的存档，backup_id 不为空时高亮并滚动到该备份
+    fn jump(&mut self, item_id: &str, backup_id: &str) {
+        let item = match self.data.try_lock() {
+            Ok(data) => data.find(item_id).cloned(),
+            Err(_) => return,
+        };
+        let Some(item) = item else {
+            self.toasts
+                .error(format!("save item not found: {}", item_id));
+            return;
+        };
+        if let Ok(mut active) = self.active.try_lock() {
+            *active = item.clone();
+        }
+        self.control.manage = item.name.to_string();
+        self.control.backup_filter.clear();
+        self.control.jump_backup = backup_id.to_string();
+        self.control.jump_scroll = !self.control.jump_backup.is_empty();
+        self.control.nav = Navigation::Manage;
+    }
+
+    pub fn timeline_view(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        let (lanes, items) = match self.data.try_lock() {
+            Ok(data) => (
+                timeline::lanes(&data, &self.control.timeline_item),
+                data.monitors
+                    .iter()
+                    .filter(|item| item.status == Status::Valid)
+                    .map(|item| (item.id.to_string(), item.name.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
+            Err(_) => return,
+        };
+        let color = |kind| match kind {
+            PointKind::Manual => style::primary_color(theme),
+            PointKind::Auto => style::success_color(theme),
+            PointKind::PreRestore => style::waring_color(theme),
+            PointKind::Failed => style::danger_color(theme),
+        };
+
+        ui.horizontal(|ui| {
+            let selected = items
+                .iter()
+                .find(|(id, _)| *id == self.control.timeline_item)
+                .map(|(_, name)| name.as_str())
+                .unwrap_or(self.t.get("All"));
+            ComboBox::from_id_source("timeline_item")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.control.timeline_item,
+                        String::new(),
+                        self.t.get("All"),
+                    );
+                    for (id, name) in &items {
+                        ui.selectable_value(&mut self.control.timeline_item, id.to_string(), name);
+                    }
+                });
+            for (days, label) in [
+                (7, "7 Days"),
+                (30, "30 Days"),
+                (90, "90 Days"),
+                (365, "1 Year"),
+                (0, "All"),
+            ] {
+                ui.selectable_value(&mut self.control.timeline_days, days, self.t.get(label));
             }
-            if save.0 {
-                save.1.save(self);
+            ui.separator();
+            for kind in PointKind::ALL {
+                ui.colored_label(color(kind), format!("■ {}", self.t.get(kind.name())));
             }
         });
-    }
+        ui.separator();
 
-    pub fn setting_view(&mut self, ctx: &Context, ui: &mut Ui) {
-        CentralPanel::default().show_inside(ui, |ui| {
-            ui.vertical(|ui| {
+        let now = Local::now().timestamp_millis();
+        let Some((start, end)) = timeline::range(&lanes, self.control.timeline_days, now) else {
+            ui.label(self.t.get("No backups"));
+            return;
+        };
+        let max_size = lanes
+            .iter()
+            .flat_map(|lane| &lane.points)
+            .map(|point| point.size)
+            .max()
+            .unwrap_or_default()
+            .max(1);
+        // 单个存档时画得高一些
+        let lane_height = if lanes.len() == 1 { 200.0 } else { 48.0 };
+        let label_size = Vec2::new(140.0, lane_height);
+        let x_of = |rect: Rect, time: i64| {
+            rect.left() + (time - start) as f32 / (end - start) as f32 * rect.width()
+        };
+
+        let mut jump = None;
+        ScrollArea::vertical().show(ui, |ui| {
+            for lane in &lanes {
                 ui.horizontal(|ui| {
-                    ui.label(self.t.get("Theme"));
                     if ui
-                        .selectable_value(&mut self.setting.theme, Theme::Dark, self.t.get("Dark"))
-                        .changed()
-                        || ui
-                            .selectable_value(
-                                &mut self.setting.theme,
-                                Theme::Light,
-                                self.t.get("Light"),
-                            )
-                            .changed()
+                        .add_sized(
+                            label_size,
+                            Label::new(&lane.item_name)
+                                .truncate(true)
+                                .sense(Sense::click()),
+                        )
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
                     {
-                        ctx.set_visuals(self.setting.theme.visuals());
-                        self.setting.save();
+                        self.control.timeline_item = lane.item_id.to_string();
+                    }
+                    let size = Vec2::new(ui.available_width(), lane_height);
+                    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+                    let painter = ui.painter_at(rect);
+                    let axis = ui.visuals().weak_text_color();
+                    painter.line_segment(
+                        [rect.left_bottom(), rect.right_bottom()],
+                        Stroke::new(1.0, axis),
+                    );
+                    let visible: Vec<&Point> = lane
+                        .points
+                        .iter()
+                        .filter(|point| point.time >= start && point.time <= end)
+                        .collect();
+                    for point in &visible {
+                        let x = x_of(rect, point.time);
+                        let color = color(point.kind);
+                        if point.kind == PointKind::Failed {
+                            painter.circle_filled(Pos2::new(x, rect.bottom() - 4.0), 4.0, color);
+                            continue;
+                        }
+                        let height =
+                            (point.size as f32 / max_size as f32 * (lane_height - 6.0)).max(3.0);
+                        painter.rect_filled(
+                            Rect::from_min_max(
+                                Pos2::new(x - 1.5, rect.bottom() - height),
+                                Pos2::new(x + 1.5, rect.bottom()),
+                            ),
+                            0.0,
+                            color,
+                        );
                     }
-                });
 
-                ui.horizontal(|ui| {
-                    ui.label(self.t.get("Language"));
-                    if ui
-                        .selectable_value(&mut self.setting.lang, Lang::En, "English")
-                        .changed()
-                        || ui
-                            .selectable_value(&mut self.setting.lang, Lang::Cn, "简体中文")
-                            .changed()
-                    {
-                        self.t.change(&self.setting.lang);
-                        self.setting.save();
+                    // 悬浮和点击时取横向最近的点
+                    let nearest = response.hover_pos().and_then(|pos| {
+                        visible
+                            .iter()
+                            .map(|point| (point, (x_of(rect, point.time) - pos.x).abs()))
+                            .filter(|(_, distance)| *distance <= 6.0)
+                            .min_by(|a, b| a.1.total_cmp(&b.1))
+                            .map(|(point, _)| *point)
+                    });
+                    if let Some(point) = nearest {
+                        let time = util::format_time(point.time).unwrap_or_default();
+                        let mut text = format!("{}\n{}", time, self.t.get(point.kind.name()));
+                        if point.kind != PointKind::Failed {
+                            text.push_str(&format!("  {}", util::format_size(point.size)));
+                        }
+                        if !point.text.is_empty() {
+                            text.push_str(&format!("\n{}", point.text));
+                        }
+                        let response = response.on_hover_cursor(CursorIcon::PointingHand);
+                        if response.clicked() {
+                            jump = Some((
+                                lane.item_id.to_string(),
+                                point.backup_id.clone().unwrap_or_default(),
+                            ));
+                        }
+                        response.on_hover_text_at_pointer(text);
                     }
                 });
-            })
-        });
-    }
+            }
 
-    pub fn package_toasts(&mut self, results: Vec<(ToastLevel, String)>) {
-        for (level, msg) in results {
-            let msg = self.t.get(&msg);
-            match level {
-                ToastLevel::Info => {
-                    self.toasts.info(msg);
-                }
-                ToastLevel::Warning => {
-                    self.toasts.warning(msg);
-                }
-                ToastLevel::Error => {
-                    self.toasts.error(msg);
-                }
-                ToastLevel::Success => {
-                    self.toasts.success(msg);
+            // 时间刻度
+            ui.horizontal(|ui| {
+                ui.add_sized(Vec2::new(label_size.x, 16.0), Label::new(""));
+                let (rect, _) =
+                    ui.allocate_exact_size(Vec2::new(ui.available_width(), 16.0), Sense::hover());
+                let painter = ui.painter_at(rect);
+                let ticks = 6;
+                for i in 0..=ticks {
+                    let time = start + (end - start) * i / ticks;
+                    let align = match i {
+                        0 => Align2::LEFT_TOP,
+                        i if i == ticks => Align2::RIGHT_TOP,
+                        _ => Align2::CENTER_TOP,
+                    };
+                    let text = chrono::DateTime::from_timestamp_millis(time)
+                        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+                        .unwrap_or_default();
+                    painter.text(
+                        Pos2::new(x_of(rect, time), rect.top()),
+                        align,
+                        text,
+                        FontId::proportional(11.0),
+                        ui.visuals().weak_text_color(),
+                    );
                 }
-                _ => {}
-            }
+            });
+        });
+        if let Some((item_id, backup_id)) = jump {
+            self.jump(&item_id, &backup_id);
         }
     }
 
-    pub fn manage_list(&mut self, ui: &mut eframe::egui::Ui) {
-        if let Ok(active) = self.active.try_lock() {
-            if active.id.is_empty() {
-                return;
-            }
-        }
-
+    pub fn storage_view(&mut self, ui: &mut Ui) {
         let theme = &self.setting.get_theme();
+        let report = match self.data.try_lock() {
+            Ok(data) => storage::report(&data),
+            Err(_) => return,
+        };
 
-        CentralPanel::default().show_inside(ui, |ui| {
-            ui.vertical(|ui| {
-                style::shadow_frame(theme).show(ui, |ui| {
-
-                    if let Ok(ref mut active) = self.active.try_lock() {
-                        ui.horizontal(|ui| {
-                            ui.label(self.t.get("Name"));
-                            ui.label(active.name.to_string());
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label(self.t.get("Description"));
-                            Label::new(active.description.to_string()).wrap(true).ui(ui);
-                        });
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{}: {}",
+                self.t.get("Total Size"),
+                util::format_size(report.size)
+            ));
+            ui.separator();
+            ui.label(format!("{}: {}", self.t.get("Files"), report.files));
+            ui.separator();
+            ui.label(format!("{}: {}", self.t.get("Backup"), report.backups));
+            ui.separator();
+            ui.label(format!(
+                "{}: {}",
+                self.t.get("Reclaimable"),
+                util::format_size(report.reclaimable)
+            ))
+            .on_hover_text(self.t.get("Space the retention policies would reclaim"));
+        });
+        ui.separator();
 
-                        ui.horizontal(|ui| {
-                            ui.label(self.t.get("Remark"));
-                            TextEdit::singleline(&mut self.control.new_remark)
-                            .hint_text(self.t.get("Please enter comments for the new backup"))
-                            .ui(ui);
+        ScrollArea::vertical().show(ui, |ui| {
+            ui.strong(self.t.get("Save Item"));
+            let mut changed = None;
+            let mut prune = None;
+            ui.push_id("storage_items", |ui| {
+            TableBuilder::new(ui)
+                .striped(true)
+                .resizable(true)
+                .vscroll(false)
+                .cell_layout(Layout::left_to_right(Align::Center))
+                .column(Column::auto().clip(true).at_least(140.0))
+                .column(Column::auto())
+                .column(Column::auto().at_least(80.0))
+                .column(Column::auto())
+                .column(Column::auto())
+                .column(Column::auto())
+                .column(Column::auto().at_least(80.0))
+                .column(Column::auto())
+                .header(20.0, |mut header| {
+                    for title in [
+                        "Name",
+                        "Backup",
+                        "Size",
+                        "Files",
+                        "Keep Last",
+                        "Keep Days",
+                        "Reclaimable",
+                        "Operate",
+                    ] {
+                        header.col(|ui| {
+                            ui.strong(self.t.get(title));
                         });
                     }
-
-                    ui.horizontal(|ui| {
-                        if style::btn_primary_round(self.t.get("Backup Now"), theme)
-                            .ui(ui)
-                            .on_hover_cursor(CursorIcon::PointingHand)
-                            .on_hover_text(self.t.get("If the file is too large, there may be stutters, please wait patiently"))
-                            .clicked()
-                        {
-                            if self.control.debounce {
-                                self.toasts.warning(self.t.get("Operation is too fast. Try again later."));
-                            } else {
-                                self.control.debounce = true;
-                                let mut cl = SaveItem::default();
-                                let mut msg = vec![];
-                                if let Ok(mut active) = self.active.try_lock() {
-                                    let (msgs, new_backup) = Backup::run(
-                                    active.clone(),
-                                    self.control.new_remark.to_string(),
-                                    );
-                                    active.backups.insert(0, new_backup);
-                                    cl = active.clone();
-                                    msg = msgs;
-                                }
-                                cl.save(self);
-                                self.package_toasts(msg);
-                                self.control.debounce = false;
-                            }
-                        }
-
-                        if let Ok(active) = self.active.try_lock() {
-                            if !active.startup_path.is_empty() {
-                                if style::btn_success_round(self.t.get("Startup"), theme)
+                })
+                .body(|mut body| {
+                    for item in &report.items {
+                        body.row(20.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(item.name.to_string());
+                            });
+                            row.col(|ui| {
+                                ui.label(item.backups.to_string());
+                            });
+                            row.col(|ui| {
+                                ui.label(util::format_size(item.size));
+                            });
+                            row.col(|ui| {
+                                ui.label(item.files.to_string());
+                            });
+                            let mut retention = item.retention.clone();
+                            row.col(|ui| {
+                                if DragValue::new(&mut retention.keep_last)
+                                    .clamp_range(0..=1000)
                                     .ui(ui)
-                                    .on_hover_cursor(CursorIcon::PointingHand)
-                                    .on_hover_text(active.startup_path.to_string())
-                                    .clicked()
+                                    .on_hover_text(self.t.get("0 means unlimited"))
+                                    .changed()
                                 {
-                                    let _ = open::that(active.startup_path.to_string());
-                                }
-                            }
-
-                            if style::btn_primary_round(self.t.get("Open Backup Folder"), theme)
-                                .ui(ui)
-                                .on_hover_cursor(CursorIcon::PointingHand)
-                                .clicked()
-                            {
-                                let folder =
-                                    format!("{}/data/backup/{}", util::current_dir(), active.id);
-                                let _ = open::that(folder);
-                            }
-                            if style::btn_primary_round(self.t.get("Check Monitor"), theme)
-                                .ui(ui)
-                                .on_hover_cursor(CursorIcon::PointingHand)
-                                .clicked()
-                            {
-                                self.control.show_check_monitor = active.id.to_string();
-                            }
-                            if style::btn_primary_round(self.t.get("Edit"), theme)
-                                .ui(ui)
-                                .on_hover_cursor(CursorIcon::PointingHand)
-                                .clicked()
-                            {
-                                self.form.save_item = active.clone();
-                                self.form.new = false;
-                                self.form.open = true;
-                                self.control.nav = Navigation::Edit;
-                            }
-                        }
-
-                        if style::btn_waring_round(self.t.get("Archive"), theme)
-                        .ui(ui)
-                        .on_hover_ui(|ui| {
-                            ui.label(self.t.get("When Archived"));
-                            ui.label(self.t.get("Will move to archived tab"));
-                            ui.label(self.t.get("and Stop the current auto backup task"));
-                        })
-                        .on_hover_cursor(CursorIcon::PointingHand)
-                        .clicked()
-                        {
-                            let mut save = SaveItem::default();
-                            if let Ok(mut active) = self.active.try_lock() {
-                                active.status = Status::Archive;
-                                active.auto.status = AutoStatus::Stop;                        save = active.clone();
-                                *active = SaveItem::default();
-                            }
-                            save.save(self);
-                            let _ = self.command.clone().unwrap().send(Command::StartJob);
-                            self.toasts.success(self.t.get("Auto Backup Already Restart"));
-                        }
-                    });
-
-                    let mut save = false;
-                    let mut toggle_auto = false;
-                    ui.horizontal(|ui| {
-                        if let Ok(mut active) = self.active.try_lock() {
-                            // let auto = &mut active.auto;
-                        if ui
-                            .checkbox(&mut active.auto.open, self.t.get("Use Auto Backup"))
-                            .on_hover_cursor(CursorIcon::PointingHand)
-                            .clicked()
-                        {
-                            if !active.auto.open {
-                                active.auto.status = AutoStatus::Stop;
-                            }
-                            // active.clone().save(self);
-                            save = true;
-                        }
-
-                        if active.auto.open {
-                            ui.label(self.t.get("Cron"));
-
-                            let cron = ui.text_edit_singleline(&mut active.auto.cron);
-                            if cron.changed() {
-                                active.auto.status = AutoStatus::Stop;
-                                // active.clone().save(self);
-                                save = true;
-                                let _ = self.command.clone().unwrap().send(Command::StartJob);
-                                self.toasts.success(self.t.get("Auto Backup Already Restart"));
-                            }
-                            let mut parse_time = job::parse_time(&active.auto.cron);
-                            if cron.hovered() {
-                                if parse_time.is_empty() {
-                                    parse_time = vec![self.t.get("Format Invalid").to_string()];
-                                }
-                                let tips = format!(
-                                    "{}\nsec min hour day_of_month month day_of_week year\n{}\n{}",
-                                    self.t.get("Format"),
-                                    self.t.get("Next Start Time"),
-                                    parse_time.join("\n")
-                                );
-                                cron.on_hover_text(tips);
-                            }
-
-                            let color = match active.auto.status {
-                                AutoStatus::Running => {
-                                    style::success_color(&self.setting.get_theme())
+                                    changed = Some((item.id.to_string(), retention.clone()));
                                 }
-                                AutoStatus::Stop => style::danger_color(&self.setting.get_theme()),
-                            };
-                            ui.colored_label(color, self.t.get(&active.auto.status.to_string()));
-
-                            match active.auto.status {
-                                AutoStatus::Stop => {
-                                    if style::btn_success(self.t.get("Startup"), theme)
-                                        .ui(ui)
-                                        .on_hover_cursor(CursorIcon::PointingHand)
-                                        .clicked() 
-                                    {
-                                        if parse_time.is_empty() {
-                                            self.toasts.warning(self.t.get("Cron format invalid"));
-                                        } else {
-                                            active.auto.status = AutoStatus::Running;
-                                        }
-                                        // active.clone().save(self);
-                                        save = true;
-                                        toggle_auto = true;
-                                    }
+                            });
+                            row.col(|ui| {
+                                if DragValue::new(&mut retention.keep_days)
+                                    .clamp_range(0..=3650)
+                                    .ui(ui)
+                                    .on_hover_text(self.t.get("0 means unlimited"))
+                                    .changed()
+                                {
+                                    changed = Some((item.id.to_string(), retention.clone()));
                                 }
-                                AutoStatus::Running => {
-                                    if style::btn_info(self.t.get("Stop"), theme)
+                            });
+                            row.col(|ui| {
+                                ui.label(format!(
+                                    "{} ({})",
+                                    util::format_size(item.reclaimable),
+                                    item.reclaimable_backups
+                                ));
+                            });
+                            row.col(|ui| {
+                                if item.reclaimable_backups > 0
+                                    && style::btn_waring(self.t.get("Apply Retention"), theme)
                                         .ui(ui)
+                                        .on_hover_text(self.t.get(
+                                            "Move the backups beyond the retention policy to the trash",
+                                        ))
                                         .on_hover_cursor(CursorIcon::PointingHand)
                                         .clicked()
-                                    {
-                                        active.auto.status = AutoStatus::Stop;
-                                        // active.clone().save(self);
-                                        save = true;
-                                        toggle_auto = true;
-                                    }
+                                {
+                                    prune = Some(item.id.to_string());
                                 }
-                            }
+                            });
+                        });
+                    }
+                });
+            });
+            if let Some((id, retention)) = changed {
+                let mut save = None;
+                if let Ok(mut data) = self.data.try_lock() {
+                    if let Some(item) = data.find_mut(&id) {
+                        item.retention = retention;
+                        save = Some(item.clone());
+                    }
+                }
+                if let Some(item) = save {
+                    if let Ok(mut active) = self.active.try_lock() {
+                        if active.id == item.id {
+                            active.retention = item.retention.clone();
                         }
+                    }
+                    item.save(self);
+                }
+            }
+            if let Some(id) = prune {
+                self.request(
+                    "prune",
+                    |reply| Command::Prune {
+                        id,
+                        reply: Some(reply),
+                    },
+                    |app, result| match result {
+                        Ok(_) => {
+                            app.toasts.success(app.t.get("Moved to the trash"));
                         }
-                    });
-
-                    if save {
-                        let mut save = SaveItem::default();
-                        if let Ok(active) = self.active.try_lock() {
-                            save = active.clone();
+                        Err(e) => {
+                            app.toasts.error(e);
                         }
-                        save.save(self);
-                    }
-                    if toggle_auto {
-                        let _ = self.command.clone().unwrap().send(Command::StartJob);
-                        self.toasts.success(self.t.get("Auto Backup Already Restart"));
-                    }
+                    },
+                );
+            }
 
-                    // ui.separator();
+            ui.separator();
+            ui.strong(self.t.get("Usage Over Time"));
+            let max = report.timeline.iter().map(|m| m.total).max().unwrap_or(0);
+            for month in &report.timeline {
+                ui.horizontal(|ui| {
+                    ui.label(month.month.to_string());
+                    let fraction = if max == 0 {
+                        0.0
+                    } else {
+                        month.total as f32 / max as f32
+                    };
+                    ProgressBar::new(fraction)
+                        .desired_width(300.0)
+                        .text(util::format_size(month.total))
+                        .ui(ui)
+                        .on_hover_text(format!(
+                            "{}: +{}",
+                            self.t.get("Added"),
+                            util::format_size(month.added)
+                        ));
                 });
-                style::shadow_frame(theme).show(ui, |ui| {
-                    ScrollArea::vertical().show(ui, |ui| {
-                        self.backup_list(ui);
-                    });
+            }
+
+            ui.separator();
+            ui.strong(self.t.get("Largest Backups"));
+            ui.push_id("storage_largest", |ui| {
+            TableBuilder::new(ui)
+                .striped(true)
+                .resizable(true)
+                .vscroll(false)
+                .cell_layout(Layout::left_to_right(Align::Center))
+                .column(Column::auto().clip(true).at_least(140.0))
+                .column(Column::auto().at_least(130.0))
+                .column(Column::auto().clip(true).at_least(160.0))
+                .column(Column::auto().at_least(80.0))
+                .column(Column::auto())
+                .header(20.0, |mut header| {
+                    for title in ["Name", "Bakup Time", "Remark", "Size", "Files"] {
+                        header.col(|ui| {
+                            ui.strong(self.t.get(title));
+                        });
+                    }
+                })
+                .body(|mut body| {
+                    for backup in &report.largest {
+                        body.row(20.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(backup.item_name.to_string());
+                            });
+                            row.col(|ui| {
+                                ui.label(util::format_time(backup.time).unwrap_or_default());
+                            });
+                            row.col(|ui| {
+                                ui.label(backup.remark.to_string());
+                            });
+                            row.col(|ui| {
+                                ui.label(util::format_size(backup.size));
+                            });
+                            row.col(|ui| {
+                                ui.label(backup.files.to_string());
+                            });
+                        });
+                    }
                 });
             });
         });
     }
 
-    pub fn backup_list(&mut self, ui: &mut Ui) {
+    pub fn trash_view(&mut self, ui: &mut Ui) {
         let theme = &self.setting.get_theme();
+        if self.control.trash.is_none() {
+            self.control.trash = Some(trash::list());
+        }
+        let days = self.setting.trash.purge_days;
 
-        let mut backups = vec![];
-        if let Ok(active) = self.active.try_lock() {
-            backups = active.backups.to_vec();
+        ui.horizontal(|ui| {
+            ui.label(self.t.get("Keep deleted items for"));
+            if DragValue::new(&mut self.setting.trash.purge_days)
+                .clamp_range(0..=3650)
+                .ui(ui)
+                .on_hover_text(self.t.get("0 means keep forever"))
+                .changed()
+            {
+                self.setting.save();
+            }
+            ui.label(self.t.get("Days"));
+            if style::btn_primary_round(self.t.get("Refresh"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                self.control.trash = None;
+            }
+        });
+        ui.separator();
+
+        let entries = self.control.trash.clone().unwrap_or_default();
+        if entries.is_empty() {
+            ui.label(self.t.get("Trash is empty"));
+            return;
         }
 
-        // let available_height = ui.available_height();
-        let table = TableBuilder::new(ui)
+        TableBuilder::new(ui)
             .striped(true)
             .resizable(true)
             .cell_layout(Layout::left_to_right(Align::Center))
             .column(Column::auto())
-            .column(Column::auto().at_least(130.0))
+            .column(Column::auto().at_least(120.0))
             .column(Column::auto().clip(true).at_least(160.0))
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto().at_least(130.0))
             .column(Column::auto())
-            .column(Column::auto())
-            // .min_scrolled_height(0.0)
-            // .max_scroll_height(available_height)
-            .sense(Sense::click());
-
-        table
             .header(20.0, |mut header| {
                 header.col(|ui| {
-                    ui.strong(self.t.get("Row"));
+                    ui.strong(self.t.get("Type"));
                 });
                 header.col(|ui| {
-                    ui.strong(self.t.get("Bakup Time"));
+                    ui.strong(self.t.get("Name"));
                 });
                 header.col(|ui| {
                     ui.strong(self.t.get("Remark"));
-                    ui.text_edit_singleline(&mut self.control.backup_filter);
                 });
                 header.col(|ui| {
-                    ui.strong(self.t.get("Protect"));
+                    ui.strong(self.t.get("Deleted At"));
+                });
+                header.col(|ui| {
+                    ui.strong(self.t.get("Purge At"));
                 });
                 header.col(|ui| {
                     ui.strong(self.t.get("Operate"));
                 });
             })
             .body(|mut body| {
-                // if let Ok( active) = self.active.try_lock() {
-
-                for (index, item) in backups.iter().enumerate() {
-                    let filter = &self.control.backup_filter;
-                    if !filter.is_empty() && !item.remark.contains(filter) {
-                        continue;
-                    }
-                    body.row(18.0, |mut row| {
-                        let mut protect = item.protect;
-                        row.set_selected(protect);
+                for entry in entries.iter() {
+                    body.row(20.0, |mut row| {
                         row.col(|ui| {
-                            ui.label((index + 1).to_string());
+                            ui.label(self.t.get(&entry.kind.to_string()));
                         });
-                        row.col(|ui: &mut Ui| {
-                            let time = if let Some(time) =
-                                chrono::DateTime::from_timestamp_millis(item.time)
-                            {
-                                let fmt = "%Y-%m-%d %H:%M:%S";
-                                format!("{}", time.with_timezone(&Local).format(fmt))
-                            } else {
-                                self.t.get("Unknow").to_string()
+                        row.col(|ui| {
+                            ui.label(entry.item_name.to_string());
+                        });
+                        row.col(|ui| {
+                            let remark = match &entry.backup {
+                                Some(backup) => backup.remark.to_string(),
+                                None => String::new(),
                             };
-                            ui.label(time);
+                            ui.label(remark.to_string()).on_hover_text(remark);
                         });
                         row.col(|ui| {
-                            ui.label(item.remark.to_string())
-                                .on_hover_text(item.remark.clone());
+                            ui.label(
+                                util::format_time(entry.deleted_at)
+                                    .unwrap_or(self.t.get("Unknow").to_string()),
+                            );
                         });
                         row.col(|ui| {
-                            if ui.checkbox(&mut protect, "").clicked() {
-                                let _ =
-                                    self.command.clone().unwrap().send(Command::ProtectBackup {
-                                        id: item.id.to_string(),
-                                        protect,
-                                    });
-                            }
+                            let purge_at = match entry.expire_at(days) {
+                                Some(time) => util::format_time(time)
+                                    .unwrap_or(self.t.get("Unknow").to_string()),
+                                None => self.t.get("Never").to_string(),
+                            };
+                            ui.label(purge_at);
                         });
                         row.col(|ui| {
-                            let overwrite_btn = style::btn_waring(self.t.get("Overwrite"), theme)
-                                .ui(ui)
-                                .on_hover_cursor(CursorIcon::PointingHand);
-                            if overwrite_btn.clicked() {
-                                self.control.show_confirm_overwrite_backup =
-                                    ShowConfirmOverwriteBackup::new(
-                                        // item.id.to_string(),
-                                        item.datas.to_vec(),
-                                        overwrite_btn.interact_pointer_pos(),
-                                    );
-                                self.control.show_confirm_overwrite_backup.show();
-                            }
-
-                            if style::btn_info(self.t.get("Remark"), theme)
-                                .ui(ui)
-                                .on_hover_cursor(CursorIcon::PointingHand)
-                                .clicked()
-                            {
-                                if self.control.show_backup_remark == index as isize {
-                                    self.control.show_backup_remark = -1;
-                                } else {
-                                    self.control.show_backup_remark = index as isize;
-                                }
-                            }
-
-                            if style::btn_primary(self.t.get("Open"), theme)
+                            if style::btn_success(self.t.get("Restore"), theme)
                                 .ui(ui)
                                 .on_hover_cursor(CursorIcon::PointingHand)
                                 .clicked()
                             {
-                                let _ = open::that(&item.backup_folder);
+                                let id = entry.id.to_string();
+                                self.request(
+                                    "restore_trash",
+                                    |reply| Command::RestoreTrash {
+                                        id,
+                                        reply: Some(reply),
+                                    },
+                                    |app, result| {
+                                        match result {
+                                            Ok(_) => {
+                                                app.toasts.success(app.t.get("Restore Success"));
+                                            }
+                                            Err(e) => {
+                                                app.toasts.error(e);
+                                            }
+                                        }
+                                        app.control.trash = None;
+                                    },
+                                );
                             }
-
-                            let delete_backup_btn = style::btn_danger(self.t.get("Delete"), theme)
-                                .ui(ui)
-                                .on_hover_cursor(CursorIcon::PointingHand);
-                            if delete_backup_btn.clicked() {
-                                if protect {
-                                    self.toasts
-                                        .warning(self.t.get("Please cancel the protection first"));
-                                } else if !self.control.show_confirm_del_backup.is_show() {
-                                    self.control.show_confirm_del_backup =
-                                        ShowConfirmDelBackup::new(
-                                            item.id.to_string(),
-                                            delete_backup_btn.interact_pointer_pos(),
-                                        );
-                                    self.control.show_confirm_del_backup.show();
-                                } else {
-                                    self.toasts
-                                        .warning(self.t.get("Please confirm the operation first"));
-                                }
+                            let purge_btn =
+                                style::btn_danger(self.t.get("Delete Permanently"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand);
+                            if purge_btn.clicked() {
+                                self.control.show_confirm_purge_trash = ShowConfirmPurgeTrash::new(
+                                    entry.id.to_string(),
+                                    purge_btn.interact_pointer_pos(),
+                                );
+                                self.control.show_confirm_purge_trash.show();
                             }
                         });
-
-                        // if row.response().clicked() {
-                        //     mutex_data.selected_index = index;
-                        // }
                     });
                 }
-
-                // }
             });
     }
 
-    fn save_item_args_error(&mut self) -> bool {
-        if self.form.save_item.name.is_empty() {
-            self.toasts.warning(self.t.get("Please input name"));
-            return true;
-        }
-        if self.form.save_item.monitors.is_empty() {
-            self.toasts
-                .warning(self.t.get("Please select monitor file"));
-            return true;
+    /// 日志页面，按级别、存档和关键字筛选
+    pub fn logs_view(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        let names: Vec<String> = match self.data.try_lock() {
+            Ok(data) => data
+                .monitors
+                .iter()
+                .map(|item| item.name.to_string())
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        let before = self.control.log_filter.clone();
+        ui.horizontal(|ui| {
+            let filter = &mut self.control.log_filter;
+            ui.label(self.t.get("Level"));
+            ComboBox::from_id_source("log_level")
+                .selected_text(self.t.get(&filter.level.to_string()))
+                .show_ui(ui, |ui| {
+                    for level in Level::ALL {
+                        ui.selectable_value(
+                            &mut filter.level,
+                            level,
+                            self.t.get(&level.to_string()),
+                        );
+                    }
+                });
+            ui.label(self.t.get("Save Item"));
+            let selected = if filter.item.is_empty() {
+                self.t.get("All").to_string()
+            } else {
+                filter.item.to_string()
+            };
+            ComboBox::from_id_source("log_item")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut filter.item, String::new(), self.t.get("All"));
+                    for name in &names {
+                        ui.selectable_value(&mut filter.item, name.to_string(), name);
+                    }
+                });
+            TextEdit::singleline(&mut filter.keyword)
+                .hint_text(self.t.get("Search message, backup and job"))
+                .ui(ui);
+            if style::btn_primary_round(self.t.get("Refresh"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                self.control.logs = None;
+            }
+            if style::btn_primary_round(self.t.get("Open Log Folder"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                let _ = open::that(log::log_folder());
+            }
+        });
+        if self.control.log_filter != before {
+            self.control.logs = None;
+        }
+        ui.separator();
+
+        if self.control.logs.is_none() && !self.is_pending("logs") {
+            let filter = self.control.log_filter.clone();
+            let query = filter.clone();
+            self.request(
+                "logs",
+                |reply| Command::Logs { filter, reply },
+                move |app, result| {
+                    // 等待期间修改了筛选条件时丢弃，下一帧重新读取
+                    if app.control.log_filter == query {
+                        let logs = result.and_then(|value| {
+                            serde_json::from_value(value).map_err(|e| e.to_string())
+                        });
+                        app.control.logs = Some(logs);
+                    }
+                },
+            );
+        }
+        let entries = match self.control.logs.clone() {
+            Some(Ok(entries)) => entries,
+            Some(Err(e)) => {
+                ui.colored_label(Color32::RED, e);
+                return;
+            }
+            None => return,
+        };
+        if entries.is_empty() {
+            ui.label(self.t.get("No logs"));
+            return;
+        }
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto())
+            .column(Column::auto().clip(true).at_least(100.0))
+            .column(Column::auto().clip(true).at_least(80.0))
+            .column(Column::auto().clip(true).at_least(80.0))
+            .column(Column::remainder().clip(true))
+            .header(20.0, |mut header| {
+                for title in ["Time", "Level", "Save Item", "Backup", "Job", "Message"] {
+                    header.col(|ui| {
+                        ui.strong(self.t.get(title));
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, entries.len(), |mut row| {
+                    let entry = &entries[row.index()];
+                    row.col(|ui| {
+                        ui.label(util::format_time(entry.time).unwrap_or_default());
+                    });
+                    row.col(|ui| {
+                        let color = match entry.level {
+                            Level::Debug => style::info_color(theme),
+                            Level::Info => style::success_color(theme),
+                            Level::Warn => style::waring_color(theme),
+                            Level::Error => style::danger_color(theme),
+                        };
+                        ui.colored_label(color, self.t.get(&entry.level.to_string()));
+                    });
+                    row.col(|ui| {
+                        ui.label(entry.item.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(&entry.backup[..entry.backup.len().min(8)])
+                            .on_hover_text(entry.backup.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(entry.job.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(entry.message.lines().next().unwrap_or_default())
+                            .on_hover_text(entry.message.to_string());
+                    });
+                });
+            });
+    }
+
+    fn new_backup_button(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        if style::btn_primary_round(self.t.get("New Backup"), theme)
+            .ui(ui)
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .clicked()
+        {
+            self.control.nav = Navigation::New;
+        }
+    }
+
+    fn import_button(&mut self, ui: &mut Ui) {
+        if self.is_pending("import") {
+            ui.spinner();
+            ui.label(self.t.get("Importing..."));
+            return;
+        }
+        let theme = &self.setting.get_theme();
+        if !style::btn_primary_round(self.t.get("Import"), theme)
+            .ui(ui)
+            .on_hover_text(self.t.get("Import a save item exported by AutoBackup"))
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .clicked()
+        {
+            return;
+        }
+        let Some(path) = FileDialog::new().add_filter("zip", &["zip"]).pick_file() else {
+            return;
+        };
+        let path = path.display().to_string();
+        let manifest = match bundle::read_manifest(&path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                self.toasts.error(e);
+                return;
+            }
+        };
+        // 导入时会再次检查冲突，这里拿不到锁时直接交给后台处理
+        let conflicts = match self.data.try_lock() {
+            Ok(data) => bundle::conflicts(&manifest.item, &data),
+            Err(_) => vec![],
+        };
+        if conflicts.is_empty() {
+            self.import(path, ImportMode::Strict);
+        } else {
+            self.control.show_confirm_import = ShowConfirmImport::new(path, manifest, conflicts);
+            self.control.show_confirm_import.show();
+        }
+    }
+
+    fn scan_games_button(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        if !style::btn_primary_round(self.t.get("Scan for Games"), theme)
+            .ui(ui)
+            .on_hover_text(self.t.get("Find installed games in the game database"))
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .clicked()
+        {
+            return;
+        }
+        self.request(
+            "scan_games",
+            |reply| Command::ScanGames { reply },
+            |app, result| match result
+                .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+            {
+                Ok(games) => {
+                    app.control.show_scan_games = ShowScanGames::new(games);
+                    app.control.show_scan_games.show();
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                }
+            },
+        );
+    }
+
+    pub fn import(&mut self, path: String, mode: ImportMode) {
+        self.request(
+            "import",
+            |reply| Command::Import {
+                path,
+                mode,
+                reply: Some(reply),
+            },
+            |app, result| match result {
+                Ok(_) => {
+                    app.toasts.success(app.t.get("Import Success"));
+                    let _ = app.command.clone().unwrap().send(Command::StartJob);
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                }
+            },
+        );
+    }
+
+    /// 把命令交给 command_proc 执行，不阻塞界面，回复后在 receive_replies 中调用 on_reply
+    ///
+    /// 同名的请求还在执行时提示稍后再试，返回 false
+    pub fn request(
+        &mut self,
+        name: &'static str,
+        cmd: impl FnOnce(Reply) -> Command,
+        on_reply: impl FnOnce(&mut AutoBackup, Result<Value, String>) + 'static,
+    ) -> bool {
+        if self.is_pending(name) {
+            self.toasts
+                .warning(self.t.get("Operation is too fast. Try again later."));
+            return false;
+        }
+        let (tx, rx) = std::sync::mpsc::channel();
+        if let Err(e) = self.command.clone().unwrap().send(cmd(tx)) {
+            on_reply(self, Err(e.to_string()));
+            return false;
+        }
+        self.control.pending.push(PendingRequest {
+            name,
+            rx,
+            on_reply: Box::new(on_reply),
+        });
+        true
+    }
+
+    pub fn is_pending(&self, name: &str) -> bool {
+        self.control
+            .pending
+            .iter()
+            .any(|request| request.name == name)
+    }
+
+    pub fn setting_view(&mut self, ctx: &Context, ui: &mut Ui) {
+        CentralPanel::default().show_inside(ui, |ui| {
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label(self.t.get("Theme"));
+                    if ui
+                        .selectable_value(&mut self.setting.theme, Theme::Dark, self.t.get("Dark"))
+                        .changed()
+                        || ui
+                            .selectable_value(
+                                &mut self.setting.theme,
+                                Theme::Light,
+                                self.t.get("Light"),
+                            )
+                            .changed()
+                    {
+                        ctx.set_visuals(self.setting.theme.visuals());
+                        self.setting.save();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label(self.t.get("Language"));
+                    if ui
+                        .selectable_value(&mut self.setting.lang, Lang::En, "English")
+                        .changed()
+                        || ui
+                            .selectable_value(&mut self.setting.lang, Lang::Cn, "简体中文")
+                            .changed()
+                    {
+                        self.t.change(&self.setting.lang);
+                        self.setting.save();
+                    }
+                });
+
+                ui.separator();
+                self.background_setting(ui);
+
+                ui.separator();
+                self.storage_setting(ui);
+
+                ui.separator();
+                self.log_setting(ui);
+
+                ui.separator();
+                self.health_setting(ui);
+
+                ui.separator();
+                self.notify_setting(ui);
+
+                ui.separator();
+                self.api_setting(ui);
+            })
+        });
+    }
+
+    fn background_setting(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(
+                    &mut self.setting.background,
+                    self.t
+                        .get("Keep running in background after closing the window"),
+                )
+                .on_hover_text(
+                    self.t
+                        .get("Run auto_backup show or start it again to reopen the window"),
+                )
+                .changed()
+            {
+                self.setting.save();
+            }
+            if style::btn_danger_round(self.t.get("Quit"), theme)
+                .ui(ui)
+                .on_hover_text(self.t.get("Stop auto backup jobs and quit"))
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                let _ = self
+                    .command
+                    .clone()
+                    .unwrap()
+                    .send(Command::Quit { reply: None });
+            }
+        });
+    }
+
+    fn storage_setting(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        ui.horizontal(|ui| {
+            ui.label(self.t.get("Storage"));
+            if style::btn_primary_round(self.t.get("Rescan Storage"), theme)
+                .ui(ui)
+                .on_hover_text(self.t.get(
+                    "Compare the backup folders with the records, and adopt the folders without records",
+                ))
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                self.rescan(false);
+            }
+            if style::btn_info_round(self.t.get("Export Data"), theme)
+                .ui(ui)
+                .on_hover_text(self.t.get(
+                    "Export all save items, backups and runs in the data.json format",
+                ))
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("json", &["json"])
+                    .set_file_name("data.json")
+                    .save_file()
+                {
+                    self.export_data(path.display().to_string());
+                }
+            }
+        });
+    }
+
+    fn export_data(&mut self, path: String) {
+        self.request(
+            "export_data",
+            |reply| Command::ExportData {
+                path,
+                reply: Some(reply),
+            },
+            |app, result| match result {
+                Ok(_) => {
+                    app.toasts.success(app.t.get("Export Success"));
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                }
+            },
+        );
+    }
+
+    pub fn rescan(&mut self, apply: bool) {
+        self.request(
+            "rescan",
+            |reply| Command::Rescan {
+                apply,
+                reply: Some(reply),
+            },
+            move |app, result| match result
+                .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+            {
+                Ok(report) => {
+                    if apply {
+                        app.toasts.success(app.t.get("Rescan Success"));
+                    }
+                    app.control.show_rescan = ShowRescan::new(report);
+                    app.control.show_rescan.show();
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                }
+            },
+        );
+    }
+
+    fn log_setting(&mut self, ui: &mut Ui) {
+        let log = &mut self.setting.log;
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(self.t.get("Log Level"));
+            ComboBox::from_id_source("setting_log_level")
+                .selected_text(self.t.get(&log.level.to_string()))
+                .show_ui(ui, |ui| {
+                    for level in Level::ALL {
+                        changed |= ui
+                            .selectable_value(&mut log.level, level, self.t.get(&level.to_string()))
+                            .changed();
+                    }
+                });
+            ui.label(self.t.get("New log file after"));
+            changed |= DragValue::new(&mut log.max_size_mb)
+                .clamp_range(0..=1024)
+                .suffix(" MB")
+                .ui(ui)
+                .on_hover_text(self.t.get("0 means no limit"))
+                .changed();
+            ui.label(self.t.get("Keep old logs for"));
+            changed |= DragValue::new(&mut log.keep_days)
+                .clamp_range(0..=3650)
+                .ui(ui)
+                .on_hover_text(self.t.get("0 means keep forever"))
+                .changed();
+            ui.label(self.t.get("Days"));
+        });
+        if changed {
+            log::configure(log);
+            self.setting.save();
+        }
+    }
+
+    fn health_setting(&mut self, ui: &mut Ui) {
+        let health = &mut self.setting.health;
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(self.t.get("Warn when no backup for"));
+            changed |= DragValue::new(&mut health.stale_days)
+                .clamp_range(0..=3650)
+                .ui(ui)
+                .on_hover_text(self.t.get("0 means no limit"))
+                .changed();
+            ui.label(self.t.get("Days"));
+            ui.label(self.t.get("Error after consecutive auto backup failures"));
+            changed |= DragValue::new(&mut health.failed_runs)
+                .clamp_range(1..=100)
+                .ui(ui)
+                .changed();
+        });
+        if changed {
+            self.setting.save();
+        }
+    }
+
+    fn notify_setting(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        let notify = &mut self.setting.notify;
+        let mut changed = false;
+        let mut test = false;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut notify.desktop, self.t.get("Desktop Notifications"))
+                .on_hover_text(self.t.get("Only supported on Linux"))
+                .changed();
+            ui.label(self.t.get("Webhook"));
+            changed |= TextEdit::singleline(&mut notify.webhook)
+                .hint_text("http://127.0.0.1:8080/backup")
+                .ui(ui)
+                .on_hover_text(
+                    self.t
+                        .get("Post the backup outcome as JSON, empty to disable"),
+                )
+                .changed();
+            test = style::btn_primary_round(self.t.get("Test"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked();
+        });
+        if changed {
+            notify::configure(notify);
+            self.setting.save();
+        }
+        if test {
+            self.test_notify();
+        }
+    }
+
+    fn test_notify(&mut self) {
+        self.request(
+            "test_notify",
+            |reply| Command::TestNotify { reply },
+            Self::show_deliveries,
+        );
+    }
+
+    fn show_deliveries(&mut self, result: Result<Value, String>) {
+        let deliveries: Vec<Delivery> = match result
+            .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+        {
+            Ok(deliveries) => deliveries,
+            Err(e) => {
+                self.toasts.error(self.t.get(&e));
+                return;
+            }
+        };
+        for delivery in deliveries {
+            match delivery.error {
+                None => {
+                    self.toasts
+                        .success(format!("{} {}", delivery.channel, self.t.get("Success")));
+                }
+                Some(e) => {
+                    self.toasts.error(format!("{}: {}", delivery.channel, e));
+                }
+            }
+        }
+    }
+
+    fn api_setting(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        let api = &mut self.setting.api;
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut api.open, self.t.get("Local HTTP API"))
+                .on_hover_text(self.t.get("Restart to take effect"))
+                .changed();
+        });
+        if api.open {
+            ui.horizontal(|ui| {
+                ui.label(self.t.get("Port"));
+                changed |= DragValue::new(&mut api.port)
+                    .clamp_range(1024..=65535)
+                    .ui(ui)
+                    .changed();
+                ui.label(format!("http://127.0.0.1:{}/api", api.port));
+            });
+            ui.horizontal(|ui| {
+                ui.label(self.t.get("Token"));
+                Label::new(RichText::new(api.token.to_string()).monospace()).ui(ui);
+                if style::btn_primary_round(self.t.get("Regenerate"), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    api.token = uuid::Uuid::new_v4().simple().to_string();
+                    changed = true;
+                }
+            });
+        }
+        if changed {
+            self.setting.save();
+        }
+    }
+
+    /// 健康检查会访问监听路径，只在数据变化、设置变化或者超过间隔时重新检查
+    pub fn refresh_health(&mut self) {
+        let revision = catalog::revision();
+        if self.statis.health_fresh(revision, &self.setting.health) {
+            return;
+        }
+        if let Ok(data) = self.data.try_lock() {
+            let healths = health::check_all(&data, &self.setting.health);
+            self.statis
+                .set_healths(healths, revision, &self.setting.health);
+        }
+    }
+
+    /// 处理已经回复的请求，还有请求在等待时继续刷新界面
+    pub fn receive_replies(&mut self, ctx: &Context) {
+        let mut replies = vec![];
+        for request in std::mem::take(&mut self.control.pending) {
+            match request.rx.try_recv() {
+                Ok(result) => replies.push((request.on_reply, result)),
+                Err(TryRecvError::Empty) => self.control.pending.push(request),
+                Err(TryRecvError::Disconnected) => {
+                    replies.push((request.on_reply, Err("command worker stopped".to_string())))
+                }
+            }
+        }
+        for (on_reply, result) in replies {
+            on_reply(self, result);
+        }
+        if !self.control.pending.is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(200));
+        }
+    }
+
+    /// 备份或恢复完成后提示结果，回复中的 warnings 是钩子的错误等警告
+    pub fn toast_result(&mut self, success: &str, result: Result<Value, String>) {
+        match result {
+            Ok(value) => {
+                let mut msgs = vec![(ToastLevel::Success, success.to_string())];
+                for warning in value["warnings"].as_array().into_iter().flatten() {
+                    let warning = warning.as_str().unwrap_or_default().to_string();
+                    msgs.push((ToastLevel::Warning, warning));
+                }
+                self.package_toasts(msgs);
+            }
+            Err(e) => self.package_toasts(vec![(ToastLevel::Error, e)]),
+        }
+    }
+
+    pub fn package_toasts(&mut self, results: Vec<(ToastLevel, String)>) {
+        for (level, msg) in results {
+            let msg = self.t.get(&msg);
+            match level {
+                ToastLevel::Info => {
+                    self.toasts.info(msg);
+                }
+                ToastLevel::Warning => {
+                    self.toasts.warning(msg);
+                }
+                ToastLevel::Error => {
+                    self.toasts.error(msg);
+                }
+                ToastLevel::Success => {
+                    self.toasts.success(msg);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn manage_list(&mut self, ui: &mut eframe::egui::Ui) {
+        if let Ok(active) = self.active.try_lock() {
+            if active.id.is_empty() {
+                return;
+            }
+        }
+
+        let theme = &self.setting.get_theme();
+
+        CentralPanel::default().show_inside(ui, |ui| {
+            ui.vertical(|ui| {
+                style::shadow_frame(theme).show(ui, |ui| {
+
+                    if let Ok(ref mut active) = self.active.try_lock() {
+                        ui.horizontal(|ui| {
+                            ui.label(self.t.get("Name"));
+                            ui.label(active.name.to_string());
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label(self.t.get("Description"));
+                            Label::new(active.description.to_string()).wrap(true).ui(ui);
+                        });
+                        item_labels(ui, &self.t, active);
+
+                        ui.horizontal(|ui| {
+                            ui.label(self.t.get("Remark"));
+                            TextEdit::singleline(&mut self.control.new_remark)
+                            .hint_text(self.t.get("Please enter comments for the new backup"))
+                            .ui(ui);
+                        });
+                    }
+                    self.profile_form(ui);
+
+                    ui.horizontal(|ui| {
+                        if style::btn_primary_round(self.t.get("Backup Now"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .on_hover_text(self.t.get("The backup runs in the background, a message shows when it finishes"))
+                            .clicked()
+                        {
+                            let id = match self.active.try_lock() {
+                                Ok(active) => Some(active.id.to_string()),
+                                Err(_) => None,
+                            };
+                            if let Some(id) = id {
+                                // 复制文件和钩子可能很久，交给 command_proc 执行，备份记录由它保存并同步到当前存档
+                                let remark = self.control.new_remark.to_string();
+                                self.request(
+                                    "backup",
+                                    |reply| Command::Backup { id, remark, reply: Some(reply) },
+                                    |app, result| app.toast_result("Backup Success", result),
+                                );
+                            }
+                        }
+
+                        if let Ok(active) = self.active.try_lock() {
+                            if !active.startup_path.is_empty() {
+                                if style::btn_success_round(self.t.get("Startup"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .on_hover_text(active.startup_path.to_string())
+                                    .clicked()
+                                {
+                                    let _ = open::that(active.startup_path.to_string());
+                                }
+                            }
+
+                            if style::btn_primary_round(self.t.get("Open Backup Folder"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                let folder =
+                                    format!("{}/data/backup/{}", util::current_dir(), active.id);
+                                let _ = open::that(folder);
+                            }
+                            if style::btn_primary_round(self.t.get("Check Monitor"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.show_check_monitor = active.id.to_string();
+                            }
+                            if style::btn_primary_round(self.t.get("Edit"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.form.save_item = active.clone();
+                                self.form.new = false;
+                                self.form.open = true;
+                                self.control.nav = Navigation::Edit;
+                            }
+                            if style::btn_primary_round(self.t.get("Export"), theme)
+                                .ui(ui)
+                                .on_hover_text(self.t.get("Export the save item and selected backups to a zip file"))
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.show_export = ShowExport::new(&active);
+                                self.control.show_export.show();
+                            }
+                            if style::btn_primary_round(self.t.get("File History"), theme)
+                                .ui(ui)
+                                .on_hover_text(self.t.get("Find the backups containing a file and restore one version of it"))
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.show_file_history = ShowFileHistory::new(&active);
+                                self.control.show_file_history.show();
+                            }
+                            if style::btn_primary_round(self.t.get("Change History"), theme)
+                                .ui(ui)
+                                .on_hover_text(self.t.get("Show the changes of the settings and monitored paths, and undo one of them"))
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.show_item_history = ShowItemHistory::new(&active);
+                                self.control.show_item_history.show();
+                            }
+                            if style::btn_primary_round(self.t.get("Timeline"), theme)
+                                .ui(ui)
+                                .on_hover_text(self.t.get("Show the backups and failed runs on a time axis"))
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.timeline_item = active.id.to_string();
+                                self.control.nav = Navigation::Timeline;
+                            }
+                        }
+
+                        if style::btn_waring_round(self.t.get("Archive"), theme)
+                        .ui(ui)
+                        .on_hover_ui(|ui| {
+                            ui.label(self.t.get("When Archived"));
+                            ui.label(self.t.get("Will move to archived tab"));
+                            ui.label(self.t.get("and Stop the current auto backup task"));
+                        })
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                        {
+                            let mut save = SaveItem::default();
+                            if let Ok(mut active) = self.active.try_lock() {
+                                active.status = Status::Archive;
+                                active.auto.status = AutoStatus::Stop;                        save = active.clone();
+                                *active = SaveItem::default();
+                            }
+                            save.save(self);
+                            let _ = self.command.clone().unwrap().send(Command::StartJob);
+                            self.toasts.success(self.t.get("Auto Backup Already Restart"));
+                        }
+                    });
+
+                    let mut save = false;
+                    let mut toggle_auto = false;
+                    ui.horizontal(|ui| {
+                        if let Ok(mut active) = self.active.try_lock() {
+                            // let auto = &mut active.auto;
+                        if ui
+                            .checkbox(&mut active.auto.open, self.t.get("Use Auto Backup"))
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            if !active.auto.open {
+                                active.auto.status = AutoStatus::Stop;
+                            }
+                            // active.clone().save(self);
+                            save = true;
+                        }
+
+                        if active.auto.open {
+                            ui.label(self.t.get("Cron"));
+
+                            let cron = ui.text_edit_singleline(&mut active.auto.cron);
+                            if cron.changed() {
+                                active.auto.status = AutoStatus::Stop;
+                                // active.clone().save(self);
+                                save = true;
+                                let _ = self.command.clone().unwrap().send(Command::StartJob);
+                                self.toasts.success(self.t.get("Auto Backup Already Restart"));
+                            }
+                            let mut parse_time = job::parse_time(&active.auto.cron);
+                            if cron.hovered() {
+                                if parse_time.is_empty() {
+                                    parse_time = vec![self.t.get("Format Invalid").to_string()];
+                                }
+                                let tips = format!(
+                                    "{}\nsec min hour day_of_month month day_of_week year\n{}\n{}",
+                                    self.t.get("Format"),
+                                    self.t.get("Next Start Time"),
+                                    parse_time.join("\n")
+                                );
+                                cron.on_hover_text(tips);
+                            }
+
+                            let color = match active.auto.status {
+                                AutoStatus::Running => {
+                                    style::success_color(&self.setting.get_theme())
+                                }
+                                AutoStatus::Stop => style::danger_color(&self.setting.get_theme()),
+                            };
+                            ui.colored_label(color, self.t.get(&active.auto.status.to_string()));
+
+                            match active.auto.status {
+                                AutoStatus::Stop => {
+                                    if style::btn_success(self.t.get("Startup"), theme)
+                                        .ui(ui)
+                                        .on_hover_cursor(CursorIcon::PointingHand)
+                                        .clicked() 
+                                    {
+                                        if parse_time.is_empty() {
+                                            self.toasts.warning(self.t.get("Cron format invalid"));
+                                        } else {
+                                            active.auto.status = AutoStatus::Running;
+                                        }
+                                        // active.clone().save(self);
+                                        save = true;
+                                        toggle_auto = true;
+                                    }
+                                }
+                                AutoStatus::Running => {
+                                    if style::btn_info(self.t.get("Stop"), theme)
+                                        .ui(ui)
+                                        .on_hover_cursor(CursorIcon::PointingHand)
+                                        .clicked()
+                                    {
+                                        active.auto.status = AutoStatus::Stop;
+                                        // active.clone().save(self);
+                                        save = true;
+                                        toggle_auto = true;
+                                    }
+                                }
+                            }
+                        }
+                        }
+                    });
+
+                    if self.condition_form(ui) {
+                        save = true;
+                        toggle_auto = true;
+                    }
+
+                    if save {
+                        let mut save = SaveItem::default();
+                        if let Ok(active) = self.active.try_lock() {
+                            save = active.clone();
+                        }
+                        save.save(self);
+                    }
+                    if toggle_auto {
+                        let _ = self.command.clone().unwrap().send(Command::StartJob);
+                        self.toasts.success(self.t.get("Auto Backup Already Restart"));
+                    }
+
+                    // ui.separator();
+                });
+                style::shadow_frame(theme).show(ui, |ui| {
+                    self.backup_list(ui);
+                });
+            });
+        });
+    }
+
+    pub fn backup_list(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+
+        let mut active_id = String::new();
+        let mut active_name = String::new();
+        if let Ok(active) = self.active.try_lock() {
+            active_id = active.id.to_string();
+            active_name = active.name.to_string();
+        }
+
+        let (query, page) = self.backup_page(&active_id);
+
+        // 切换存档或删除后去掉不存在的备份
+        self.control
+            .selected_backups
+            .retain(|id| page.item_ids.contains(id));
+        if !self.control.selected_backups.is_empty() {
+            self.bulk_bar(ui, &active_id, &active_name);
+        }
+        self.page_bar(ui, &page);
+
+        let available_height = ui.available_height();
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::auto())
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto().clip(true).at_least(80.0))
+            .column(Column::auto().clip(true).at_least(160.0))
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            // .min_scrolled_height(0.0)
+            .max_scroll_height(available_height)
+            .sense(Sense::click());
+        if std::mem::take(&mut self.control.jump_scroll) {
+            if let Some(row) = page
+                .rows
+                .iter()
+                .position(|(_, backup)| backup.id == self.control.jump_backup)
+            {
+                table = table.scroll_to_row(row, Some(Align::Center));
+            }
+        }
+
+        table
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    let selected = &mut self.control.selected_backups;
+                    let mut all =
+                        !page.ids.is_empty() && page.ids.iter().all(|id| selected.contains(id));
+                    if ui
+                        .checkbox(&mut all, "")
+                        .on_hover_text(self.t.get("Select All"))
+                        .changed()
+                    {
+                        for id in &page.ids {
+                            if all {
+                                selected.insert(id.to_string());
+                            } else {
+                                selected.remove(id);
+                            }
+                        }
+                    }
+                    self.sort_header(ui, "Row", BackupSort::Time);
+                });
+                header.col(|ui| {
+                    self.sort_header(ui, "Bakup Time", BackupSort::Time);
+                });
+                header.col(|ui| {
+                    self.sort_header(ui, "Profile", BackupSort::Profile);
+                });
+                header.col(|ui| {
+                    self.sort_header(ui, "Remark", BackupSort::Remark);
+                    if TextEdit::singleline(&mut self.control.backup_filter)
+                        .hint_text(self.t.get("Search title, remark and notes"))
+                        .ui(ui)
+                        .changed()
+                    {
+                        self.control.backup_page = 0;
+                    }
+                });
+                header.col(|ui| {
+                    self.sort_header(ui, "Size", BackupSort::Size);
+                });
+                header.col(|ui| {
+                    self.sort_header(ui, "Protect", BackupSort::Protect);
+                });
+                header.col(|ui| {
+                    ui.strong(self.t.get("Operate"));
+                });
+            })
+            .body(|body| {
+                // 只绘制可见的行
+                body.rows(18.0, page.rows.len(), |mut row| {
+                    let (index, item) = &page.rows[row.index()];
+                    let index = *index;
+                    let mut protect = item.protect;
+                    row.set_selected(protect || item.id == self.control.jump_backup);
+                    row.col(|ui| {
+                        let selected = &mut self.control.selected_backups;
+                        let mut checked = selected.contains(&item.id);
+                        if ui.checkbox(&mut checked, "").changed() {
+                            if checked {
+                                selected.insert(item.id.to_string());
+                            } else {
+                                selected.remove(&item.id);
+                            }
+                        }
+                        ui.label((index + 1).to_string());
+                    });
+                    row.col(|ui: &mut Ui| {
+                        let time = if let Some(time) =
+                            chrono::DateTime::from_timestamp_millis(item.time)
+                        {
+                            let fmt = "%Y-%m-%d %H:%M:%S";
+                            format!("{}", time.with_timezone(&Local).format(fmt))
+                        } else {
+                            self.t.get("Unknow").to_string()
+                        };
+                        ui.label(time);
+                    });
+                    row.col(|ui| {
+                        ui.label(self.t.get(profile::display(&item.profile)));
+                    });
+                    row.col(|ui| {
+                        backup_labels(ui, item).on_hover_ui(|ui| {
+                            backup_details(ui, &self.t, item);
+                        });
+                    });
+                    row.col(|ui| {
+                        ui.label(util::format_size(item.size));
+                    });
+                    row.col(|ui| {
+                        if ui.checkbox(&mut protect, "").clicked() {
+                            let _ = self.command.clone().unwrap().send(Command::ProtectBackup {
+                                id: active_id.to_string(),
+                                backup_id: item.id.to_string(),
+                                protect,
+                                reply: None,
+                            });
+                        }
+                    });
+                    row.col(|ui| {
+                        let overwrite_btn = style::btn_waring(self.t.get("Overwrite"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand);
+                        if overwrite_btn.clicked() {
+                            self.control.show_confirm_overwrite_backup =
+                                ShowConfirmOverwriteBackup::new(
+                                    // item.id.to_string(),
+                                    item.clone(),
+                                    overwrite_btn.interact_pointer_pos(),
+                                );
+                            self.control.show_confirm_overwrite_backup.show();
+                        }
+
+                        if style::btn_info(self.t.get("Details"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            if self.control.show_backup_remark == index as isize {
+                                self.control.show_backup_remark = -1;
+                            } else {
+                                self.control.show_backup_remark = index as isize;
+                            }
+                        }
+
+                        if style::btn_primary(self.t.get("Open"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            let _ = open::that(&item.backup_folder);
+                        }
+
+                        if item.is_git() {
+                            if style::btn_info(self.t.get("Diff"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .on_hover_text(item.commit.to_string())
+                                .clicked()
+                            {
+                                self.control.show_git = ShowGit::new(item.clone(), false);
+                                self.control.show_git.show();
+                            }
+                            if style::btn_info(self.t.get("Blame"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.show_git = ShowGit::new(item.clone(), true);
+                                self.control.show_git.show();
+                            }
+                        }
+
+                        let delete_backup_btn = style::btn_danger(self.t.get("Delete"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand);
+                        if delete_backup_btn.clicked() {
+                            if protect {
+                                self.toasts
+                                    .warning(self.t.get("Please cancel the protection first"));
+                            } else if !self.control.show_confirm_del_backup.is_show() {
+                                self.control.show_confirm_del_backup = ShowConfirmDelBackup::new(
+                                    item.id.to_string(),
+                                    delete_backup_btn.interact_pointer_pos(),
+                                );
+                                self.control.show_confirm_del_backup.show();
+                            } else {
+                                self.toasts
+                                    .warning(self.t.get("Please confirm the operation first"));
+                            }
+                        }
+                    });
+
+                    // if row.response().clicked() {
+                    //     mutex_data.selected_index = index;
+                    // }
+                });
+            });
+        self.control.backup_view = Some((query, page));
+    }
+
+    /// 当前存档的一页备份，查询条件和备份索引都没有变化时使用缓存
+    fn backup_page(&mut self, item_id: &str) -> (PageQuery, Page) {
+        let page_size = match self.control.backup_page_size {
+            0 => PAGE_SIZES[1],
+            size => size,
+        };
+        let mut query = PageQuery {
+            item_id: item_id.to_string(),
+            keyword: self.control.backup_filter.to_string(),
+            sort: self.control.backup_sort,
+            desc: !self.control.backup_asc,
+            page: self.control.backup_page,
+            page_size,
+        };
+        if let Some((cached, page)) = self.control.backup_view.take() {
+            if cached == query && page.revision == catalog::revision() {
+                return (cached, page);
+            }
+        }
+        let mut page = self.query_page(&query);
+        // 跳转的备份不在当前页时翻到它所在的页
+        let jump = if self.control.jump_scroll {
+            page.ids
+                .iter()
+                .position(|id| *id == self.control.jump_backup)
+        } else {
+            None
+        };
+        let target = match jump {
+            Some(position) => position / page_size,
+            None => query.page.min(page.pages(page_size) - 1),
+        };
+        if target != query.page {
+            query.page = target;
+            self.control.backup_page = target;
+            page = self.query_page(&query);
+        }
+        (query, page)
+    }
+
+    fn query_page(&mut self, query: &PageQuery) -> Page {
+        catalog::page(query).unwrap_or_else(|e| {
+            log::log_err(format!("query backups error: {}", e));
+            Page {
+                revision: catalog::revision(),
+                ..Default::default()
+            }
+        })
+    }
+
+    /// 点击表头按该列排序，再次点击切换升序和降序
+    fn sort_header(&mut self, ui: &mut Ui, label: &str, sort: BackupSort) {
+        let arrow = match (self.control.backup_sort == sort, self.control.backup_asc) {
+            (false, _) => "",
+            (true, true) => " ↑",
+            (true, false) => " ↓",
+        };
+        if ui
+            .add(
+                Label::new(RichText::new(format!("{}{}", self.t.get(label), arrow)).strong())
+                    .sense(Sense::click()),
+            )
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .clicked()
+        {
+            if self.control.backup_sort == sort {
+                self.control.backup_asc = !self.control.backup_asc;
+            } else {
+                self.control.backup_sort = sort;
+                self.control.backup_asc = false;
+            }
+            self.control.backup_page = 0;
+        }
+    }
+
+    /// 备份总数、翻页和每页数量
+    fn page_bar(&mut self, ui: &mut Ui, page: &Page) {
+        let theme = &self.setting.get_theme();
+        let page_size = match self.control.backup_page_size {
+            0 => PAGE_SIZES[1],
+            size => size,
+        };
+        let pages = page.pages(page_size);
+        ui.horizontal(|ui| {
+            ui.label(format!("{}: {}", self.t.get("Total"), page.ids.len()));
+            if pages > 1 {
+                if ui
+                    .add_enabled(self.control.backup_page > 0, style::btn_info("<", theme))
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.control.backup_page -= 1;
+                }
+                ui.label(format!("{} / {}", self.control.backup_page + 1, pages));
+                if ui
+                    .add_enabled(
+                        self.control.backup_page + 1 < pages,
+                        style::btn_info(">", theme),
+                    )
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.control.backup_page += 1;
+                }
+            }
+            ComboBox::from_id_source("backup_page_size")
+                .selected_text(format!("{} {}", page_size, self.t.get("Per Page")))
+                .show_ui(ui, |ui| {
+                    for size in PAGE_SIZES {
+                        if ui
+                            .selectable_value(
+                                &mut self.control.backup_page_size,
+                                size,
+                                size.to_string(),
+                            )
+                            .clicked()
+                        {
+                            self.control.backup_page = 0;
+                        }
+                    }
+                });
+        });
+    }
+
+    /// 选中多个备份后的批量操作
+    fn bulk_bar(&mut self, ui: &mut Ui, id: &str, name: &str) {
+        let theme = &self.setting.get_theme();
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{}: {}",
+                self.t.get("Selected"),
+                self.control.selected_backups.len()
+            ));
+            for (label, bulk) in [
+                ("Protect", BulkAction::Protect),
+                ("Unprotect", BulkAction::Unprotect),
+                ("Verify", BulkAction::Verify),
+            ] {
+                if style::btn_primary(self.t.get(label), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    action = Some(bulk);
+                }
+            }
+            if style::btn_primary(self.t.get("Export"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                action = FileDialog::new()
+                    .add_filter("zip", &["zip"])
+                    .set_file_name(format!("{}.zip", name))
+                    .save_file()
+                    .map(|path| BulkAction::Export {
+                        path: path.display().to_string(),
+                    });
+            }
+            if self.control.confirm_bulk_delete {
+                ui.label(self.t.get("Move the selected backups to the trash?"));
+                if style::btn_danger(self.t.get("Confirm"), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    action = Some(BulkAction::Delete);
+                    self.control.confirm_bulk_delete = false;
+                }
+                if style::btn_info(self.t.get("Cancel"), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.control.confirm_bulk_delete = false;
+                }
+            } else if style::btn_danger(self.t.get("Delete"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                self.control.confirm_bulk_delete = true;
+            }
+            if style::btn_info(self.t.get("Clear Selection"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                self.control.selected_backups.clear();
+                self.control.confirm_bulk_delete = false;
+            }
+        });
+        if let Some(action) = action {
+            self.bulk_backups(id, action);
+        }
+    }
+
+    fn bulk_backups(&mut self, id: &str, action: BulkAction) {
+        let request = BulkRequest {
+            selector: BackupSelector {
+                ids: self.control.selected_backups.iter().cloned().collect(),
+                ..BackupSelector::default()
+            },
+            action: action.clone(),
+            dry_run: false,
+        };
+        let id = id.to_string();
+        self.request(
+            "bulk",
+            |reply| Command::BulkBackups {
+                id,
+                request,
+                reply: Some(reply),
+            },
+            move |app, result| app.show_bulk_results(action, result),
+        );
+    }
+
+    fn show_bulk_results(&mut self, action: BulkAction, result: Result<Value, String>) {
+        let results: Vec<BulkResult> = match result.and_then(|value| {
+            serde_json::from_value(value["results"].clone()).map_err(|e| e.to_string())
+        }) {
+            Ok(results) => results,
+            Err(e) => {
+                self.toasts.error(e);
+                return;
+            }
+        };
+        let errors: Vec<&BulkResult> = results.iter().filter(|r| r.error.is_some()).collect();
+        if errors.is_empty() {
+            self.toasts
+                .success(format!("{}: {}", self.t.get("Success"), results.len()));
+        } else {
+            self.toasts.error(format!(
+                "{}: {}\n{}",
+                self.t.get("Failed"),
+                errors.len(),
+                errors
+                    .iter()
+                    .take(5)
+                    .map(|r| r.error.clone().unwrap_or_default())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+        // 校验和导出后保留选择，方便继续操作
+        if action == BulkAction::Delete {
+            self.control.selected_backups.clear();
+        }
+    }
+
+    /// 切换和添加配置
+    fn profile_form(&mut self, ui: &mut Ui) {
+        let theme = &self.setting.get_theme();
+        let (id, current, names) = match self.active.try_lock() {
+            Ok(active) => (
+                active.id.to_string(),
+                active.profile.to_string(),
+                profile::names(&active),
+            ),
+            Err(_) => return,
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(self.t.get("Profile"));
+            // 切换完成前不能再次切换
+            if self.is_pending("switch_profile") {
+                ui.spinner();
+                ui.label(self.t.get("Switching profile..."));
+            } else {
+                ComboBox::from_id_source("profile")
+                    .selected_text(self.t.get(profile::display(&current)))
+                    .show_ui(ui, |ui| {
+                        for name in &names {
+                            let response = ui.selectable_label(
+                                *name == current,
+                                self.t.get(profile::display(name)),
+                            );
+                            if response.clicked() && *name != current {
+                                self.control.show_confirm_switch_profile =
+                                    ShowConfirmSwitchProfile::new(
+                                        name.to_string(),
+                                        response.interact_pointer_pos(),
+                                    );
+                                self.control.show_confirm_switch_profile.show();
+                            }
+                        }
+                    });
+            }
+
+            TextEdit::singleline(&mut self.control.new_profile)
+                .hint_text(self.t.get("New profile name"))
+                .desired_width(120.0)
+                .ui(ui);
+            if style::btn_primary(self.t.get("Add Profile"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                let name = self.control.new_profile.to_string();
+                self.request(
+                    "add_profile",
+                    |reply| Command::AddProfile {
+                        id,
+                        name,
+                        reply: Some(reply),
+                    },
+                    |app, result| match result {
+                        Ok(_) => app.control.new_profile.clear(),
+                        Err(e) => {
+                            app.toasts.error(e);
+                        }
+                    },
+                );
+            }
+        });
+    }
+
+    /// 分组、平台和标签
+    fn classify_form(&mut self, ui: &mut Ui, label_size: Vec2) {
+        let theme = &self.setting.get_theme();
+        let (groups, platforms, tags) = match self.data.try_lock() {
+            Ok(data) => (
+                filter::all_groups(&data.monitors),
+                filter::all_platforms(&data.monitors),
+                filter::all_tags(&data.monitors),
+            ),
+            Err(_) => Default::default(),
+        };
+        let save_item = &mut self.form.save_item;
+        ui.horizontal(|ui| {
+            ui.add_sized(label_size, Label::new(self.t.get("Group")));
+            text_with_options(ui, "form_group", &mut save_item.group, &groups);
+            ui.add_sized(label_size, Label::new(self.t.get("Platform")));
+            text_with_options(ui, "form_platform", &mut save_item.platform, &platforms);
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.add_sized(label_size, Label::new(self.t.get("Tags")));
+            let mut remove = None;
+            for (index, tag) in save_item.tags.iter().enumerate() {
+                if style::btn_info_round(&format!("{} ×", tag), theme)
+                    .ui(ui)
+                    .on_hover_text(self.t.get("Remove"))
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    remove = Some(index);
+                }
+            }
+            if let Some(index) = remove {
+                save_item.tags.remove(index);
+            }
+            let input = TextEdit::singleline(&mut self.form.new_tag)
+                .hint_text(self.t.get("New Tag"))
+                .desired_width(100.0)
+                .ui(ui);
+            let mut add = input.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            let unused: Vec<&String> = tags
+                .iter()
+                .filter(|tag| !filter::has_tag(save_item, tag))
+                .collect();
+            if !unused.is_empty() {
+                ComboBox::from_id_source("form_tags")
+                    .selected_text("")
+                    .width(20.0)
+                    .show_ui(ui, |ui| {
+                        for tag in unused {
+                            if ui.selectable_label(false, tag).clicked() {
+                                self.form.new_tag = tag.to_string();
+                                add = true;
+                            }
+                        }
+                    });
+            }
+            if (add
+                || style::btn_primary_round(self.t.get("Add"), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked())
+                && !self.form.new_tag.trim().is_empty()
+            {
+                save_item.tags.push(self.form.new_tag.to_string());
+                save_item.tags = filter::normalize_tags(&save_item.tags);
+                self.form.new_tag.clear();
+            }
+        });
+    }
+
+    /// 选择已安装的 Steam 游戏，设置启动方式，并把 Windows 路径解析到 Proton 前缀中
+    fn steam_form(&mut self, ui: &mut Ui, label_size: Vec2) {
+        let theme = &self.setting.get_theme();
+        let apps = self
+            .form
+            .steam_apps
+            .get_or_insert_with(steam::installed_apps)
+            .clone();
+        if apps.is_empty() {
+            return;
+        }
+        // 启动方式是 steam://rungameid/<appid> 时就是选中的游戏
+        let selected = steam::parse_launch_url(&self.form.save_item.startup_path)
+            .and_then(|id| apps.iter().find(|app| app.app_id == id))
+            .cloned();
+        ui.horizontal(|ui| {
+            ui.add_sized(label_size, Label::new(self.t.get("Steam Game")));
+            ComboBox::from_id_source("form_steam_app")
+                .selected_text(
+                    selected
+                        .as_ref()
+                        .map(|app| app.name.to_string())
+                        .unwrap_or_default(),
+                )
+                .width(200.0)
+                .show_ui(ui, |ui| {
+                    for app in &apps {
+                        if ui
+                            .selectable_label(
+                                selected.as_ref().map(|a| a.app_id) == Some(app.app_id),
+                                &app.name,
+                            )
+                            .on_hover_text(app.install_dir.to_string())
+                            .clicked()
+                        {
+                            let save_item = &mut self.form.save_item;
+                            if save_item.name.is_empty() {
+                                save_item.name = app.name.to_string();
+                            }
+                            save_item.startup_path = app.launch_url();
+                            save_item.platform = if app.prefix.is_some() {
+                                "Proton".to_string()
+                            } else {
+                                "Steam".to_string()
+                            };
+                        }
+                    }
+                });
+            if style::btn_info_round(self.t.get("Refresh"), theme)
+                .ui(ui)
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+            {
+                self.form.steam_apps = None;
+            }
+        });
+        let Some(prefix) = selected.and_then(|app| app.prefix) else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.add_sized(label_size, Label::new(self.t.get("Proton Path")));
+            TextEdit::singleline(&mut self.form.windows_path)
+                .hint_text("%APPDATA%\\Game\\Saves")
+                .ui(ui)
+                .on_hover_text(prefix.to_string());
+            if style::btn_primary_round(self.t.get("Add"), theme)
+                .ui(ui)
+                .on_hover_text(
+                    self.t
+                        .get("Resolve the Windows path inside the Proton prefix"),
+                )
+                .on_hover_cursor(CursorIcon::PointingHand)
+                .clicked()
+                && !self.form.windows_path.trim().is_empty()
+            {
+                let path = steam::resolve_windows_path(&prefix, &self.form.windows_path);
+                if !util::file_exist(&path) {
+                    self.toasts
+                        .warning(format!("{}: {}", self.t.get("Path does not exist"), path));
+                }
+                let backup_type = if std::path::Path::new(&path).is_file() {
+                    FileType::File
+                } else {
+                    FileType::Folder
+                };
+                self.form
+                    .save_item
+                    .monitors
+                    .push(Monitor { path, backup_type });
+                self.form.windows_path.clear();
+            }
+        });
+    }
+
+    fn hooks_form(&mut self, ui: &mut Ui, label_size: Vec2) {
+        let tips = "AUTO_BACKUP_HOOK, AUTO_BACKUP_ITEM_ID, AUTO_BACKUP_ITEM_NAME\nAUTO_BACKUP_ID, AUTO_BACKUP_FOLDER, AUTO_BACKUP_REMARK, AUTO_BACKUP_OUTCOME";
+        let hooks = &mut self.form.save_item.hooks;
+        CollapsingHeader::new(self.t.get("Hooks"))
+            .id_source("hooks")
+            .show(ui, |ui| {
+                ui.label(
+                    self.t
+                        .get("Shell commands run before and after backup or restore"),
+                )
+                .on_hover_text(tips);
+                for (label, command) in [
+                    ("Pre Backup", &mut hooks.pre_backup),
+                    ("Post Backup", &mut hooks.post_backup),
+                    ("Pre Restore", &mut hooks.pre_restore),
+                    ("Post Restore", &mut hooks.post_restore),
+                ] {
+                    ui.horizontal(|ui| {
+                        ui.add_sized(label_size, Label::new(self.t.get(label)));
+                        ui.text_edit_singleline(command);
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.add_sized(label_size, Label::new(self.t.get("Timeout (s)")));
+                    DragValue::new(&mut hooks.timeout)
+                        .clamp_range(1..=3600)
+                        .ui(ui);
+                });
+            });
+    }
+
+    fn notify_form(&mut self, ui: &mut Ui) {
+        let rule = &mut self.form.save_item.notify;
+        CollapsingHeader::new(self.t.get("Notifications"))
+            .id_source("notify")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(self.t.get("Notify when backup"));
+                    ui.checkbox(&mut rule.success, self.t.get("Success"));
+                    ui.checkbox(&mut rule.failure, self.t.get("Failed"));
+                    ui.checkbox(&mut rule.skipped, self.t.get("Skipped"));
+                });
+            });
+    }
+
+    /// 自动备份的执行条件，返回是否有修改
+    fn condition_form(&mut self, ui: &mut Ui) -> bool {
+        let theme = &self.setting.get_theme();
+        let mut changed = false;
+        let Ok(mut active) = self.active.try_lock() else {
+            return changed;
+        };
+        if !active.auto.open {
+            return changed;
+        }
+
+        if let Some(run) = active.runs.first() {
+            let time = match chrono::DateTime::from_timestamp_millis(run.time) {
+                Some(time) => time
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+                None => self.t.get("Unknow").to_string(),
+            };
+            let outcome = run.outcome.to_string();
+            ui.horizontal(|ui| {
+                ui.label(self.t.get("Last Run"));
+                ui.label(time);
+                ui.label(self.t.get(&outcome));
+                if !run.reason.is_empty() {
+                    Label::new(run.reason.to_string()).truncate(true).ui(ui);
+                }
+            });
+        }
+
+        // 编辑的是草稿，提交后才保存并重新启动任务，不会每次输入都重启
+        // 存档切换或条件在别处被修改（如撤销变更）时重新生成草稿
+        let condition = match &mut self.control.condition_draft {
+            Some((id, base, draft)) if *id == active.id && *base == active.auto.condition => draft,
+            slot => {
+                let condition = active.auto.condition.clone();
+                &mut slot
+                    .insert((active.id.to_string(), condition.clone(), condition))
+                    .2
+            }
+        };
+        CollapsingHeader::new(self.t.get("Run Conditions"))
+            .id_source("run_conditions")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut condition.quiet_hours, self.t.get("Quiet Hours"));
+                    if condition.quiet_hours {
+                        for time in [&mut condition.quiet_start, &mut condition.quiet_end] {
+                            TextEdit::singleline(time)
+                                .desired_width(50.0)
+                                .hint_text("HH:MM")
+                                .ui(ui);
+                        }
+                    }
+                });
+                ui.checkbox(
+                    &mut condition.skip_on_battery,
+                    self.t.get("Skip on battery"),
+                );
+                ui.horizontal(|ui| {
+                    ui.label(self.t.get("Min free space (MB)"));
+                    DragValue::new(&mut condition.min_free_mb)
+                        .speed(100)
+                        .ui(ui)
+                        .on_hover_text(self.t.get("0 means no check"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label(self.t.get("Process"));
+                    TextEdit::singleline(&mut condition.process)
+                        .desired_width(120.0)
+                        .ui(ui);
+                    for rule in [
+                        ProcessRule::Ignore,
+                        ProcessRule::Running,
+                        ProcessRule::NotRunning,
+                    ] {
+                        let text = self.t.get(&rule.to_string()).to_string();
+                        ui.selectable_value(&mut condition.process_rule, rule, text);
+                    }
+                });
+                if *condition == active.auto.condition {
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    if style::btn_success(self.t.get("Submit"), theme)
+                        .ui(ui)
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let times = [&condition.quiet_start, &condition.quiet_end];
+                        if condition.quiet_hours
+                            && !times.iter().all(|time| Condition::valid_time(time))
+                        {
+                            self.toasts.warning(self.t.get("Invalid quiet hours time"));
+                        } else {
+                            active.auto.condition = condition.clone();
+                            changed = true;
+                        }
+                    }
+                    if style::btn_info(self.t.get("Cancel"), theme)
+                        .ui(ui)
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        *condition = active.auto.condition.clone();
+                    }
+                });
+            });
+        changed
+    }
+
+    fn save_item_args_error(&mut self) -> bool {
+        if self.form.save_item.name.is_empty() {
+            self.toasts.warning(self.t.get("Please input name"));
+            return true;
+        }
+        if self.form.save_item.monitors.is_empty() {
+            self.toasts
+                .warning(self.t.get("Please select monitor file"));
+            return true;
         }
         false
     }
 }
+
+/// 健康状态的标记，悬浮时显示原因，正常时不显示
+fn health_badge(ui: &mut Ui, t: &Translator, health: &Health) {
+    let color = match health.status {
+        HealthStatus::Ok => return,
+        HealthStatus::Warn => style::rgb(220, 160, 60),
+        HealthStatus::Error => Color32::RED,
+    };
+    ui.colored_label(color, format!("● {}", t.get(&health.status.to_string())))
+        .on_hover_text(health.text(|key| t.get(key).to_string()));
+}
+
+/// 存档列表上方的筛选栏：关键字、排序、分组、平台和标签
+fn filter_bar(ui: &mut Ui, t: &Translator, filter: &mut ItemFilter, items: &[&SaveItem]) {
+    let groups = filter::all_groups(items.iter().copied());
+    let platforms = filter::all_platforms(items.iter().copied());
+    let tags = filter::all_tags(items.iter().copied());
+
+    TextEdit::singleline(&mut filter.keyword)
+        .hint_text(t.get("Filter by Name, Description or Tag"))
+        .ui(ui);
+    ui.horizontal_wrapped(|ui| {
+        ComboBox::from_id_source("filter_sort")
+            .selected_text(format!(
+                "{}: {}",
+                t.get("Sort"),
+                t.get(&filter.sort.to_string())
+            ))
+            .show_ui(ui, |ui| {
+                for sort in SortBy::ALL {
+                    ui.selectable_value(&mut filter.sort, sort, t.get(&sort.to_string()));
+                }
+            });
+        for (id, all, value, options) in [
+            ("filter_group", "All Groups", &mut filter.group, &groups),
+            (
+                "filter_platform",
+                "All Platforms",
+                &mut filter.platform,
+                &platforms,
+            ),
+        ] {
+            if options.is_empty() {
+                continue;
+            }
+            let selected = if value.is_empty() {
+                t.get(all).to_string()
+            } else {
+                value.to_string()
+            };
+            ComboBox::from_id_source(id)
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(value, String::new(), t.get(all));
+                    for option in options {
+                        ui.selectable_value(value, option.to_string(), option);
+                    }
+                });
+        }
+    });
+    if !tags.is_empty() {
+        ui.horizontal_wrapped(|ui| {
+            for tag in &tags {
+                let selected = filter.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+                if ui
+                    .selectable_label(selected, tag)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    filter.toggle_tag(tag);
+                }
+            }
+        });
+    }
+    if !filter.is_empty() && ui.small_button(t.get("Clear Filter")).clicked() {
+        let sort = filter.sort;
+        *filter = ItemFilter {
+            sort,
+            ..Default::default()
+        };
+    }
+}
+
+/// 存档的分组、平台和标签
+fn item_labels(ui: &mut Ui, t: &Translator, item: &SaveItem) {
+    if item.group.is_empty() && item.platform.is_empty() && item.tags.is_empty() {
+        return;
+    }
+    ui.horizontal_wrapped(|ui| {
+        if !item.group.is_empty() {
+            ui.label(format!("{}: {}", t.get("Group"), item.group));
+        }
+        if !item.platform.is_empty() {
+            ui.label(format!("{}: {}", t.get("Platform"), item.platform));
+        }
+        for tag in &item.tags {
+            ui.label(RichText::new(format!("#{}", tag)).weak());
+        }
+    });
+}
+
+/// 单行输入，旁边的下拉框可以选择已有的值
+fn text_with_options(ui: &mut Ui, id: &str, text: &mut String, options: &[String]) {
+    TextEdit::singleline(text).desired_width(120.0).ui(ui);
+    if options.is_empty() {
+        return;
+    }
+    ComboBox::from_id_source(id)
+        .selected_text("")
+        .width(20.0)
+        .show_ui(ui, |ui| {
+            for option in options {
+                ui.selectable_value(text, option.to_string(), option);
+            }
+        });
+}
+
+/// 备份列表中的置顶、星级、标题和备注
+fn backup_labels(ui: &mut Ui, backup: &Backup) -> Response {
+    ui.horizontal(|ui| {
+        if backup.pinned {
+            ui.label("📌");
+        }
+        if backup.rating > 0 {
+            ui.colored_label(Color32::GOLD, "★".repeat(backup.rating as usize));
+        }
+        if !backup.screenshot.is_empty() {
+            ui.label("🖼");
+        }
+        if !backup.title.is_empty() {
+            ui.strong(backup.title.to_string());
+        }
+        ui.label(backup.remark.to_string());
+    })
+    .response
+}
+
+/// 鼠标悬停时显示的笔记、游玩时间和截图
+fn backup_details(ui: &mut Ui, t: &Translator, backup: &Backup) {
+    if !backup.title.is_empty() {
+        ui.strong(backup.title.to_string());
+    }
+    if !backup.remark.is_empty() {
+        ui.label(backup.remark.to_string());
+    }
+    if !backup.notes.is_empty() {
+        ui.separator();
+        ui.label(backup.notes.to_string());
+    }
+    if let Some(playtime) = backup.playtime {
+        ui.label(format!(
+            "{}: {}h {}m",
+            t.get("Playtime"),
+            playtime / 60,
+            playtime % 60
+        ));
+    }
+    if !backup.screenshot.is_empty() {
+        ui.add(
+            Image::new(screenshot::uri(&backup.screenshot))
+                .max_size(Vec2::new(320.0, 180.0))
+                .maintain_aspect_ratio(true),
+        );
+    }
+}
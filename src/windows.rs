@@ -1,16 +1,24 @@
 use eframe::egui::{
-    Align2, Context, CursorIcon, Label, Pos2, ScrollArea, TextEdit, TextStyle, Vec2, Widget, Window,
+    Align, Align2, Color32, ComboBox, Context, CursorIcon, Image, Key, Label, Layout, Pos2,
+    RichText, ScrollArea, Sense, TextEdit, TextStyle, Ui, Vec2, Widget, Window,
 };
-use egui_extras::{Size, StripBuilder};
+use egui_extras::{Column, Size, StripBuilder, TableBuilder};
+use rfd::FileDialog;
 
 // use std::io::Write;
 // use zip::{write::SimpleFileOptions, ZipWriter};
 
 use crate::{
-    backup::{self, BackupData},
+    backup::{Backup, MAX_RATING},
+    bundle::{ImportMode, Manifest},
+    changelog::{ChangeSummary, Diff, DiffKind},
     data::{FileType, SaveItem},
-    entity::{AutoBackup, Command},
-    style,
+    entity::{AutoBackup, Command, Navigation},
+    filehistory::{FileHistory, FileVersion},
+    gamedb::Detected,
+    gitrepo, profile,
+    rescan::{Found, RescanReport},
+    screenshot, style, util,
 };
 
 pub trait ShowConfirm {
@@ -49,7 +57,7 @@ impl ShowConfirmDelBackup {
 #[derive(Default)]
 pub struct ShowConfirmOverwriteBackup {
     show: bool,
-    pub datas: Vec<BackupData>,
+    pub backup: Option<Backup>,
     pub pos: Option<Pos2>,
 }
 impl ShowConfirm for ShowConfirmOverwriteBackup {
@@ -64,10 +72,10 @@ impl ShowConfirm for ShowConfirmOverwriteBackup {
     }
 }
 impl ShowConfirmOverwriteBackup {
-    pub fn new(datas: Vec<BackupData>, pos: Option<Pos2>) -> Self {
+    pub fn new(backup: Backup, pos: Option<Pos2>) -> Self {
         Self {
             show: false,
-            datas,
+            backup: Some(backup),
             pos,
         }
     }
@@ -100,9 +108,297 @@ impl ShowConfirmTotoallyDel {
     }
 }
 
+#[derive(Default)]
+pub struct ShowConfirmPurgeTrash {
+    show: bool,
+    pub id: String,
+    pub pos: Option<Pos2>,
+}
+impl ShowConfirm for ShowConfirmPurgeTrash {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+impl ShowConfirmPurgeTrash {
+    pub fn new(id: String, pos: Option<Pos2>) -> Self {
+        Self {
+            show: false,
+            id,
+            pos,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShowExport {
+    show: bool,
+    pub id: String,
+    pub name: String,
+    pub backups: Vec<(Backup, bool)>, // 备份以及是否选中
+}
+impl ShowConfirm for ShowExport {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+impl ShowExport {
+    pub fn new(save_item: &SaveItem) -> Self {
+        Self {
+            show: false,
+            id: save_item.id.to_string(),
+            name: save_item.name.to_string(),
+            backups: save_item
+                .backups
+                .iter()
+                .map(|backup| (backup.clone(), true))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShowConfirmImport {
+    show: bool,
+    pub path: String,
+    pub name: String,
+    pub conflicts: Vec<String>,
+    pub same_name: bool, // 有同名存档时可以合并
+}
+impl ShowConfirm for ShowConfirmImport {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+impl ShowConfirmImport {
+    pub fn new(path: String, manifest: Manifest, conflicts: Vec<String>) -> Self {
+        let name = manifest.item.name;
+        Self {
+            show: false,
+            path,
+            same_name: conflicts.iter().any(|c| c.starts_with("name")),
+            name,
+            conflicts,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShowRescan {
+    show: bool,
+    pub report: RescanReport,
+}
+impl ShowConfirm for ShowRescan {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+impl ShowRescan {
+    pub fn new(report: RescanReport) -> Self {
+        Self {
+            show: false,
+            report,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShowConfirmSwitchProfile {
+    show: bool,
+    pub profile: String, // 目标配置
+    pub pos: Option<Pos2>,
+}
+impl ShowConfirm for ShowConfirmSwitchProfile {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+impl ShowConfirmSwitchProfile {
+    pub fn new(profile: String, pos: Option<Pos2>) -> Self {
+        Self {
+            show: false,
+            profile,
+            pos,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShowGit {
+    show: bool,
+    pub backup: Option<Backup>,
+    pub blame: bool,                          // 为 false 时查看修改
+    pub files: Vec<String>,                   // 备份中的文件
+    pub file: String,                         // 逐行记录的文件
+    pub text: Option<Result<String, String>>, // 为空时重新读取
+}
+impl ShowConfirm for ShowGit {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+impl ShowGit {
+    pub fn new(backup: Backup, blame: bool) -> Self {
+        let files = gitrepo::files(&backup).unwrap_or_default();
+        Self {
+            show: false,
+            backup: Some(backup),
+            blame,
+            file: files.first().cloned().unwrap_or_default(),
+            files,
+            text: None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShowFileHistory {
+    show: bool,
+    pub id: String,
+    pub name: String,
+    pub path: String,                                 // 查看历史的文件
+    pub files: Vec<String>,                           // 监听路径中现有的文件
+    pub history: Option<Result<FileHistory, String>>, // 为空时还没有查询
+    pub confirm: String,                              // 等待确认恢复的备份
+}
+impl ShowConfirm for ShowFileHistory {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+#[derive(Default)]
+pub struct ShowItemHistory {
+    show: bool,
+    pub id: String,
+    pub name: String,
+    pub changes: Option<Result<Vec<ChangeSummary>, String>>, // 为空时还没有查询
+    pub confirm: String,                                     // 等待确认撤销的修改
+}
+impl ShowConfirm for ShowItemHistory {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+impl ShowItemHistory {
+    pub fn new(save_item: &SaveItem) -> Self {
+        Self {
+            id: save_item.id.to_string(),
+            name: save_item.name.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl ShowFileHistory {
+    pub fn new(save_item: &SaveItem) -> Self {
+        let mut files = vec![];
+        for monitor in &save_item.monitors {
+            match monitor.backup_type {
+                FileType::File => files.push(monitor.path.to_string()),
+                FileType::Folder => {
+                    let mut paths = vec![];
+                    let _ = util::get_files(&monitor.path, &mut paths);
+                    files.extend(
+                        paths
+                            .into_iter()
+                            .filter(|path| std::path::Path::new(path).is_file()),
+                    );
+                }
+            }
+        }
+        files.sort();
+        Self {
+            id: save_item.id.to_string(),
+            name: save_item.name.to_string(),
+            files,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShowScanGames {
+    show: bool,
+    pub games: Vec<(Detected, bool)>, // 扫描到的游戏以及是否选中
+}
+impl ShowConfirm for ShowScanGames {
+    fn close(&mut self) {
+        self.show = false;
+    }
+    fn show(&mut self) {
+        self.show = true;
+    }
+    fn is_show(&self) -> bool {
+        self.show
+    }
+}
+impl ShowScanGames {
+    pub fn new(games: Vec<Detected>) -> Self {
+        Self {
+            show: false,
+            // 默认选中还没有存档的游戏
+            games: games
+                .into_iter()
+                .map(|game| {
+                    let selected = !game.exists;
+                    (game, selected)
+                })
+                .collect(),
+        }
+    }
+}
+
 impl AutoBackup {
     pub fn form_set_backup_remark(&mut self, ctx: &Context) {
         let mut is_open = self.control.show_backup_remark >= 0;
+        let theme = &self.setting.get_theme();
         Window::new(self.t.get("Remark"))
             .title_bar(false)
             .movable(true)
@@ -111,24 +407,114 @@ impl AutoBackup {
             .auto_sized()
             .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
             .show(ctx, |ui| {
-                let title = "Please input Remark";
-
-                ui.strong(self.t.get(title));
+                ui.strong(self.t.get("Backup Details"));
 
                 if let Ok(mut active) = self.active.try_lock() {
-                    if ui
-                        .text_edit_multiline(
-                            &mut active.backups[self.control.show_backup_remark as usize].remark,
-                        )
-                        .changed()
-                    {
+                    let id = active.id.to_string();
+                    let index = self.control.show_backup_remark as usize;
+                    let Some(backup) = active.backups.get_mut(index) else {
+                        return;
+                    };
+                    let mut changed = false;
+                    let label_size = Vec2::new(60.0, 10.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_sized(label_size, Label::new(self.t.get("Title")));
+                        changed |= ui.text_edit_singleline(&mut backup.title).changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_sized(label_size, Label::new(self.t.get("Rating")));
+                        for star in 1..=MAX_RATING {
+                            let text = if star <= backup.rating { "★" } else { "☆" };
+                            if ui
+                                .selectable_label(false, RichText::new(text).color(Color32::GOLD))
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                // 再次点击当前星级时清除
+                                backup.rating = if backup.rating == star { 0 } else { star };
+                                changed = true;
+                            }
+                        }
+                        changed |= ui.checkbox(&mut backup.pinned, self.t.get("Pin")).changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_sized(label_size, Label::new(self.t.get("Remark")));
+                        changed |= ui.text_edit_multiline(&mut backup.remark).changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_sized(label_size, Label::new(self.t.get("Notes")));
+                        changed |= TextEdit::multiline(&mut backup.notes)
+                            .desired_rows(5)
+                            .ui(ui)
+                            .changed();
+                    });
+                    if let Some(playtime) = backup.playtime {
+                        ui.horizontal(|ui| {
+                            ui.add_sized(label_size, Label::new(self.t.get("Playtime")));
+                            ui.label(format!("{}h {}m", playtime / 60, playtime % 60));
+                        });
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.add_sized(label_size, Label::new(self.t.get("Screenshot")));
+                        if style::btn_primary(self.t.get("Select"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("Image", &screenshot::EXTENSIONS)
+                                .pick_file()
+                            {
+                                let path = path.display().to_string();
+                                match screenshot::attach(&id, &backup.id, &path) {
+                                    Ok(path) => {
+                                        util::delete_file(&backup.screenshot);
+                                        backup.screenshot = path;
+                                        changed = true;
+                                    }
+                                    Err(e) => {
+                                        self.toasts.error(e);
+                                    }
+                                }
+                            }
+                        }
+                        if !backup.screenshot.is_empty() {
+                            if style::btn_info(self.t.get("Open"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                let _ = open::that(&backup.screenshot);
+                            }
+                            if style::btn_waring(self.t.get("Remove"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                util::delete_file(&backup.screenshot);
+                                backup.screenshot.clear();
+                                changed = true;
+                            }
+                        }
+                    });
+                    if !backup.screenshot.is_empty() {
+                        ui.add(
+                            Image::new(screenshot::uri(&backup.screenshot))
+                                .max_size(Vec2::new(320.0, 180.0))
+                                .maintain_aspect_ratio(true),
+                        );
+                    }
+
+                    if changed {
+                        let backup = backup.clone();
                         if let Ok(mut data) = self.data.try_lock() {
-                            data.set_monitor(active.id.to_string(), active.clone());
+                            data.set_backup(&id, backup);
                         }
                     }
                 }
 
-                let theme = &self.setting.get_theme();
                 if style::btn_info(self.t.get("Close"), theme)
                     .ui(ui)
                     .on_hover_cursor(CursorIcon::PointingHand)
@@ -229,13 +615,31 @@ impl AutoBackup {
                     .on_hover_cursor(CursorIcon::PointingHand)
                     .clicked()
                 {
-                    let mut results = vec![];
-                    backup::overwrite(
-                        &self.control.show_confirm_overwrite_backup.datas,
-                        &mut results,
-                    );
-                    self.package_toasts(results);
-                    self.control.show_confirm_overwrite_backup.close();
+                    // 钩子和复制文件可能很久，交给 command_proc 执行，存档和钩子从 data 中读取
+                    let id = match self.active.try_lock() {
+                        Ok(active) => Some(active.id.to_string()),
+                        Err(_) => None,
+                    };
+                    let backup = &self.control.show_confirm_overwrite_backup.backup;
+                    match (id, backup.as_ref().map(|backup| backup.id.to_string())) {
+                        (Some(id), Some(backup_id)) => {
+                            self.request(
+                                "restore",
+                                |reply| Command::Restore {
+                                    id,
+                                    backup_id,
+                                    reply: Some(reply),
+                                },
+                                |app, result| app.toast_result("Overwrite Success", result),
+                            );
+                            self.control.show_confirm_overwrite_backup.close();
+                        }
+                        (None, _) => {
+                            self.toasts
+                                .warning(self.t.get("Operation is too fast. Try again later."));
+                        }
+                        _ => self.control.show_confirm_overwrite_backup.close(),
+                    }
                 }
                 if style::btn_info(self.t.get("Cancel"), &self.setting.get_theme())
                     .ui(ui)
@@ -270,7 +674,7 @@ impl AutoBackup {
         window.show(ctx, |ui| {
             let title = "Confirm to delete?";
             ui.strong(self.t.get(title));
-            ui.label(self.t.get("This operation cannot be recovered"));
+            ui.label(self.t.get("The backup will be moved to the trash"));
             ui.horizontal(|ui| {
                 if style::btn_danger(self.t.get("Confirm"), &self.setting.get_theme())
                     .ui(ui)
@@ -280,6 +684,7 @@ impl AutoBackup {
                     if let Err(_) = self.command.clone().unwrap().send(Command::DeleteBackup {
                         id: self.active.try_lock().unwrap().id.to_string(),
                         backup_id: self.control.show_confirm_del_backup.id.to_string(),
+                        reply: None,
                     }) {
                         self.toasts.error(self.t.get("Delete error"));
                     } else {
@@ -316,7 +721,10 @@ impl AutoBackup {
         window.show(ctx, |ui| {
             let title = "Confirm to delete?";
             ui.strong(self.t.get(title));
-            ui.label(self.t.get("This operation cannot be recovered"));
+            ui.label(
+                self.t
+                    .get("The save item and all its backups will be moved to the trash"),
+            );
 
             ui.label(
                 self.control
@@ -365,4 +773,952 @@ impl AutoBackup {
             });
         });
     }
+
+    pub fn confirm_purge_trash(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_confirm_purge_trash.is_show();
+
+        let mut window = Window::new(self.t.get("confirm_purge_trash"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .auto_sized();
+
+        if self.control.show_confirm_purge_trash.is_show() {
+            if let Some(pos) = self.control.show_confirm_purge_trash.pos {
+                window = window.fixed_pos(pos);
+            }
+        }
+
+        window.show(ctx, |ui| {
+            let title = "Confirm to delete?";
+            ui.strong(self.t.get(title));
+            ui.label(self.t.get("This operation cannot be recovered"));
+            ui.horizontal(|ui| {
+                if style::btn_danger(self.t.get("Confirm"), &self.setting.get_theme())
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    let id = self.control.show_confirm_purge_trash.id.to_string();
+                    self.request(
+                        "purge_trash",
+                        |reply| Command::PurgeTrash {
+                            id,
+                            reply: Some(reply),
+                        },
+                        |app, result| {
+                            match result {
+                                Ok(_) => {
+                                    app.toasts.success(app.t.get("Delete Success"));
+                                }
+                                Err(e) => {
+                                    app.toasts.error(e);
+                                }
+                            }
+                            app.control.trash = None;
+                        },
+                    );
+                    self.control.show_confirm_purge_trash.close();
+                }
+                if style::btn_info(self.t.get("Cancel"), &self.setting.get_theme())
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.control.show_confirm_purge_trash.close();
+                }
+            });
+        });
+    }
+
+    pub fn export_window(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_export.is_show();
+        let theme = &self.setting.get_theme();
+
+        Window::new(self.t.get("Export"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .max_size(Vec2::new(450.0, 450.0))
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.strong(format!(
+                    "{} {}",
+                    self.t.get("Export"),
+                    self.control.show_export.name
+                ));
+                ui.label(self.t.get("Select the backups to export"));
+
+                let body_text_size = TextStyle::Body.resolve(ui.style()).size;
+                StripBuilder::new(ui)
+                    .size(Size::remainder().at_most(350.0))
+                    .size(Size::exact(body_text_size))
+                    .vertical(|mut stript| {
+                        stript.cell(|ui| {
+                            ScrollArea::vertical().show(ui, |ui| {
+                                for (backup, selected) in &mut self.control.show_export.backups {
+                                    let time = util::format_time(backup.time).unwrap_or_default();
+                                    ui.checkbox(selected, format!("{} {}", time, backup.remark));
+                                }
+                            });
+                        });
+                        stript.cell(|ui| {
+                            ui.horizontal(|ui| {
+                                // 导出完成前不能再次导出
+                                if self.is_pending("export") {
+                                    ui.spinner();
+                                    ui.label(self.t.get("Exporting..."));
+                                } else if style::btn_primary(self.t.get("Export"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    self.export();
+                                }
+                                if style::btn_info(self.t.get("Cancel"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    self.control.show_export.close();
+                                }
+                            });
+                        });
+                    });
+            });
+    }
+
+    fn export(&mut self) {
+        let export = &self.control.show_export;
+        let backup_ids: Vec<String> = export
+            .backups
+            .iter()
+            .filter(|(_, selected)| *selected)
+            .map(|(backup, _)| backup.id.to_string())
+            .collect();
+        if backup_ids.is_empty() {
+            self.toasts.warning(self.t.get("Please select backups"));
+            return;
+        }
+        let Some(path) = FileDialog::new()
+            .add_filter("zip", &["zip"])
+            .set_file_name(format!("{}.zip", export.name))
+            .save_file()
+        else {
+            return;
+        };
+        let id = export.id.to_string();
+        self.request(
+            "export",
+            |reply| Command::Export {
+                id,
+                backup_ids,
+                path: path.display().to_string(),
+                reply: Some(reply),
+            },
+            |app, result| match result {
+                Ok(_) => {
+                    app.toasts.success(app.t.get("Export Success"));
+                    app.control.show_export.close();
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                }
+            },
+        );
+    }
+
+    pub fn confirm_import(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_confirm_import.is_show();
+        let theme = &self.setting.get_theme();
+
+        Window::new(self.t.get("confirm_import"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .auto_sized()
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.strong(format!(
+                    "{} {}",
+                    self.t.get("Import"),
+                    self.control.show_confirm_import.name
+                ));
+                ui.label(self.t.get("Conflict with existing save items"));
+                for conflict in &self.control.show_confirm_import.conflicts {
+                    ui.label(conflict.to_string());
+                }
+
+                ui.horizontal(|ui| {
+                    let path = self.control.show_confirm_import.path.to_string();
+                    if style::btn_primary(self.t.get("Import as New"), theme)
+                        .ui(ui)
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.import(path.to_string(), ImportMode::Rename);
+                        self.control.show_confirm_import.close();
+                    }
+                    if self.control.show_confirm_import.same_name
+                        && style::btn_waring(self.t.get("Merge"), theme)
+                            .ui(ui)
+                            .on_hover_text(
+                                self.t
+                                    .get("Add the backups to the save item with the same name"),
+                            )
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                    {
+                        self.import(path, ImportMode::Merge);
+                        self.control.show_confirm_import.close();
+                    }
+                    if style::btn_info(self.t.get("Cancel"), theme)
+                        .ui(ui)
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.control.show_confirm_import.close();
+                    }
+                });
+            });
+    }
+
+    pub fn rescan_window(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_rescan.is_show();
+        let theme = &self.setting.get_theme();
+
+        Window::new(self.t.get("Rescan Storage"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .max_size(Vec2::new(600.0, 450.0))
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                let report = self.control.show_rescan.report.clone();
+                ui.strong(self.t.get("Rescan Storage"));
+                if report.is_clean() {
+                    ui.label(self.t.get("Backup folders and records are consistent"));
+                }
+
+                let body_text_size = TextStyle::Body.resolve(ui.style()).size;
+                StripBuilder::new(ui)
+                    .size(Size::remainder().at_most(350.0))
+                    .size(Size::exact(body_text_size))
+                    .vertical(|mut stript| {
+                        stript.cell(|ui| {
+                            ScrollArea::vertical().show(ui, |ui| {
+                                let sections: [(&str, &Vec<Found>); 5] = [
+                                    ("Folders without records", &report.adopted),
+                                    ("Records with moved folders", &report.relinked),
+                                    ("Records without contents", &report.rebuilt),
+                                    ("Folders without save items", &report.recovered_items),
+                                    ("Records without folders", &report.dangling),
+                                ];
+                                for (title, founds) in sections {
+                                    if founds.is_empty() {
+                                        continue;
+                                    }
+                                    ui.separator();
+                                    ui.strong(format!("{} ({})", self.t.get(title), founds.len()));
+                                    for found in founds {
+                                        ui.label(format!("{}  {}", found.item_name, found.folder));
+                                    }
+                                }
+                                if !report.orphan_folders.is_empty() {
+                                    ui.separator();
+                                    ui.strong(format!(
+                                        "{} ({})",
+                                        self.t.get("Unrecognized folders"),
+                                        report.orphan_folders.len()
+                                    ));
+                                    for folder in &report.orphan_folders {
+                                        ui.label(folder.to_string());
+                                    }
+                                }
+                            });
+                        });
+                        stript.cell(|ui| {
+                            ui.horizontal(|ui| {
+                                if !report.applied
+                                    && report.can_apply()
+                                    && style::btn_waring(self.t.get("Apply"), theme)
+                                        .ui(ui)
+                                        .on_hover_text(
+                                            self.t.get("Adopt the folders and repair the records"),
+                                        )
+                                        .on_hover_cursor(CursorIcon::PointingHand)
+                                        .clicked()
+                                {
+                                    self.rescan(true);
+                                }
+                                if style::btn_info(self.t.get("Close"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    self.control.show_rescan.close();
+                                }
+                            });
+                        });
+                    });
+            });
+    }
+
+    pub fn scan_games_window(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_scan_games.is_show();
+        let theme = &self.setting.get_theme();
+
+        Window::new(self.t.get("Scan for Games"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .max_size(Vec2::new(600.0, 450.0))
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.strong(self.t.get("Scan for Games"));
+                if self.control.show_scan_games.games.is_empty() {
+                    ui.label(self.t.get("No installed games found in the game database"));
+                } else {
+                    ui.label(self.t.get("Select the games to create save items"));
+                }
+
+                let body_text_size = TextStyle::Body.resolve(ui.style()).size;
+                StripBuilder::new(ui)
+                    .size(Size::remainder().at_most(350.0))
+                    .size(Size::exact(body_text_size))
+                    .vertical(|mut stript| {
+                        stript.cell(|ui| {
+                            ScrollArea::vertical().show(ui, |ui| {
+                                for (game, selected) in &mut self.control.show_scan_games.games {
+                                    ui.separator();
+                                    ui.add_enabled_ui(!game.exists, |ui| {
+                                        ui.checkbox(
+                                            selected,
+                                            format!("{} ({})", game.name, game.platform),
+                                        )
+                                        .on_disabled_hover_text(
+                                            self.t.get("A save item for this game already exists"),
+                                        );
+                                    });
+                                    for monitor in &game.monitors {
+                                        ui.add(Label::new(monitor.path.to_string()).wrap(true));
+                                    }
+                                }
+                            });
+                        });
+                        stript.cell(|ui| {
+                            ui.horizontal(|ui| {
+                                if style::btn_primary(self.t.get("Add Selected"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    self.add_games();
+                                }
+                                if style::btn_info(self.t.get("Cancel"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    self.control.show_scan_games.close();
+                                }
+                            });
+                        });
+                    });
+            });
+    }
+
+    fn add_games(&mut self) {
+        let names: Vec<String> = self
+            .control
+            .show_scan_games
+            .games
+            .iter()
+            .filter(|(game, selected)| *selected && !game.exists)
+            .map(|(game, _)| game.name.to_string())
+            .collect();
+        if names.is_empty() {
+            self.toasts.warning(self.t.get("Please select games"));
+            return;
+        }
+        self.request(
+            "add_games",
+            |reply| Command::AddGames {
+                names,
+                reply: Some(reply),
+            },
+            |app, result| match result {
+                Ok(added) => {
+                    let count = added.as_array().map(|a| a.len()).unwrap_or_default();
+                    app.toasts
+                        .success(format!("{}: {}", app.t.get("Save items created"), count));
+                    app.control.show_scan_games.close();
+                    app.control.nav = Navigation::Manage;
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                }
+            },
+        );
+    }
+
+    pub fn confirm_switch_profile(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_confirm_switch_profile.is_show();
+        let theme = &self.setting.get_theme();
+
+        let mut window = Window::new(self.t.get("confirm_switch_profile"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .auto_sized();
+        if let Some(pos) = self.control.show_confirm_switch_profile.pos {
+            window = window.fixed_pos(pos);
+        }
+
+        window.show(ctx, |ui| {
+            let target = self.control.show_confirm_switch_profile.profile.to_string();
+            ui.strong(format!(
+                "{} {}?",
+                self.t.get("Confirm to Switch Profile"),
+                self.t.get(profile::display(&target))
+            ));
+            ui.label(self.t.get(
+                "The current files will be backed up into the current profile, then replaced by the latest backup of the target profile",
+            ));
+
+            ui.horizontal(|ui| {
+                if style::btn_waring(self.t.get("Confirm"), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.switch_profile(target);
+                    self.control.show_confirm_switch_profile.close();
+                }
+                if style::btn_info(self.t.get("Cancel"), theme)
+                    .ui(ui)
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    self.control.show_confirm_switch_profile.close();
+                }
+            });
+        });
+    }
+
+    fn switch_profile(&mut self, target: String) {
+        let mut id = String::new();
+        if let Ok(active) = self.active.try_lock() {
+            id = active.id.to_string();
+        }
+        self.request(
+            "switch_profile",
+            |reply| Command::SwitchProfile {
+                id,
+                profile: target,
+                reply: Some(reply),
+            },
+            |app, result| match result {
+                Ok(result) => {
+                    if result["restored"].is_null() {
+                        app.toasts.info(app.t.get(
+                            "The target profile has no backup yet, the current files are kept",
+                        ));
+                    }
+                    app.toasts.success(app.t.get("Profile switched"));
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                }
+            },
+        );
+    }
+
+    pub fn git_window(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_git.is_show();
+        let theme = &self.setting.get_theme();
+        let Some(backup) = self.control.show_git.backup.clone() else {
+            return;
+        };
+
+        Window::new(self.t.get("Backup History"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .max_size(Vec2::new(700.0, 500.0))
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                let show_git = &mut self.control.show_git;
+                ui.horizontal(|ui| {
+                    ui.strong(&backup.commit[..backup.commit.len().min(8)]);
+                    ui.label(backup.remark.to_string());
+                });
+                ui.horizontal(|ui| {
+                    if ui
+                        .selectable_value(&mut show_git.blame, false, self.t.get("Diff"))
+                        .changed()
+                        || ui
+                            .selectable_value(&mut show_git.blame, true, self.t.get("Blame"))
+                            .changed()
+                    {
+                        show_git.text = None;
+                    }
+                    if show_git.blame {
+                        ComboBox::from_id_source("git_blame_file")
+                            .selected_text(show_git.file.to_string())
+                            .width(300.0)
+                            .show_ui(ui, |ui| {
+                                for file in &show_git.files {
+                                    if ui
+                                        .selectable_value(
+                                            &mut show_git.file,
+                                            file.to_string(),
+                                            file,
+                                        )
+                                        .changed()
+                                    {
+                                        show_git.text = None;
+                                    }
+                                }
+                            });
+                    }
+                });
+
+                let text = show_git
+                    .text
+                    .get_or_insert_with(|| match show_git.blame {
+                        true => gitrepo::blame(&backup, &show_git.file),
+                        false => gitrepo::diff(&backup),
+                    })
+                    .clone();
+                let body_text_size = TextStyle::Body.resolve(ui.style()).size;
+                StripBuilder::new(ui)
+                    .size(Size::remainder().at_most(400.0))
+                    .size(Size::exact(body_text_size))
+                    .vertical(|mut stript| {
+                        stript.cell(|ui| {
+                            ScrollArea::both().show(ui, |ui| match &text {
+                                Ok(text) => {
+                                    ui.add(Label::new(RichText::new(text.as_str()).monospace()));
+                                }
+                                Err(e) => {
+                                    ui.colored_label(Color32::RED, e.as_str());
+                                }
+                            });
+                        });
+                        stript.cell(|ui| {
+                            if style::btn_info(self.t.get("Close"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.show_git.close();
+                            }
+                        });
+                    });
+            });
+    }
+
+    pub fn file_history_window(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_file_history.is_show();
+        let theme = &self.setting.get_theme();
+
+        Window::new(self.t.get("File History"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .max_size(Vec2::new(800.0, 500.0))
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                let show = &mut self.control.show_file_history;
+                ui.strong(format!("{} - {}", self.t.get("File History"), show.name));
+                let mut query = false;
+                ui.horizontal(|ui| {
+                    ui.label(self.t.get("File"));
+                    let input = TextEdit::singleline(&mut show.path)
+                        .hint_text(self.t.get("A file under the monitored paths"))
+                        .desired_width(360.0)
+                        .ui(ui);
+                    query = input.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                    ComboBox::from_id_source("file_history_files")
+                        .selected_text("")
+                        .width(20.0)
+                        .show_ui(ui, |ui| {
+                            for file in &show.files {
+                                if ui.selectable_label(false, file).clicked() {
+                                    show.path = file.to_string();
+                                    query = true;
+                                }
+                            }
+                        });
+                    if style::btn_primary_round(self.t.get("Search"), theme)
+                        .ui(ui)
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        query = true;
+                    }
+                });
+                if query {
+                    self.query_file_history();
+                }
+
+                let history = self.control.show_file_history.history.clone();
+                let mut restore = None;
+                let body_text_size = TextStyle::Body.resolve(ui.style()).size;
+                StripBuilder::new(ui)
+                    .size(Size::remainder().at_most(350.0))
+                    .size(Size::exact(body_text_size))
+                    .vertical(|mut stript| {
+                        stript.cell(|ui| match &history {
+                            None => {}
+                            Some(Err(e)) => {
+                                ui.colored_label(Color32::RED, e.as_str());
+                            }
+                            Some(Ok(history)) => restore = self.file_versions(ui, history),
+                        });
+                        stript.cell(|ui| {
+                            if style::btn_info(self.t.get("Close"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.show_file_history.close();
+                            }
+                        });
+                    });
+
+                if let Some(version) = restore {
+                    self.restore_file(version);
+                }
+            });
+    }
+
+    /// 当前文件和包含它的备份，返回确认恢复的版本
+    fn file_versions(&mut self, ui: &mut Ui, history: &FileHistory) -> Option<FileVersion> {
+        let theme = &self.setting.get_theme();
+        let current = history.current.as_ref();
+        let pending = self.control.show_file_history.confirm.to_string();
+        match current {
+            Some(current) => {
+                ui.label(format!(
+                    "{}: {}",
+                    self.t.get("Current File"),
+                    version_text(current)
+                ));
+            }
+            None => {
+                ui.label(self.t.get("The file does not exist now"));
+            }
+        }
+        if history.versions.is_empty() {
+            ui.label(self.t.get("No backup contains this file"));
+            return None;
+        }
+        ui.label(self.t.get(
+            "Versions with the same hash have the same content, the highlighted ones are the same as the current file",
+        ));
+        let mut restore = None;
+        let mut confirm = None;
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto().clip(true).at_least(120.0))
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto())
+            .column(Column::auto())
+            .sense(Sense::click())
+            .header(20.0, |mut header| {
+                for title in [
+                    "Bakup Time",
+                    "Remark",
+                    "Profile",
+                    "Size",
+                    "Modified",
+                    "Hash",
+                    "Operate",
+                ] {
+                    header.col(|ui| {
+                        ui.strong(self.t.get(title));
+                    });
+                }
+            })
+            .body(|mut body| {
+                for version in &history.versions {
+                    body.row(18.0, |mut row| {
+                        row.set_selected(
+                            current.is_some_and(|current| current.hash == version.hash),
+                        );
+                        row.col(|ui| {
+                            ui.label(util::format_time(version.time).unwrap_or_default());
+                        });
+                        row.col(|ui| {
+                            ui.label(version.remark.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(self.t.get(profile::display(&version.profile)));
+                        });
+                        row.col(|ui| {
+                            ui.label(util::format_size(version.size));
+                        });
+                        row.col(|ui| {
+                            ui.label(
+                                version
+                                    .modified
+                                    .and_then(util::format_time)
+                                    .unwrap_or("-".to_string()),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.monospace(&version.hash[..version.hash.len().min(8)])
+                                .on_hover_text(version.hash.to_string());
+                        });
+                        row.col(|ui| {
+                            if pending == version.backup_id {
+                                if style::btn_waring(self.t.get("Confirm"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    restore = Some(version.clone());
+                                }
+                                if style::btn_info(self.t.get("Cancel"), theme)
+                                    .ui(ui)
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    confirm = Some(String::new());
+                                }
+                            } else if style::btn_waring(self.t.get("Restore"), theme)
+                                .ui(ui)
+                                .on_hover_text(
+                                    self.t.get(
+                                        "Overwrite only this file with the version in the backup",
+                                    ),
+                                )
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                confirm = Some(version.backup_id.to_string());
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(backup_id) = confirm {
+            self.control.show_file_history.confirm = backup_id;
+        }
+        restore
+    }
+
+    fn query_file_history(&mut self) {
+        let show = &self.control.show_file_history;
+        let (id, path) = (show.id.to_string(), show.path.trim().to_string());
+        self.request(
+            "file_history",
+            |reply| Command::FileHistory { id, path, reply },
+            |app, result| {
+                let history = result
+                    .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()));
+                app.control.show_file_history.history = Some(history);
+                app.control.show_file_history.confirm.clear();
+            },
+        );
+    }
+
+    fn restore_file(&mut self, version: FileVersion) {
+        let show = &self.control.show_file_history;
+        let (id, path) = (show.id.to_string(), show.path.trim().to_string());
+        self.request(
+            "restore_file",
+            |reply| Command::RestoreFile {
+                id,
+                backup_id: version.backup_id,
+                path,
+                reply: Some(reply),
+            },
+            |app, result| match result {
+                Ok(_) => {
+                    app.toasts.success(app.t.get("File restored"));
+                    app.query_file_history();
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                    app.control.show_file_history.confirm.clear();
+                }
+            },
+        );
+    }
+
+    pub fn item_history_window(&mut self, ctx: &Context) {
+        let mut is_open = self.control.show_item_history.is_show();
+        let theme = &self.setting.get_theme();
+        if self.control.show_item_history.changes.is_none() && !self.is_pending("item_changes") {
+            self.query_item_history();
+        }
+
+        Window::new(self.t.get("Change History"))
+            .title_bar(false)
+            .open(&mut is_open)
+            .collapsible(false)
+            .max_size(Vec2::new(800.0, 500.0))
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                let show = &self.control.show_item_history;
+                ui.strong(format!("{} - {}", self.t.get("Change History"), show.name));
+                ui.label(
+                    self.t
+                        .get("Undo only reverts the fields of that change, later changes are kept"),
+                );
+                let changes = show.changes.clone();
+                let mut revert = None;
+                let body_text_size = TextStyle::Body.resolve(ui.style()).size;
+                StripBuilder::new(ui)
+                    .size(Size::remainder().at_most(380.0))
+                    .size(Size::exact(body_text_size))
+                    .vertical(|mut stript| {
+                        stript.cell(|ui| match &changes {
+                            None => {}
+                            Some(Err(e)) => {
+                                ui.colored_label(Color32::RED, e.as_str());
+                            }
+                            Some(Ok(changes)) if changes.is_empty() => {
+                                ui.label(self.t.get("No changes yet"));
+                            }
+                            Some(Ok(changes)) => revert = self.item_changes(ui, changes),
+                        });
+                        stript.cell(|ui| {
+                            if style::btn_info(self.t.get("Close"), theme)
+                                .ui(ui)
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                self.control.show_item_history.close();
+                            }
+                        });
+                    });
+
+                if let Some(change_id) = revert {
+                    self.revert_change(change_id);
+                }
+            });
+    }
+
+    /// 每次修改的时间和修改的字段，返回确认撤销的修改
+    fn item_changes(&mut self, ui: &mut Ui, changes: &[ChangeSummary]) -> Option<String> {
+        let theme = &self.setting.get_theme();
+        let pending = self.control.show_item_history.confirm.to_string();
+        let mut revert = None;
+        let mut confirm = None;
+        ScrollArea::vertical().show(ui, |ui| {
+            for change in changes {
+                ui.horizontal(|ui| {
+                    ui.strong(util::format_time(change.time).unwrap_or_default());
+                    if pending == change.id {
+                        if style::btn_waring(self.t.get("Confirm"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            revert = Some(change.id.to_string());
+                        }
+                        if style::btn_info(self.t.get("Cancel"), theme)
+                            .ui(ui)
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            confirm = Some(String::new());
+                        }
+                    } else if change.diffs.iter().any(Diff::revertible)
+                        && style::btn_waring(self.t.get("Undo"), theme)
+                            .ui(ui)
+                            .on_hover_text(
+                                self.t.get("Change these fields back to the values before"),
+                            )
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                    {
+                        confirm = Some(change.id.to_string());
+                    }
+                });
+                for diff in &change.diffs {
+                    let (color, text) = match diff.kind {
+                        DiffKind::Added => (style::rgb(80, 170, 90), format!("+ {}", diff.after)),
+                        DiffKind::Removed => (Color32::RED, format!("- {}", diff.before)),
+                        DiffKind::Changed => (
+                            style::rgb(220, 160, 60),
+                            format!("{} → {}", diff.before, diff.after),
+                        ),
+                    };
+                    ui.horizontal_wrapped(|ui| {
+                        ui.monospace(&diff.field);
+                        ui.colored_label(color, text);
+                    });
+                }
+                ui.separator();
+            }
+        });
+        if let Some(change_id) = confirm {
+            self.control.show_item_history.confirm = change_id;
+        }
+        revert
+    }
+
+    fn query_item_history(&mut self) {
+        let id = self.control.show_item_history.id.to_string();
+        let query = id.to_string();
+        self.request(
+            "item_changes",
+            |reply| Command::ItemChanges { id, reply },
+            move |app, result| {
+                // 等待期间打开了其他存档的记录时丢弃，下一帧重新读取
+                if app.control.show_item_history.id != query {
+                    return;
+                }
+                let changes = result
+                    .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()));
+                app.control.show_item_history.changes = Some(changes);
+                app.control.show_item_history.confirm.clear();
+            },
+        );
+    }
+
+    fn revert_change(&mut self, change_id: String) {
+        let id = self.control.show_item_history.id.to_string();
+        self.request(
+            "revert_change",
+            |reply| Command::RevertChange {
+                id,
+                change_id,
+                reply: Some(reply),
+            },
+            |app, result| match result {
+                Ok(_) => {
+                    app.toasts.success(app.t.get("Change undone"));
+                    app.query_item_history();
+                }
+                Err(e) => {
+                    app.toasts.error(e);
+                    app.control.show_item_history.confirm.clear();
+                }
+            },
+        );
+    }
+}
+
+/// 文件版本的大小、修改时间和哈希
+fn version_text(version: &FileVersion) -> String {
+    format!(
+        "{}  {}  {}",
+        util::format_size(version.size),
+        version
+            .modified
+            .and_then(util::format_time)
+            .unwrap_or("-".to_string()),
+        &version.hash[..version.hash.len().min(8)]
+    )
 }